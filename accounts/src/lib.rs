@@ -1,23 +1,30 @@
 use std::io::{Read, Error};
+use common::datadir::DataDir;
 use common::fileutil::new_file;
 
+/// data directory 안에서 device의 secret key가 저장되는 파일
 const ACCOUNT_FILE: &str = "device.account";
 
+fn account_path(data_dir: &DataDir) -> String {
+    data_dir.file(ACCOUNT_FILE).to_string_lossy().to_string()
+}
+
 pub struct DeviceAccount {
     pub secret_key: crypto::key::Sk,
     pub address: ethereum_types::Address
 }
 
 impl DeviceAccount {
-    pub fn new() -> Self {
+    /// 새로운 key를 만들고 data directory에 저장한다.
+    pub fn new(data_dir: &DataDir) -> Self {
         let sk = crypto::key::Sk::random();
         let address = ethereum_types::Address::from(sk.pubkey().address());
-        Self::write_account(sk.as_ref().to_vec());
+        Self::write_account(data_dir, sk.as_ref().to_vec());
         return DeviceAccount { secret_key: sk, address };
     }
 
-    pub fn read_account() -> Result<Self, ()> {
-        let mut file = common::fileutil::get_file(ACCOUNT_FILE);
+    pub fn read_account(data_dir: &DataDir) -> Result<Self, ()> {
+        let mut file = common::fileutil::get_file(account_path(data_dir).as_str());
         if file.is_err() { return Err(()); }
         let data = common::fileutil::read_file(&mut file.unwrap());
         let mut u8l32data: [u8; 32] = [0u8; 32];
@@ -31,13 +38,15 @@ impl DeviceAccount {
         return Ok(DeviceAccount { address, secret_key: sk });
     }
 
-    pub fn write_account(sk_vec: Vec<u8>) {
-        let mut file = new_file(ACCOUNT_FILE);
+    pub fn write_account(data_dir: &DataDir, sk_vec: Vec<u8>) {
+        data_dir.create().expect("could not create data directory");
+        let mut file = new_file(account_path(data_dir).as_str());
         common::fileutil::write_file(&mut file, sk_vec);
     }
 
-    pub fn store_account(&self) {
-        let mut file = new_file(ACCOUNT_FILE);
+    pub fn store_account(&self, data_dir: &DataDir) {
+        data_dir.create().expect("could not create data directory");
+        let mut file = new_file(account_path(data_dir).as_str());
         common::fileutil::write_file(&mut file, self.secret_key.as_ref().to_vec());
     }
 }
@@ -47,4 +56,19 @@ mod tests {
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn device_account_data_dir() {
+        use common::datadir::DataDir;
+        use crate::DeviceAccount;
+        let root = std::env::temp_dir().join(format!("device-account-{}", ethereum_types::H256::random()));
+        let (first, second) = (DataDir::new(root.join("a")), DataDir::new(root.join("b")));
+        assert!(DeviceAccount::read_account(&first).is_err());
+        let a = DeviceAccount::new(&first);
+        let b = DeviceAccount::new(&second);
+        assert_ne!(a.address, b.address);
+        assert_eq!(DeviceAccount::read_account(&first).unwrap().address, a.address);
+        assert_eq!(DeviceAccount::read_account(&second).unwrap().address, b.address);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
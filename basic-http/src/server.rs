@@ -5,7 +5,8 @@ use std::io::Read;
 use crate::request::{HttpRequest, RawRequest};
 use crate::response::HttpResponse;
 
-pub type Handler = fn(request: HttpRequest, response: HttpResponse);
+/// 요청을 처리하는 함수. 공유하는 상태(예: ledger)를 capture한 closure도 사용할 수 있다.
+pub type Handler = Box<dyn Fn(HttpRequest, HttpResponse) + Send + Sync>;
 
 pub struct HttpServer {
     address: SocketAddr,
@@ -31,11 +32,12 @@ impl HttpServer {
         return server;
     }
 
-    pub fn append_handler(&mut self, path: &str, handler: Handler) -> Result<(), ()> {
+    pub fn append_handler<F>(&mut self, path: &str, handler: F) -> Result<(), ()>
+        where F: Fn(HttpRequest, HttpResponse) + Send + Sync + 'static {
         if self.handlers.contains_key(path) {
             return Err(());
         }
-        self.handlers.insert(path.to_string(), handler);
+        self.handlers.insert(path.to_string(), Box::new(handler));
         return Ok(());
     }
 
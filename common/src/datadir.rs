@@ -0,0 +1,29 @@
+use std::path::{Path, PathBuf};
+
+/// 노드의 데이터(ledger 데이터베이스, device account 등)가 저장되는 디렉토리
+/// 한 프로세스에서 여러 노드를 실행할 때는 노드마다 다른 디렉토리를 사용한다.
+/// 기본값은 현재 작업 디렉토리이다.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataDir {
+    path: PathBuf,
+}
+
+impl DataDir {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        DataDir { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path { self.path.as_path() }
+
+    /// 디렉토리 안의 파일 경로
+    pub fn file(&self, name: &str) -> PathBuf { self.path.join(name) }
+
+    /// 디렉토리가 없다면 만든다.
+    pub fn create(&self) -> Result<(), ()> {
+        std::fs::create_dir_all(&self.path).map_err(|_| ())
+    }
+}
+
+impl Default for DataDir {
+    fn default() -> Self { DataDir::new(".") }
+}
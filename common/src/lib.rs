@@ -4,6 +4,7 @@ pub mod printutil;
 pub mod u256util;
 pub mod strutil;
 pub mod fileutil;
+pub mod datadir;
 
 #[cfg(test)]
 mod tests {
@@ -1,11 +1,17 @@
 use ethereum_types::{Address, H256};
+use ledger::dirty_state::DirtyStates;
 use ledger::transaction::Transaction;
+use ledger::handle::LedgerHandle;
+use common::datadir::DataDir;
 
 pub trait Engine {
     /// 트랜잭션 검증자에 대한 주소를 반환한다.
     fn author(&self) -> Address;
     /// 해당 트랜잭션으로 인한 상태 전이 해시 값을 확인한다.
-    fn verify_state_hash(&self) -> Address;
+    /// states는 트랜잭션을 실행하여 얻은 상태 변화이며 트랜잭션의 state_hash와 같아야 한다.
+    fn verify_state_hash(&self, tx: &Transaction, states: &DirtyStates) -> bool {
+        tx.state_hash == states.hash()
+    }
     /// 트랜잭션 정보를 가져온다.  PoA의 경우 리더 후보를, PoS의 경우 각 그룹의 인원을 조사한다.
     fn prepare(&self);
     /// seal은 DAG에 추가될 트랜잭션을 생성한다.
@@ -25,25 +31,44 @@ pub struct PoaEngine {
     committed_transaction: Transaction, // PoA로 합의된 트랜잭션
     dirty_state: Vec<(H256, H256)>,     // 트랜잭션으로 인해 변화된 상태값인데 필요없는 값.
     authority: bool,
+    authorities: Vec<Address>,          // 트랜잭션을 합의하는 authority들. 처음 값은 genesis에서 정해진다.
+    data_dir: DataDir,                  // 디바이스 계정이 저장된 디렉토리
+    ledger: LedgerHandle,               // RPC, P2P와 공유하는 ledger
 }
 
 impl PoaEngine {
     pub fn new(contract_address: &Address,
                transaction: Transaction,
-               dirty_state: Vec<(H256, H256)>) -> Self {
+               dirty_state: Vec<(H256, H256)>,
+               authorities: Vec<Address>,
+               data_dir: &DataDir,
+               ledger: LedgerHandle) -> Self {
         PoaEngine {
             contract_address: contract_address.clone(),
             target_transaction: Default::default(),
             committed_transaction: Default::default(),
             dirty_state,
-            authority: false
+            authority: false,
+            authorities,
+            data_dir: data_dir.clone(),
+            ledger,
         }
     }
+
+    /// genesis로 초기화된 ledger의 authority들로 engine을 만든다. 초기화되지 않은 ledger라면 None이다.
+    pub fn from_chain(contract_address: &Address, ledger: &LedgerHandle, data_dir: &DataDir) -> Option<Self> {
+        let config = ledger.read().chain_config()?;
+        Some(PoaEngine::new(contract_address, Default::default(), vec![], config.authorities, data_dir, ledger.clone()))
+    }
+
+    pub fn authorities(&self) -> &Vec<Address> { &self.authorities }
+
+    pub fn ledger(&self) -> &LedgerHandle { &self.ledger }
 }
 
 impl Engine for PoaEngine {
     fn author(&self) -> Address {
-        accounts::DeviceAccount::read_account().unwrap().address
+        accounts::DeviceAccount::read_account(&self.data_dir).unwrap().address
         // let public_key = crypto::secp256k1::recover_from_sig(
         //     &self.committed_transaction.v as i32,
         //     &self.committed_transaction.r,
@@ -57,11 +82,6 @@ impl Engine for PoaEngine {
         // }
     }
 
-    fn verify_state_hash(&self, ) -> Address {
-        let str_state_hash = String::new();
-
-    }
-
     fn prepare(&self) {
         // do nothing
         todo!()
@@ -92,11 +112,6 @@ impl Engine for PosEngine {
         todo!()
     }
 
-    /// 트랜잭션에 포함된 상태의 해시 값을 검증하는 메서드
-    fn verify_state_hash(&self) -> Address {
-        todo!()
-    }
-
     /// 트랜잭션을 합의하기 위해 필요한 내용들을 초기화 하는 메서드
     fn prepare(&self) {
         todo!()
@@ -1,5 +1,5 @@
 pub mod engine;
-mod cache;
+pub mod milestone;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,51 @@
+use ethereum_types::{Address, H256};
+use ledger::ledger::Ledger;
+use ledger::handle::LedgerHandle;
+use ledger::milestone::{Milestone, MilestoneError};
+use crypto::key::Sk;
+
+/// authority가 주기적으로 DAG의 tip들을 참조하는 마일스톤을 발행한다.
+/// 마일스톤에 참조된 트랜잭션과 그 조상들은 확정된 것으로 본다.
+pub struct MilestoneProducer {
+    interval: u64,                  // 마일스톤 발행 주기 (초)
+    authorities: Vec<Address>,
+}
+
+impl MilestoneProducer {
+    pub fn new(interval: u64, authorities: Vec<Address>) -> Self {
+        MilestoneProducer { interval, authorities }
+    }
+
+    /// genesis에서 정해진 authority들이 마일스톤을 발행한다. 초기화되지 않은 ledger라면 None이다.
+    pub fn from_chain(interval: u64, ledger: &Ledger) -> Option<Self> {
+        Some(MilestoneProducer::new(interval, ledger.chain_config()?.authorities))
+    }
+
+    pub fn authorities(&self) -> &Vec<Address> { &self.authorities }
+
+    /// 마지막 마일스톤 이후 interval이 지났고 그 사이 DAG의 tip이 바뀌었다면 true
+    pub fn should_issue(&self, ledger: &Ledger, now: u64) -> bool {
+        let tips = ledger.get_dag().tips();
+        if tips.is_empty() { return false; }
+        return match ledger.latest_milestone() {
+            None => { true }
+            Some(latest) => { now >= latest.timestamp.saturating_add(self.interval) && latest.tips != tips }
+        };
+    }
+
+    /// 현재 DAG의 tip과 state root로 다음 마일스톤을 만들어 서명하고 저장한다.
+    pub fn issue(&self, ledger: &mut Ledger, sk: &Sk, now: u64) -> Result<H256, MilestoneError> {
+        let height = ledger.latest_milestone().map_or(0, |latest| latest.height + 1);
+        let mut milestone = Milestone::new(height, ledger.get_dag().tips(), ledger.state_root(), now);
+        milestone.sign(sk);
+        ledger.add_milestone(&milestone, &self.authorities)
+    }
+
+    /// 공유하는 ledger에 마일스톤을 발행할 때라면 발행한다. 확인과 발행 사이에 다른 쓰기가 끼어들지 않도록 write lock을 잡는다.
+    /// 발행하지 않았다면 None이다.
+    pub fn tick(&self, ledger: &LedgerHandle, sk: &Sk, now: u64) -> Option<Result<H256, MilestoneError>> {
+        let mut ledger = ledger.write();
+        if !self.should_issue(&ledger, now) { return None; }
+        Some(self.issue(&mut ledger, sk, now))
+    }
+}
@@ -54,11 +54,12 @@ impl Pk {
 
     pub fn to_vec(&self) -> Vec<u8> { self.value.to_vec() }
 
+    /// 0x04 prefix를 제외한 공개키의 keccak256 hash 중 뒤의 20 bytes
     pub fn address(&self) -> [u8;20] {
-        let k = crate::hash::keccak256(self.value.as_ref());
+        let k = crate::hash::keccak256(&self.value[1..]);
         let mut raw_address = [0u8;20];
         for index in 12..32 {
-            raw_address[index] = k[index];
+            raw_address[index - 12] = k[index];
         }
         return raw_address;
     }
@@ -36,6 +36,15 @@ pub fn recover_from_sig(rec_id: i32, comp_sig: [u8; 64], hmsg: &[u8; 32]) -> Pk
     return pk;
 }
 
+/// 잘못된 서명일 경우 panic 대신 Err를 반환하는 recover_from_sig
+pub fn try_recover_from_sig(rec_id: i32, comp_sig: &[u8; 64], hmsg: &[u8; 32]) -> Result<Pk, ()> {
+    let id = RecoveryId::from_i32(rec_id).map_err(|_| ())?;
+    let msg = Message::from_slice(hmsg).map_err(|_| ())?;
+    let signature = RecoverableSignature::from_compact(comp_sig, id).map_err(|_| ())?;
+    let public_key = Secp256k1::new().recover(&msg, &signature).map_err(|_| ())?;
+    return Ok(Pk::from(public_key.serialize_uncompressed()));
+}
+
 pub fn recover_from_vrs(hmsg: &[u8; 32], v: i32, r: [u8; 32], s: [u8; 32]) -> Pk {
     let id = RecoveryId::from_i32(v).unwrap();
     let msg = Message::from_slice(hmsg).unwrap();
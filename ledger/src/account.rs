@@ -1,9 +1,12 @@
-use ethereum_types::{H256, Address, U256};
-use crate::table::{Table, Container};
-use rusqlite::{Connection, Statement};
-use std::io::Error;
-use rlp::Encodable;
-use std::collections::HashMap;
+use std::sync::Arc;
+use ethereum_types::{H256, Address};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use crate::backend::{Backend, WriteBatch};
+
+/// keccak256(address) -> rlp(AccountNode)
+pub const ACCOUNT_COLUMN: &str = "account";
+/// address || key -> value
+pub const STORAGE_COLUMN: &str = "storage";
 
 // pub struct AccountTrie {
 //     pub value: [AccountNode]
@@ -30,22 +33,58 @@ impl AccountState {
 
 pub struct AccountNode {
     pub key: H256,
-    pub nonce: H256,
+    pub nonce: u64,             // 해당 account가 전송한 트랜잭션의 수
     pub storage_root: H256,
     pub codehash: Vec<u8>,
 }
 
+impl AccountNode {
+    /// world state trie에 저장되는 값이며 rlp([nonce, storage_root, keccak256(code)])이다.
+    pub fn trie_value(&self) -> Vec<u8> {
+        let storage_root = match self.storage_root.is_zero() {
+            true => { crate::trie::EMPTY_ROOT }
+            false => { self.storage_root.clone() }
+        };
+        let mut stream = rlp::RlpStream::new_list(3);
+        stream.append(&self.nonce);
+        stream.append(&storage_root);
+        stream.append(&H256::from(crypto::hash::keccak256(self.codehash.as_slice())));
+        stream.out().to_vec()
+    }
+}
+
 impl Default for AccountNode {
     fn default() -> Self {
         AccountNode {
             key: H256::zero(),  // all nodes in secure trie get its own hash
-            nonce: H256::zero(),
+            nonce: 0,
             storage_root: H256::zero(),
             codehash: vec![],
         }
     }
 }
 
+/// backend에 저장되는 값이며 key는 column의 key로 저장되므로 포함하지 않는다.
+impl Encodable for AccountNode {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.nonce);
+        s.append(&self.storage_root);
+        s.append(&self.codehash);
+    }
+}
+
+impl Decodable for AccountNode {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(AccountNode {
+            key: H256::zero(),
+            nonce: rlp.val_at(0)?,
+            storage_root: rlp.val_at(1)?,
+            codehash: rlp.val_at(2)?,
+        })
+    }
+}
+
 
 pub struct AccountStorage {
     pub key: H256,
@@ -58,297 +97,122 @@ impl AccountStorage {
     }
 }
 
-pub struct AccountStorageTable {
-    pub connection: Connection,
-}
-
-impl AccountStorageTable {
-    pub fn new(contract_address: &Address) -> Self {
-        let str_address = hex::encode(contract_address.as_bytes());
-        let path = format!("{}.db", str_address);
-        AccountStorageTable { connection: Connection::open(path).unwrap() }
-    }
-}
-
-impl Default for AccountStorageTable {
-    fn default() -> Self {
-        let a = Address::zero();
-        AccountStorageTable::new(&a)
-    }
-}
-
-impl Table for AccountStorageTable {
-    fn get_table_name(&self) -> String {
-        "storage".to_string()
-    }
-
-    fn get_create_table_query(&self) -> String {
-        "CREATE TABLE IF NOT EXISTS storage(\
-            key BLOB,\
-            value BLOB)"
-            .to_string()
-    }
-
-    fn get_drop_table_query(&self) -> String {
-        "DROP TABLE storage".to_string()
-    }
-
-    fn get_select_query(&self, where_type: &str) -> String {
-        format!("SELECT * FROM storage WHERE {} = ?", where_type)
-    }
-
-    fn get_insert_query(&self) -> String {
-        "INSERT INTO storage (key, value) VALUES (?, ?)".to_string()
-    }
-
-    fn get_update_query(&self) -> String {
-        "UPDATE storage SET key = ?, value = ? WHERE key = ?".to_string()
-    }
-
-    fn get_delete_query(&self, where_type: &str) -> String {
-        format!("DELETE FROM storage WHERE {} = ?", where_type)
-    }
-
-    fn make_statement(&self, query: &str) -> rusqlite::Statement {
-        self.connection.prepare(query).unwrap()
-    }
-}
-
+/// 컨트랙트 하나의 storage. 값이 없는 key는 0이다.
 pub struct StorageTableManager {
-    table: Vec<Box<dyn std::any::Any>>,
-    pub database_name: String,
-    pub table_name: String,
+    backend: Arc<dyn Backend>,
     pub contract_address: Address,
 }
 
-impl Container for StorageTableManager {
-    fn initialize(&self) {
-        let query = self.get_table().get_create_table_query();
-        let mut stmt = self.get_table().make_statement(query.as_str());
-        stmt.execute([]);
-    }
-}
-
 impl StorageTableManager {
-    pub fn new(contract_address: &Address) -> Self {
-        let str_address = hex::encode(contract_address.as_bytes());
-        let mut mngr = StorageTableManager {
-            table: vec![],
-            database_name: str_address,
-            table_name: "storage".to_string(),
-            contract_address: contract_address.clone(),
-        };
-        mngr.table.push(Box::new(AccountStorageTable::new(contract_address)));
-        return mngr;
+    pub fn new(backend: Arc<dyn Backend>, contract_address: &Address) -> Self {
+        StorageTableManager { backend, contract_address: contract_address.clone() }
     }
 
-    pub fn get_table(&self) -> &AccountStorageTable {
-        self.table.get(0).unwrap().downcast_ref::<AccountStorageTable>().unwrap()
+    fn storage_key(&self, key: &H256) -> Vec<u8> {
+        [self.contract_address.as_bytes(), key.as_bytes()].concat()
     }
 
     pub fn get_storage_value(&self, key: &H256) -> AccountStorage {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<AccountStorageTable>().unwrap();
-        let query = tbl.get_select_query("key");
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let rows = stmt
-            .query_map([key.as_bytes()], |row| {
-                let keyvec: Vec<u8> = row.get(0)?;
-                let valuevec: Vec<u8> = row.get(1)?;
-
-                Ok(AccountStorage {
-                    key: H256::from(common::vecutil::copy_to_bytes32(&keyvec)),
-                    value: H256::from(common::vecutil::copy_to_bytes32(&valuevec)),
-                })
-            });
-        AccountStorage { key: H256::zero(), value: H256::zero() }
-    }
-
-    pub fn insert_storage_value(&self, account_storage: &AccountStorage) -> Result<(), ()> {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<AccountStorageTable>().unwrap();
-        let query = tbl.get_insert_query();
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let cnt = stmt.execute([
-            account_storage.key.as_bytes(),
-            account_storage.value.as_bytes()
-        ]);
-        Ok(())
-    }
-
-    pub fn update_storage_value(&self, account_storage: &AccountStorage) -> std::result::Result<(), ()> {
-        let storage = self.get_storage_value(&account_storage.key);
-        if storage.key == H256::zero() && storage.value == H256::zero() { return Err(()); }
-        let tbl = self.table.get(0).unwrap().downcast_ref::<AccountStorageTable>().unwrap();
-        let query = tbl.get_update_query();
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let cnt = stmt.execute([
-            account_storage.key.as_bytes(),
-            account_storage.value.as_bytes(),
-            account_storage.key.as_bytes(),
-        ]);
-        return Ok(());
-    }
-}
-
-/// AccountTable is a SQLite version of WorldStateTrie.
-pub struct WorldStateTable {
-    pub connection: Connection,
-}
-
-impl WorldStateTable {
-    pub fn new() -> Self {
-        let conn = Connection::open(crate::constant::DatabasePath);
-        let tbl = WorldStateTable { connection: conn.unwrap() };
-        // let query = tbl.get_create_table_query();
-        // let mut stmt = tbl.make_statement(query.as_str());
-        // stmt.execute([]);
-        return tbl;
-    }
-}
-
-impl Default for WorldStateTable {
-    fn default() -> Self {
-        WorldStateTable::new()
-    }
-}
-
-impl Table for WorldStateTable {
-    fn get_table_name(&self) -> String {
-        "account".to_string()
-    }
-
-    fn get_create_table_query(&self) -> String {
-        "CREATE TABLE IF NOT EXISTS account(\
-            key BLOB,\
-            nonce INTEGER,\
-            storage_root BLOB,\
-            codehash BLOB)"
-            .to_string()
+        let value = self.backend.get(STORAGE_COLUMN, &self.storage_key(key));
+        return match value {
+            Some(value) if value.len() == 32 => {
+                AccountStorage { key: key.clone(), value: H256::from_slice(value.as_slice()) }
+            }
+            _ => { AccountStorage { key: key.clone(), value: H256::zero() } }
+        };
     }
 
-    fn get_drop_table_query(&self) -> String {
-        "DROP TABLE account".to_string()
+    /// 값을 batch에 기록한다. 0은 저장하지 않고 삭제한다.
+    pub fn put_storage_value(&self, batch: &mut WriteBatch, account_storage: &AccountStorage) {
+        let key = self.storage_key(&account_storage.key);
+        match account_storage.value.is_zero() {
+            true => { batch.delete(STORAGE_COLUMN, &key); }
+            false => { batch.put(STORAGE_COLUMN, &key, account_storage.value.as_bytes()); }
+        }
     }
 
-    fn get_select_query(&self, where_type: &str) -> String {
-        format!("SELECT * FROM account WHERE {} = ?", where_type)
+    /// 값이 없다면 추가하고 있다면 변경한다.
+    pub fn set_storage_value(&self, account_storage: &AccountStorage) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        self.put_storage_value(&mut batch, account_storage);
+        self.backend.write(batch)
     }
 
-    fn get_insert_query(&self) -> String {
-        "INSERT INTO account \
-            (key, nonce, storage_root, codehash) \
-            VALUES (?, ?, ?, ?)"
-            .to_string()
+    /// 저장된 모든 (key, value)
+    pub fn values(&self) -> Vec<AccountStorage> {
+        self.backend.scan(STORAGE_COLUMN, self.contract_address.as_bytes()).into_iter()
+            .filter(|(key, value)| key.len() == 52 && value.len() == 32)
+            .map(|(key, value)| AccountStorage {
+                key: H256::from_slice(&key[20..]),
+                value: H256::from_slice(value.as_slice()),
+            })
+            .collect()
     }
 
-    fn get_update_query(&self) -> String {
-        "UPDATE account SET key = ?, nonce = ?, storage_root = ?, codehash = ? \
-            WHERE key = ?"
-            .to_string()
-    }
+    pub fn is_empty(&self) -> bool { self.values().is_empty() }
 
-    fn get_delete_query(&self, where_type: &str) -> String {
-        format!("DELETE FROM account WHERE {} = ?", where_type)
+    /// 컨트랙트의 storage를 삭제하는 변경을 batch에 기록한다.
+    /// archive 모드라면 삭제되기 전의 값은 ArchiveManager::record가 이력으로 남긴다.
+    pub fn put_drop_storage(&self, batch: &mut WriteBatch) {
+        for storage in self.values().iter() {
+            batch.delete(STORAGE_COLUMN, &self.storage_key(&storage.key));
+        }
     }
 
-    fn make_statement(&self, query: &str) -> Statement {
-        self.connection.prepare(query).unwrap()
+    /// 컨트랙트의 storage를 삭제한다.
+    pub fn drop_storage(self) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        self.put_drop_storage(&mut batch);
+        self.backend.write(batch)
     }
 }
 
+/// world state의 account들. key는 keccak256(address)이다.
 pub struct WorldStateTableManager {
-    table: Vec<Box<dyn std::any::Any>>,
-    pub table_name: String,
-}
-
-impl Container for WorldStateTableManager {
-    fn initialize(&self) {
-        let query = self.get_table().get_create_table_query();
-        let mut stmt = self.get_table().make_statement(query.as_str());
-        stmt.execute([]);
-    }
+    backend: Arc<dyn Backend>,
 }
 
 impl WorldStateTableManager {
-    pub fn new() -> Self {
-        let tbl = WorldStateTable::new();
-        let mut container = WorldStateTableManager { table: vec![], table_name: "account".to_string() };
-        container.table.push(Box::new(tbl));
-        return container;
-    }
-
-    pub fn get_table(&self) -> &WorldStateTable {
-        self.table.get(0).unwrap().downcast_ref::<WorldStateTable>().unwrap()
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        WorldStateTableManager { backend }
     }
 
     pub fn exist(&self, account_key: &H256) -> bool {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<WorldStateTable>().unwrap();
-        let query = tbl.get_select_query("key");
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let account_iter = stmt.query_map([account_key.as_bytes()], |row| {
-            return Ok(true);
-        });
-        return false;
+        self.backend.exists(ACCOUNT_COLUMN, account_key.as_bytes())
     }
 
     pub fn get_account(&self, address: &Address) -> AccountNode {
-        let keccak_value = crypto::hash::keccak256(address.as_bytes());
-        let keccak_address = H256::from(&keccak_value);
-        let tbl = self.table.get(0).unwrap().downcast_ref::<WorldStateTable>().unwrap();
-        let query = tbl.get_select_query("key");
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let account_iter = stmt.query_map([keccak_value.to_vec()], |row| {
-            let key = crate::sql_util::to_h256(row.get(0)?);
-            let nonce = crate::sql_util::to_h256(row.get(1)?);
-            let storage_root = crate::sql_util::to_h256(row.get(2)?);
-            // let codehash = crate::sql_util::to_H256(row.get(3)?);
-            let codehash: Vec<u8> = row.get(3).unwrap();
-            return Ok(AccountNode {
-                key,
-                nonce,
-                storage_root,
-                codehash,
-            });
-        });
-        let mut not_found = AccountNode::default();
-        not_found.key = keccak_address;
-        return not_found;
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        let account = self.backend.get(ACCOUNT_COLUMN, key.as_bytes())
+            .and_then(|value| rlp::decode::<AccountNode>(value.as_slice()).ok());
+        let mut node = account.unwrap_or_default();
+        node.key = key;
+        node
+    }
+
+    /// account를 batch에 기록한다. 없다면 추가하고 있다면 변경한다.
+    pub fn put_account(&self, batch: &mut WriteBatch, node: &AccountNode) {
+        batch.put(ACCOUNT_COLUMN, node.key.as_bytes(), &rlp::encode(node));
     }
 
     pub fn update_account(&self, node: &AccountNode) -> Result<(), ()> {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<WorldStateTable>().unwrap();
-        let query = tbl.get_update_query();
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let cnt = stmt
-            .execute(
-                [node.key.as_bytes(),
-                    node.nonce.as_bytes(),
-                    node.storage_root.as_bytes(),
-                    // node.codehash.as_bytes(),
-                    node.codehash.as_slice(),
-                    node.key.as_bytes()]
-            );
-        return match cnt {
-            Ok(n) => {
-                if n != 0 { return Ok(()); }
-                Err(())
-            }
-            Err(_) => { Err(()) }
-        };
+        if !self.exist(&node.key) { return Err(()); }
+        self.insert_account(node)
+    }
+
+    /// account를 삭제하는 변경을 batch에 기록한다.
+    pub fn put_delete_account(&self, batch: &mut WriteBatch, account_key: &H256) {
+        batch.delete(ACCOUNT_COLUMN, account_key.as_bytes());
+    }
+
+    pub fn delete_account(&self, account_key: &H256) -> Result<(), ()> {
+        if !self.exist(account_key) { return Err(()); }
+        self.backend.delete(ACCOUNT_COLUMN, account_key.as_bytes())
     }
 
     pub fn insert_account(&self, node: &AccountNode) -> Result<(), ()> {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<WorldStateTable>().unwrap();
-        let query = tbl.get_insert_query();
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let cnt = stmt
-            // .execute([node.key.as_bytes(), node.nonce.as_bytes(), node.storage_root.as_bytes(), node.codehash.as_bytes()]);
-            .execute([node.key.as_bytes(), node.nonce.as_bytes(), node.storage_root.as_bytes(), node.codehash.as_slice()]);
-        return match cnt {
-            Ok(n) => {
-                if n != 0 { return Ok(()); }
-                Err(())
-            }
-            Err(_) => { Err(()) }
-        };
+        let mut batch = WriteBatch::new();
+        self.put_account(&mut batch, node);
+        self.backend.write(batch)
     }
-}
\ No newline at end of file
+}
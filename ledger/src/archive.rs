@@ -0,0 +1,165 @@
+use std::sync::Arc;
+use crate::account::{ACCOUNT_COLUMN, STORAGE_COLUMN};
+use crate::backend::{Backend, WriteBatch, WriteOp};
+use crate::milestone::{MILESTONE_COLUMN, to_height};
+
+/// address || storage key || height(u64 big endian) -> 그 height부터의 값. 0이 된 값은 빈 값이다.
+pub const STORAGE_HISTORY_COLUMN: &str = "storage_history";
+/// keccak256(address) || height(u64 big endian) -> 그 height부터의 rlp(AccountNode). 제거된 account는 빈 값이다.
+pub const ACCOUNT_HISTORY_COLUMN: &str = "account_history";
+/// "since" -> archive 모드를 시작한 height
+pub const ARCHIVE_COLUMN: &str = "archive";
+
+const SINCE_KEY: &[u8] = b"since";
+
+/// 상태를 조회할 시점 (eth_getStorageAt, eth_call의 block parameter)
+/// Latest와 Pending은 마지막으로 반영된 상태이며 Number는 그 height의 마일스톤이 추가되기 직전까지 반영된 상태이다.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BlockTag {
+    Number(u64),
+    Earliest,
+    Latest,
+    Pending,
+}
+
+impl BlockTag {
+    /// "latest", "pending", "earliest" 또는 0x로 시작하는 height
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "" | "latest" => { Some(BlockTag::Latest) }
+            "pending" => { Some(BlockTag::Pending) }
+            "earliest" => { Some(BlockTag::Earliest) }
+            _ => {
+                let number = value.strip_prefix("0x")?;
+                u64::from_str_radix(number, 16).ok().map(BlockTag::Number)
+            }
+        }
+    }
+}
+
+/// 과거 상태를 조회하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum ArchiveError {
+    NotArchived(u64),       // archive 모드가 아니었던 height
+    UnknownHeight(u64),     // 아직 추가되지 않은 마일스톤의 height
+}
+
+fn history_column(column: &str) -> Option<&'static str> {
+    match column {
+        STORAGE_COLUMN => { Some(STORAGE_HISTORY_COLUMN) }
+        ACCOUNT_COLUMN => { Some(ACCOUNT_HISTORY_COLUMN) }
+        _ => { None }
+    }
+}
+
+/// archive 모드에서 storage와 account 값의 변경 이력을 마일스톤 height별로 보관한다.
+/// 변경은 다음 마일스톤의 height로 기록되므로 height N의 값은 마일스톤 N이 추가되기 전까지의 마지막 값이다.
+/// 한번 켜진 archive 모드는 데이터베이스에 기록되어 계속 유지된다.
+pub struct ArchiveManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl ArchiveManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        ArchiveManager { backend }
+    }
+
+    /// archive 모드를 시작한 height. archive 모드가 아니라면 None이다.
+    pub fn since(&self) -> Option<u64> {
+        let value = self.backend.get(ARCHIVE_COLUMN, SINCE_KEY)?;
+        to_height(value.as_slice())
+    }
+
+    /// 다음 마일스톤부터 변경 이력을 보관하고 시작한 height를 반환한다. 이미 archive 모드라면 바뀌지 않는다.
+    pub fn enable(&self) -> Result<u64, ()> {
+        if let Some(since) = self.since() { return Ok(since); }
+        let since = self.next_height();
+        self.backend.put(ARCHIVE_COLUMN, SINCE_KEY, &since.to_be_bytes())?;
+        Ok(since)
+    }
+
+    /// 아직 마일스톤에 포함되지 않은 변경이 기록될 height
+    fn next_height(&self) -> u64 {
+        let latest = self.backend.last(MILESTONE_COLUMN, &[]);
+        latest.and_then(|(key, _)| to_height(key.as_slice())).map_or(0, |height| height + 1)
+    }
+
+    /// batch의 storage와 account 변경을 이력으로 batch에 함께 기록한다. archive 모드가 아니라면 아무것도 하지 않는다.
+    /// 처음 변경되는 값은 변경 전의 값을 archive를 시작한 height로 먼저 남긴다.
+    pub fn record(&self, batch: &mut WriteBatch) {
+        let since = match self.since() {
+            Some(since) => { since }
+            None => { return; }
+        };
+        let height = self.next_height();
+        let changes: Vec<(&'static str, String, Vec<u8>, Vec<u8>)> = batch.ops().iter()
+            .filter_map(|op| {
+                let (column, key, value) = match op {
+                    WriteOp::Put(column, key, value) => { (column, key, value.clone()) }
+                    WriteOp::Delete(column, key) => { (column, key, vec![]) }
+                };
+                history_column(column).map(|history| (history, column.clone(), key.clone(), value))
+            })
+            .collect();
+        for (history, column, key, value) in changes.into_iter() {
+            if height > since && self.backend.last(history, &key).is_none() {
+                let previous = self.backend.get(&column, &key).unwrap_or_default();
+                batch.put(history, &[key.as_slice(), &since.to_be_bytes()].concat(), &previous);
+            }
+            batch.put(history, &[key.as_slice(), &height.to_be_bytes()].concat(), &value);
+        }
+    }
+
+    /// height 시점에 column의 key가 가졌던 값
+    /// 이력이 없다면(archive 모드 이후 변경되지 않았다면) None이며 현재 값과 같다.
+    pub fn get_at(&self, column: &str, key: &[u8], height: u64) -> Option<Option<Vec<u8>>> {
+        let history = history_column(column)?;
+        let version = match height.checked_add(1) {
+            Some(end) => { self.backend.last_before(history, key, &[key, &end.to_be_bytes()].concat()) }
+            None => { self.backend.last(history, key) }
+        };
+        let (version, value) = version?;
+        if version.len() != key.len() + 8 { return None; }
+        match value.is_empty() {
+            true => { Some(None) }
+            false => { Some(Some(value)) }
+        }
+    }
+}
+
+/// height 시점의 storage와 account 값을 읽는 읽기 전용 backend
+/// 그 외의 column과 range, scan은 현재 값을 읽는다.
+pub struct HistoricalBackend {
+    base: Arc<dyn Backend>,
+    archive: ArchiveManager,
+    height: u64,
+}
+
+impl HistoricalBackend {
+    pub fn new(base: Arc<dyn Backend>, height: u64) -> Self {
+        HistoricalBackend { archive: ArchiveManager::new(base.clone()), base, height }
+    }
+
+    pub fn height(&self) -> u64 { self.height }
+}
+
+impl Backend for HistoricalBackend {
+    fn get(&self, column: &str, key: &[u8]) -> Option<Vec<u8>> {
+        match self.archive.get_at(column, key, self.height) {
+            Some(value) => { value }
+            None => { self.base.get(column, key) }
+        }
+    }
+
+    fn range(&self, column: &str, start: &[u8], end: Option<&[u8]>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.base.range(column, start, end)
+    }
+
+    fn last(&self, column: &str, prefix: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.base.last(column, prefix)
+    }
+
+    fn write(&self, _batch: WriteBatch) -> Result<(), ()> {
+        Err(())
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::{Mutex, RwLock};
+use rusqlite::{Connection, OptionalExtension};
+use crate::migration::{self, MigrationContext, MigrationError};
+
+/// ledger의 데이터가 저장되는 key/value 저장소
+/// column은 SQL의 테이블처럼 key 공간을 나누며, 같은 column 안에서 key는 bytes 순서로 정렬된다.
+pub trait Backend: Send + Sync {
+    fn get(&self, column: &str, key: &[u8]) -> Option<Vec<u8>>;
+
+    /// start 이상 end 미만인 key의 값들을 key 오름차순으로 반환한다. end가 None이면 column의 끝까지 읽는다.
+    fn range(&self, column: &str, start: &[u8], end: Option<&[u8]>) -> Vec<(Vec<u8>, Vec<u8>)>;
+
+    /// prefix로 시작하는 key 중 가장 큰 key와 그 값
+    fn last(&self, column: &str, prefix: &[u8]) -> Option<(Vec<u8>, Vec<u8>)>;
+
+    /// batch의 모든 변경을 하나의 트랜잭션으로 반영한다. 실패하면 아무것도 반영되지 않는다.
+    fn write(&self, batch: WriteBatch) -> Result<(), ()>;
+
+    fn exists(&self, column: &str, key: &[u8]) -> bool {
+        self.get(column, key).is_some()
+    }
+
+    /// start 이상 end 미만인 key 중 가장 큰 key와 그 값
+    fn last_before(&self, column: &str, start: &[u8], end: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.range(column, start, Some(end)).pop()
+    }
+
+    /// prefix로 시작하는 key의 값들을 key 오름차순으로 반환한다.
+    fn scan(&self, column: &str, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let end = prefix_end(prefix);
+        self.range(column, prefix, end.as_ref().map(|end| end.as_slice()))
+    }
+
+    fn put(&self, column: &str, key: &[u8], value: &[u8]) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        batch.put(column, key, value);
+        self.write(batch)
+    }
+
+    fn delete(&self, column: &str, key: &[u8]) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        batch.delete(column, key);
+        self.write(batch)
+    }
+}
+
+/// prefix로 시작하는 모든 key보다 큰 가장 작은 key. prefix가 비어있거나 0xff로만 이루어졌다면 None이다.
+pub fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+pub enum WriteOp {
+    Put(String, Vec<u8>, Vec<u8>),
+    Delete(String, Vec<u8>),
+}
+
+/// 하나의 트랜잭션으로 반영될 변경들. 같은 key에 대해서는 나중의 변경이 반영된다.
+pub struct WriteBatch {
+    ops: Vec<WriteOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self { WriteBatch { ops: vec![] } }
+
+    pub fn put(&mut self, column: &str, key: &[u8], value: &[u8]) {
+        self.ops.push(WriteOp::Put(column.to_string(), key.to_vec(), value.to_vec()));
+    }
+
+    pub fn delete(&mut self, column: &str, key: &[u8]) {
+        self.ops.push(WriteOp::Delete(column.to_string(), key.to_vec()));
+    }
+
+    pub fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+    pub fn len(&self) -> usize { self.ops.len() }
+
+    pub fn ops(&self) -> &Vec<WriteOp> { &self.ops }
+
+    /// batch 안에서 key에 마지막으로 기록된 값. Some(None)은 삭제를 의미한다.
+    pub fn get(&self, column: &str, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        for op in self.ops.iter().rev() {
+            match op {
+                WriteOp::Put(c, k, v) if c == column && k.as_slice() == key => { return Some(Some(v.clone())); }
+                WriteOp::Delete(c, k) if c == column && k.as_slice() == key => { return Some(None); }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    pub fn append(&mut self, other: WriteBatch) {
+        self.ops.extend(other.ops.into_iter());
+    }
+}
+
+impl Default for WriteBatch {
+    fn default() -> Self { WriteBatch::new() }
+}
+
+/// backend를 읽으며 변경을 모아두었다가 commit 시 한번에 반영하는 트랜잭션
+/// 아직 반영되지 않은 변경도 get으로 읽을 수 있다.
+pub struct KvTransaction<'a> {
+    backend: &'a dyn Backend,
+    batch: WriteBatch,
+}
+
+impl<'a> KvTransaction<'a> {
+    pub fn new(backend: &'a dyn Backend) -> Self {
+        KvTransaction { backend, batch: WriteBatch::new() }
+    }
+
+    pub fn get(&self, column: &str, key: &[u8]) -> Option<Vec<u8>> {
+        match self.batch.get(column, key) {
+            Some(value) => { value }
+            None => { self.backend.get(column, key) }
+        }
+    }
+
+    pub fn exists(&self, column: &str, key: &[u8]) -> bool { self.get(column, key).is_some() }
+
+    pub fn put(&mut self, column: &str, key: &[u8], value: &[u8]) { self.batch.put(column, key, value); }
+
+    pub fn delete(&mut self, column: &str, key: &[u8]) { self.batch.delete(column, key); }
+
+    pub fn batch(&mut self) -> &mut WriteBatch { &mut self.batch }
+
+    pub fn commit(self) -> Result<(), ()> {
+        if self.batch.is_empty() { return Ok(()); }
+        self.backend.write(self.batch)
+    }
+}
+
+/// 모든 column을 하나의 SQLite 테이블(kv)에 저장하는 backend
+/// 테이블은 migration이 만들며 schema version은 schema_version 테이블에 기록된다.
+pub struct SqliteBackend {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// 데이터베이스 파일을 열고 아직 적용되지 않은 migration을 적용한다.
+    pub fn open(path: &str) -> Result<Self, MigrationError> {
+        let mut conn = Connection::open(path).map_err(|_| MigrationError::Database)?;
+        let context = MigrationContext { directory: Some(migration::database_directory(path)) };
+        migration::migrate(&mut conn, &context)?;
+        Ok(SqliteBackend { connection: Mutex::new(conn) })
+    }
+
+    pub fn from_connection(mut conn: Connection) -> Result<Self, MigrationError> {
+        migration::migrate(&mut conn, &MigrationContext { directory: None })?;
+        Ok(SqliteBackend { connection: Mutex::new(conn) })
+    }
+
+    pub fn schema_version(&self) -> u32 {
+        migration::schema_version(&self.connection.lock().unwrap()).unwrap_or(0)
+    }
+}
+
+impl Backend for SqliteBackend {
+    fn get(&self, column: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT value FROM kv WHERE col = ? AND key = ?").unwrap();
+        stmt.query_row(rusqlite::params![column, key], |row| row.get(0)).optional().unwrap_or(None)
+    }
+
+    fn range(&self, column: &str, start: &[u8], end: Option<&[u8]>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let conn = self.connection.lock().unwrap();
+        let map = |row: &rusqlite::Row| -> rusqlite::Result<(Vec<u8>, Vec<u8>)> { Ok((row.get(0)?, row.get(1)?)) };
+        let rows = match end {
+            Some(end) => {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT key, value FROM kv WHERE col = ? AND key >= ? AND key < ? ORDER BY key").unwrap();
+                let rows = stmt.query_map(rusqlite::params![column, start, end], map);
+                rows.and_then(|rows| rows.collect())
+            }
+            None => {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT key, value FROM kv WHERE col = ? AND key >= ? ORDER BY key").unwrap();
+                let rows = stmt.query_map(rusqlite::params![column, start], map);
+                rows.and_then(|rows| rows.collect())
+            }
+        };
+        rows.unwrap_or_default()
+    }
+
+    fn last(&self, column: &str, prefix: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let conn = self.connection.lock().unwrap();
+        let map = |row: &rusqlite::Row| -> rusqlite::Result<(Vec<u8>, Vec<u8>)> { Ok((row.get(0)?, row.get(1)?)) };
+        let row = match prefix_end(prefix) {
+            Some(end) => {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT key, value FROM kv WHERE col = ? AND key >= ? AND key < ? ORDER BY key DESC LIMIT 1").unwrap();
+                stmt.query_row(rusqlite::params![column, prefix, end], map).optional()
+            }
+            None => {
+                let mut stmt = conn.prepare_cached(
+                    "SELECT key, value FROM kv WHERE col = ? AND key >= ? ORDER BY key DESC LIMIT 1").unwrap();
+                stmt.query_row(rusqlite::params![column, prefix], map).optional()
+            }
+        };
+        row.unwrap_or(None)
+    }
+
+    fn last_before(&self, column: &str, start: &[u8], end: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let conn = self.connection.lock().unwrap();
+        let map = |row: &rusqlite::Row| -> rusqlite::Result<(Vec<u8>, Vec<u8>)> { Ok((row.get(0)?, row.get(1)?)) };
+        let mut stmt = conn.prepare_cached(
+            "SELECT key, value FROM kv WHERE col = ? AND key >= ? AND key < ? ORDER BY key DESC LIMIT 1").unwrap();
+        stmt.query_row(rusqlite::params![column, start, end], map).optional().unwrap_or(None)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), ()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = match conn.transaction() {
+            Ok(tx) => { tx }
+            Err(_) => { return Err(()); }
+        };
+        for op in batch.ops.iter() {
+            let result = match op {
+                WriteOp::Put(column, key, value) => {
+                    tx.prepare_cached("INSERT OR REPLACE INTO kv (col, key, value) VALUES (?, ?, ?)")
+                        .and_then(|mut stmt| stmt.execute(rusqlite::params![column, key, value]))
+                }
+                WriteOp::Delete(column, key) => {
+                    tx.prepare_cached("DELETE FROM kv WHERE col = ? AND key = ?")
+                        .and_then(|mut stmt| stmt.execute(rusqlite::params![column, key]))
+                }
+            };
+            if result.is_err() { return Err(()); }     // dropping tx rolls back
+        }
+        match tx.commit() {
+            Ok(_) => { Ok(()) }
+            Err(_) => { Err(()) }
+        }
+    }
+}
+
+/// 메모리에만 저장하는 backend이며 테스트와 시뮬레이션에 사용한다.
+pub struct MemoryBackend {
+    columns: RwLock<HashMap<String, BTreeMap<Vec<u8>, Vec<u8>>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        MemoryBackend { columns: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self { MemoryBackend::new() }
+}
+
+impl Backend for MemoryBackend {
+    fn get(&self, column: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let columns = self.columns.read().unwrap();
+        columns.get(column)?.get(key).cloned()
+    }
+
+    fn range(&self, column: &str, start: &[u8], end: Option<&[u8]>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let columns = self.columns.read().unwrap();
+        let values = match columns.get(column) {
+            Some(values) => { values }
+            None => { return vec![]; }
+        };
+        let upper = match end {
+            Some(end) => { Bound::Excluded(end.to_vec()) }
+            None => { Bound::Unbounded }
+        };
+        if end.map_or(false, |end| end <= start) { return vec![]; }
+        values.range((Bound::Included(start.to_vec()), upper))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    fn last(&self, column: &str, prefix: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let columns = self.columns.read().unwrap();
+        let values = columns.get(column)?;
+        let upper = match prefix_end(prefix) {
+            Some(end) => { Bound::Excluded(end) }
+            None => { Bound::Unbounded }
+        };
+        values.range((Bound::Included(prefix.to_vec()), upper)).next_back()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    fn last_before(&self, column: &str, start: &[u8], end: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        if end <= start { return None; }
+        let columns = self.columns.read().unwrap();
+        let values = columns.get(column)?;
+        values.range((Bound::Included(start.to_vec()), Bound::Excluded(end.to_vec()))).next_back()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), ()> {
+        let mut columns = self.columns.write().unwrap();
+        for op in batch.ops.into_iter() {
+            match op {
+                WriteOp::Put(column, key, value) => {
+                    columns.entry(column).or_insert_with(BTreeMap::new).insert(key, value);
+                }
+                WriteOp::Delete(column, key) => {
+                    if let Some(values) = columns.get_mut(&column) { values.remove(&key); }
+                }
+            }
+        }
+        Ok(())
+    }
+}
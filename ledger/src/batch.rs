@@ -0,0 +1,338 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+use crate::dirty_state::DirtyStates;
+use crate::ledger::Ledger;
+use crate::log::Log;
+use crate::receipt::{STATUS_FAILED, STATUS_SUCCESS};
+use crate::transaction::{Transaction, recover_sender};
+
+/// batch 트랜잭션의 type (EIP-2718의 범위 안에서 이 chain이 사용하는 값)
+pub const BATCH_TX_TYPE: u8 = 0x71;
+/// sender || keccak256(sensor) || timestamp(u64 big endian) -> rlp(Reading)
+pub const READING_COLUMN: &str = "reading";
+/// tx hash -> rlp([SubReceipt])
+pub const SUB_RECEIPT_COLUMN: &str = "sub_receipt";
+
+/// batch 트랜잭션을 실행하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum BatchError {
+    InvalidSignature,   // 서명에서 sender를 복구할 수 없음
+    ChainId(u64),       // 다른 chain을 위해 서명됨. 이 chain의 id를 갖는다.
+    Empty,              // entry가 없음
+    Nonce(u64),         // sender의 다음 nonce와 다름. 기대한 nonce를 갖는다.
+}
+
+/// device가 측정한 값
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reading {
+    pub timestamp: u64,
+    pub sensor: String,
+    pub value: Vec<u8>,
+}
+
+impl Encodable for Reading {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.timestamp);
+        s.append(&self.sensor);
+        s.append(&self.value);
+    }
+}
+
+impl Decodable for Reading {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Reading { timestamp: rlp.val_at(0)?, sensor: rlp.val_at(1)?, value: rlp.val_at(2)? })
+    }
+}
+
+/// batch에 담기는 측정값 하나 또는 컨트랙트 호출 하나
+/// rlp([0, timestamp, sensor, value]) 또는 rlp([1, to, data])
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BatchEntry {
+    Reading(Reading),
+    Call { recipient: Address, data: Vec<u8> },
+}
+
+impl Encodable for BatchEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            BatchEntry::Reading(reading) => {
+                s.begin_list(4);
+                s.append(&0u8);
+                s.append(&reading.timestamp);
+                s.append(&reading.sensor);
+                s.append(&reading.value);
+            }
+            BatchEntry::Call { recipient, data } => {
+                s.begin_list(3);
+                s.append(&1u8);
+                s.append(recipient);
+                s.append(data);
+            }
+        }
+    }
+}
+
+impl Decodable for BatchEntry {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match (rlp.val_at::<u8>(0)?, rlp.item_count()?) {
+            (0, 4) => {
+                let reading = Reading { timestamp: rlp.val_at(1)?, sensor: rlp.val_at(2)?, value: rlp.val_at(3)? };
+                Ok(BatchEntry::Reading(reading))
+            }
+            (1, 3) => { Ok(BatchEntry::Call { recipient: rlp.val_at(1)?, data: rlp.val_at(2)? }) }
+            _ => { Err(DecoderError::Custom("unknown batch entry")) }
+        }
+    }
+}
+
+/// 여러 측정값이나 컨트랙트 호출을 하나의 서명으로 보내는 트랜잭션
+/// atomic이라면 한 entry라도 실패할 때 아무것도 반영되지 않으며, 아니라면 성공한 entry만 반영된다.
+/// 0x71 || rlp([chainId, nonce, atomic, entries, yParity, r, s])
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchTransaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub atomic: bool,
+    pub entries: Vec<BatchEntry>,
+    pub y_parity: u8,
+    pub r: Vec<u8>,
+    pub s: Vec<u8>,
+}
+
+impl BatchTransaction {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecoderError> {
+        match bytes.first() {
+            Some(&BATCH_TX_TYPE) => { rlp::decode(&bytes[1..]) }
+            _ => { Err(DecoderError::Custom("not a batch transaction")) }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&[BATCH_TX_TYPE], rlp::encode(self).as_ref()].concat()
+    }
+
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(self.to_bytes().as_slice()))
+    }
+
+    fn append_payload(&self, s: &mut RlpStream) {
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.atomic);
+        s.append_list(&self.entries);
+    }
+
+    /// keccak256(0x71 || rlp([chainId, nonce, atomic, entries]))
+    pub fn signing_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(4);
+        self.append_payload(&mut s);
+        H256::from(crypto::hash::keccak256(&[&[BATCH_TX_TYPE], s.out().as_ref()].concat()))
+    }
+
+    pub fn sign(&mut self, sk: &crypto::key::Sk) {
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.y_parity = rec_id as u8;
+        self.r = signature[..32].to_vec();
+        self.s = signature[32..].to_vec();
+    }
+
+    pub fn try_get_sender(&self) -> Option<Address> {
+        if self.y_parity > 1 { return None; }
+        recover_sender(self.y_parity as i32, &self.r, &self.s, &self.signing_hash())
+    }
+
+    /// DAG에 저장하는 트랜잭션이며 data는 batch 트랜잭션의 bytes이다.
+    /// parents, timestamp, state_hash는 실행한 뒤 채운다.
+    pub fn to_transaction(&self) -> Transaction {
+        let mut tx = Transaction::default();
+        tx.nonce = self.nonce as usize;
+        tx.data = self.to_bytes();
+        tx.v = self.y_parity as usize;
+        tx.r = self.r.clone();
+        tx.s = self.s.clone();
+        tx
+    }
+}
+
+impl Encodable for BatchTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(7);
+        self.append_payload(s);
+        s.append(&self.y_parity);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for BatchTransaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 7 { return Err(DecoderError::RlpIncorrectListLen); }
+        Ok(BatchTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            atomic: rlp.val_at(2)?,
+            entries: rlp.list_at(3)?,
+            y_parity: rlp.val_at(4)?,
+            r: rlp.val_at(5)?,
+            s: rlp.val_at(6)?
+        })
+    }
+}
+
+/// batch entry 하나의 실행 결과
+pub struct SubReceipt {
+    pub status: u8,
+    pub output: Vec<u8>,
+    pub logs: Vec<Log>,
+}
+
+impl SubReceipt {
+    pub fn new(status: u8, output: Vec<u8>, logs: Vec<Log>) -> Self {
+        SubReceipt { status, output, logs }
+    }
+
+    pub fn is_success(&self) -> bool { self.status == STATUS_SUCCESS }
+}
+
+impl Encodable for SubReceipt {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.status);
+        s.append(&self.output);
+        s.append_list(&self.logs);
+    }
+}
+
+impl Decodable for SubReceipt {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(SubReceipt { status: rlp.val_at(0)?, output: rlp.val_at(1)?, logs: rlp.list_at(2)? })
+    }
+}
+
+/// batch의 컨트랙트 호출을 실행한다. ledger crate는 vm에 의존할 수 없으므로 vm이 구현한다.
+pub trait CallExecutor {
+    /// pending은 앞선 entry들이 바꾼 storage이다. 실행 결과와 이 호출이 바꾼 storage를 반환한다.
+    fn call(&self, ledger: &Ledger, pending: &DirtyStates, sender: &Address, recipient: &Address,
+            data: &Vec<u8>) -> (SubReceipt, DirtyStates);
+}
+
+/// batch 트랜잭션의 실행 결과이며 Ledger::commit_batch로 반영한다.
+/// * `status` - atomic batch의 entry가 하나라도 실패했다면 STATUS_FAILED이며 states와 readings는 비어있다.
+/// * `sub_receipts` - entry 순서이며 반영되지 않은 entry도 자신의 실행 결과를 갖는다.
+pub struct BatchOutcome {
+    pub batch: H256,
+    pub sender: Address,
+    pub status: u8,
+    pub states: DirtyStates,
+    pub readings: Vec<Reading>,
+    pub sub_receipts: Vec<SubReceipt>,
+}
+
+impl BatchOutcome {
+    /// 반영된 entry들의 log이며 batch의 receipt에 기록된다.
+    pub fn logs(&self) -> Vec<Log> {
+        if self.status != STATUS_SUCCESS { return vec![]; }
+        self.sub_receipts.iter()
+            .filter(|receipt| receipt.is_success())
+            .flat_map(|receipt| receipt.logs.iter().cloned())
+            .collect()
+    }
+}
+
+/// batch 트랜잭션을 entry 순서대로 실행한다. ledger에는 아무것도 기록하지 않는다.
+/// 측정값은 같은 sensor의 마지막 측정값보다 timestamp가 커야 하며, 컨트랙트 호출은 executor가 실행한다.
+pub fn execute(ledger: &Ledger, tx: &BatchTransaction, executor: &dyn CallExecutor) -> Result<BatchOutcome, BatchError> {
+    if tx.entries.is_empty() { return Err(BatchError::Empty); }
+    let sender = tx.try_get_sender().ok_or(BatchError::InvalidSignature)?;
+    if let Some(config) = ledger.chain_config() {
+        if tx.chain_id != config.chain_id { return Err(BatchError::ChainId(config.chain_id)); }
+    }
+    let nonce = ledger.next_nonce(&sender);
+    if tx.nonce != nonce { return Err(BatchError::Nonce(nonce)); }
+
+    let mut states = DirtyStates::new();
+    let mut readings = vec![];
+    let mut sub_receipts = vec![];
+    let mut last_timestamps: HashMap<String, u64> = HashMap::new();
+    for entry in tx.entries.iter() {
+        match entry {
+            BatchEntry::Reading(reading) => {
+                let last = match last_timestamps.get(&reading.sensor) {
+                    Some(timestamp) => { Some(*timestamp) }
+                    None => { ledger.get_batches().last_reading(&sender, &reading.sensor).map(|last| last.timestamp) }
+                };
+                if last.map_or(false, |last| reading.timestamp <= last) {
+                    sub_receipts.push(SubReceipt::new(STATUS_FAILED, vec![], vec![]));
+                    continue;
+                }
+                last_timestamps.insert(reading.sensor.clone(), reading.timestamp);
+                readings.push(reading.clone());
+                sub_receipts.push(SubReceipt::new(STATUS_SUCCESS, vec![], vec![]));
+            }
+            BatchEntry::Call { recipient, data } => {
+                let (receipt, changes) = executor.call(ledger, &states, &sender, recipient, data);
+                if receipt.is_success() { states.extend(&changes); }
+                sub_receipts.push(receipt);
+            }
+        }
+    }
+
+    let failed = sub_receipts.iter().any(|receipt| !receipt.is_success());
+    let status = match tx.atomic && failed {
+        true => {
+            states = DirtyStates::new();
+            readings.clear();
+            STATUS_FAILED
+        }
+        false => { STATUS_SUCCESS }
+    };
+    Ok(BatchOutcome { batch: tx.hash(), sender, status, states, readings, sub_receipts })
+}
+
+fn sensor_key(sender: &Address, sensor: &str) -> Vec<u8> {
+    [sender.as_bytes(), &crypto::hash::keccak256(sensor.as_bytes())[..]].concat()
+}
+
+/// batch 트랜잭션으로 기록된 측정값과 sub receipt
+pub struct BatchTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl BatchTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        BatchTableManager { backend }
+    }
+
+    pub fn put_reading(&self, batch: &mut WriteBatch, sender: &Address, reading: &Reading) {
+        let key = [sensor_key(sender, &reading.sensor).as_slice(), &reading.timestamp.to_be_bytes()].concat();
+        batch.put(READING_COLUMN, &key, &rlp::encode(reading));
+    }
+
+    /// sender가 기록한 sensor의 측정값들. timestamp 순서이다.
+    pub fn readings(&self, sender: &Address, sensor: &str) -> Vec<Reading> {
+        self.backend.scan(READING_COLUMN, &sensor_key(sender, sensor)).into_iter()
+            .filter_map(|(_, value)| rlp::decode(value.as_slice()).ok())
+            .collect()
+    }
+
+    pub fn last_reading(&self, sender: &Address, sensor: &str) -> Option<Reading> {
+        let (_, value) = self.backend.last(READING_COLUMN, &sensor_key(sender, sensor))?;
+        rlp::decode(value.as_slice()).ok()
+    }
+
+    pub fn put_sub_receipts(&self, batch: &mut WriteBatch, tx_hash: &H256, sub_receipts: &Vec<SubReceipt>) {
+        let mut s = RlpStream::new();
+        s.append_list(sub_receipts);
+        batch.put(SUB_RECEIPT_COLUMN, tx_hash.as_bytes(), &s.out());
+    }
+
+    /// batch 트랜잭션의 sub receipt들. entry 순서이다.
+    pub fn get_sub_receipts(&self, tx_hash: &H256) -> Option<Vec<SubReceipt>> {
+        let value = self.backend.get(SUB_RECEIPT_COLUMN, tx_hash.as_bytes())?;
+        Rlp::new(value.as_slice()).as_list().ok()
+    }
+}
@@ -0,0 +1,30 @@
+use common::datadir::DataDir;
+use ledger::genesis::Genesis;
+use ledger::ledger::Ledger;
+
+const USAGE: &str = "usage: genesis <data directory> <genesis file (json)>";
+
+fn exit(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 { exit(USAGE.to_string()); }
+    let genesis = match Genesis::load(args[2].as_str()) {
+        Ok(genesis) => { genesis }
+        Err(err) => { exit(format!("cannot read {}: {:?}", args[2], err)) }
+    };
+    let mut ledger = match Ledger::open(&DataDir::new(args[1].as_str())) {
+        Ok(ledger) => { ledger }
+        Err(err) => { exit(format!("cannot open {}: {:?}", args[1], err)) }
+    };
+    match ledger.initialize(&genesis) {
+        Ok(hash) => {
+            println!("initialized chain {} (network {}) with genesis 0x{}",
+                     genesis.chain_id, genesis.network_id, hex::encode(hash.as_bytes()));
+        }
+        Err(err) => { exit(format!("initialization failed: {:?}", err)) }
+    }
+}
@@ -0,0 +1,66 @@
+use ethereum_types::Address;
+use common::datadir::DataDir;
+use ledger::ledger::Ledger;
+use ledger::snapshot;
+
+const USAGE: &str = "usage:\n\
+    snapshot export <data directory> <snapshot file> <authority secret key (hex)> [max transactions]\n\
+    snapshot import <data directory> <snapshot file> <authority address (hex)>...";
+
+fn open_ledger(path: &str) -> Ledger {
+    match Ledger::open(&DataDir::new(path)) {
+        Ok(ledger) => { ledger }
+        Err(err) => { exit(format!("cannot open {}: {:?}", path, err)) }
+    }
+}
+
+fn decode_hex(value: &str) -> Vec<u8> {
+    match hex::decode(value.trim_start_matches("0x")) {
+        Ok(bytes) => { bytes }
+        Err(_) => { exit(format!("invalid hex: {}", value)) }
+    }
+}
+
+fn exit(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 { exit(USAGE.to_string()); }
+    let mut ledger = open_ledger(args[2].as_str());
+    match args[1].as_str() {
+        "export" => {
+            let secret = decode_hex(args[4].as_str());
+            if secret.len() != 32 { exit("secret key must be 32 bytes".to_string()); }
+            let mut value = [0u8; 32];
+            value.copy_from_slice(secret.as_slice());
+            let max_transactions = match args.get(5) {
+                Some(limit) => { limit.parse().unwrap_or_else(|_| exit(format!("invalid number: {}", limit))) }
+                None => { snapshot::DEFAULT_DAG_SUFFIX }
+            };
+            let sk = crypto::key::Sk::new(&value);
+            if let Err(err) = snapshot::export_file(&ledger, args[3].as_str(), &sk, max_transactions) {
+                exit(format!("export failed: {:?}", err));
+            }
+            println!("exported state root 0x{} to {}", hex::encode(ledger.state_root().as_bytes()), args[3]);
+        }
+        "import" => {
+            let authorities: Vec<Address> = args[4..].iter().map(|arg| {
+                let address = decode_hex(arg.as_str());
+                if address.len() != 20 { exit(format!("invalid address: {}", arg)); }
+                Address::from_slice(address.as_slice())
+            }).collect();
+            match snapshot::import_file(&mut ledger, args[3].as_str(), &authorities) {
+                Ok(imported) => {
+                    println!("imported milestone {} with state root 0x{} ({} accounts, {} transactions)",
+                             imported.milestone.height, hex::encode(imported.state_root.as_bytes()),
+                             imported.accounts.len(), imported.transactions.len());
+                }
+                Err(err) => { exit(format!("import failed: {:?}", err)); }
+            }
+        }
+        _ => { exit(USAGE.to_string()); }
+    }
+}
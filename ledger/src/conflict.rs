@@ -0,0 +1,127 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+use crate::milestone::to_height;
+use crate::transaction::Transaction;
+
+/// sender || nonce(u64 big endian) || tx hash -> ()
+pub const TX_NONCE_COLUMN: &str = "tx_nonce";
+/// tx hash -> sender || nonce(u64 big endian)
+pub const TX_SENDER_COLUMN: &str = "tx_sender";
+/// tx hash -> 충돌에서 이긴 트랜잭션의 hash
+pub const TX_REJECTED_COLUMN: &str = "tx_rejected";
+/// sender || nonce(u64 big endian) -> rlp(Evidence)
+pub const EVIDENCE_COLUMN: &str = "evidence";
+
+/// 트랜잭션의 상태
+#[derive(Debug, Eq, PartialEq)]
+pub enum TxStatus {
+    Unknown,            // 저장되지 않은 트랜잭션
+    Pending,            // 아직 마일스톤에 포함되지 않은 트랜잭션
+    Final(u64),         // 트랜잭션을 확정한 마일스톤의 height
+    Rejected(H256),     // 같은 sender와 nonce의 다른 트랜잭션에 밀려난 트랜잭션. 이긴 트랜잭션의 hash를 갖는다.
+}
+
+/// validator가 같은 nonce로 서로 다른 트랜잭션을 서명했다는 증거
+/// transactions는 hash 오름차순이며 두 개 이상이다.
+pub struct Evidence {
+    pub sender: Address,
+    pub nonce: u64,
+    pub transactions: Vec<Transaction>,
+}
+
+impl Encodable for Evidence {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.sender);
+        s.append(&self.nonce);
+        s.append_list(&self.transactions);
+    }
+}
+
+impl Decodable for Evidence {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Evidence { sender: rlp.val_at(0)?, nonce: rlp.val_at(1)?, transactions: rlp.list_at(2)? })
+    }
+}
+
+fn sender_key(sender: &Address, nonce: u64) -> Vec<u8> {
+    [sender.as_bytes(), &nonce.to_be_bytes()].concat()
+}
+
+/// 같은 sender와 nonce의 트랜잭션 중 이기는 트랜잭션
+/// 먼저 마일스톤에 포함된 트랜잭션이 이기며, 같은 마일스톤에 포함되었거나 아직 확정되지 않았다면 hash가 작은 것이 이긴다.
+/// * `candidates` - 트랜잭션의 hash와 확정된 마일스톤의 height
+pub fn choose_winner(candidates: &Vec<(H256, Option<u64>)>) -> Option<H256> {
+    candidates.iter()
+        .min_by(|a, b| (a.1.unwrap_or(u64::MAX), &a.0).cmp(&(b.1.unwrap_or(u64::MAX), &b.0)))
+        .map(|(hash, _)| hash.clone())
+}
+
+/// 같은 sender가 같은 nonce로 서명한 트랜잭션들(이중 제출이나 equivocation)의 충돌
+/// 충돌에서 진 트랜잭션은 DAG에 남아있지만 거부된 것으로 기록된다.
+pub struct ConflictManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl ConflictManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        ConflictManager { backend }
+    }
+
+    /// 트랜잭션의 sender와 nonce를 batch에 기록한다.
+    pub fn put_sender(&self, batch: &mut WriteBatch, hash: &H256, sender: &Address, nonce: u64) {
+        let key = sender_key(sender, nonce);
+        batch.put(TX_NONCE_COLUMN, &[key.as_slice(), hash.as_bytes()].concat(), &[]);
+        batch.put(TX_SENDER_COLUMN, hash.as_bytes(), &key);
+    }
+
+    /// 기록된 트랜잭션의 sender와 nonce
+    pub fn sender(&self, hash: &H256) -> Option<(Address, u64)> {
+        let value = self.backend.get(TX_SENDER_COLUMN, hash.as_bytes())?;
+        if value.len() != 28 { return None; }
+        Some((Address::from_slice(&value[..20]), to_height(&value[20..])?))
+    }
+
+    /// sender가 nonce로 서명한 트랜잭션들의 hash
+    pub fn transactions(&self, sender: &Address, nonce: u64) -> Vec<H256> {
+        self.backend.scan(TX_NONCE_COLUMN, &sender_key(sender, nonce)).into_iter()
+            .filter(|(key, _)| key.len() == 60)
+            .map(|(key, _)| H256::from_slice(&key[28..]))
+            .collect()
+    }
+
+    /// 트랜잭션이 충돌에서 졌다면 이긴 트랜잭션의 hash
+    pub fn rejected_by(&self, hash: &H256) -> Option<H256> {
+        let value = self.backend.get(TX_REJECTED_COLUMN, hash.as_bytes())?;
+        if value.len() != 32 { return None; }
+        Some(H256::from_slice(value.as_slice()))
+    }
+
+    /// winner가 이기고 나머지 트랜잭션들은 거부되었음을 batch에 기록한다.
+    pub fn put_winner(&self, batch: &mut WriteBatch, winner: &H256, hashes: &Vec<H256>) {
+        for hash in hashes.iter() {
+            match hash == winner {
+                true => { batch.delete(TX_REJECTED_COLUMN, hash.as_bytes()); }
+                false => { batch.put(TX_REJECTED_COLUMN, hash.as_bytes(), winner.as_bytes()); }
+            }
+        }
+    }
+
+    pub fn put_evidence(&self, batch: &mut WriteBatch, evidence: &Evidence) {
+        batch.put(EVIDENCE_COLUMN, &sender_key(&evidence.sender, evidence.nonce), &rlp::encode(evidence));
+    }
+
+    pub fn get_evidence(&self, sender: &Address, nonce: u64) -> Option<Evidence> {
+        let value = self.backend.get(EVIDENCE_COLUMN, &sender_key(sender, nonce))?;
+        rlp::decode(value.as_slice()).ok()
+    }
+
+    /// 기록된 모든 증거. sender와 nonce 순서이다.
+    pub fn evidences(&self) -> Vec<Evidence> {
+        self.backend.scan(EVIDENCE_COLUMN, &[]).into_iter()
+            .filter_map(|(_, value)| rlp::decode(value.as_slice()).ok())
+            .collect()
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use ethereum_types::H256;
+use crate::backend::{Backend, WriteBatch};
+
+/// child || parent -> ()
+pub const DAG_PARENT_COLUMN: &str = "dag_parent";
+/// parent || child -> ()
+pub const DAG_CHILD_COLUMN: &str = "dag_child";
+/// hash -> () (아직 다른 트랜잭션이 참조하지 않은 트랜잭션)
+pub const DAG_TIP_COLUMN: &str = "dag_tip";
+
+/// DAG에 트랜잭션을 추가하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum DagError {
+    Duplicate(H256),        // 이미 추가된 트랜잭션
+    MissingParent(H256),    // 존재하지 않는 부모 트랜잭션
+    NoParents,              // genesis가 아닌 트랜잭션은 부모를 가져야 한다.
+    Database,
+}
+
+/// 트랜잭션 사이의 참조 관계(DAG)를 관리한다.
+/// 트랜잭션 자체는 TransactionTableManager에 저장되며 여기에는 hash 사이의 관계만 저장된다.
+/// 부모와 자식 방향 모두 조회할 수 있도록 edge는 두 column에 저장된다.
+pub struct DagTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+fn to_hashes(rows: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<H256> {
+    let mut hashes: Vec<H256> = rows.iter()
+        .filter(|(key, _)| key.len() == 64)
+        .map(|(key, _)| H256::from_slice(&key[32..]))
+        .collect();
+    hashes.sort();
+    hashes
+}
+
+impl DagTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        DagTableManager { backend }
+    }
+
+    /// DAG에 추가된 트랜잭션이 하나도 없다면 true
+    pub fn is_empty(&self) -> bool {
+        self.backend.last(DAG_TIP_COLUMN, &[]).is_none()
+    }
+
+    /// 트랜잭션을 DAG에 연결하는 변경을 batch에 기록한다.
+    /// 부모들은 더 이상 tip이 아니게 되며 트랜잭션이 새로운 tip이 된다.
+    pub fn put_edges(&self, batch: &mut WriteBatch, hash: &H256, parents: &Vec<H256>) {
+        for parent in parents.iter() {
+            batch.put(DAG_PARENT_COLUMN, &[hash.as_bytes(), parent.as_bytes()].concat(), &[]);
+            batch.put(DAG_CHILD_COLUMN, &[parent.as_bytes(), hash.as_bytes()].concat(), &[]);
+            batch.delete(DAG_TIP_COLUMN, parent.as_bytes());
+        }
+        batch.put(DAG_TIP_COLUMN, hash.as_bytes(), &[]);
+    }
+
+    /// 트랜잭션을 DAG에 연결한다.
+    /// 부모의 존재 여부는 호출하는 쪽(Ledger::add_transaction)에서 확인한다.
+    pub fn insert(&self, hash: &H256, parents: &Vec<H256>) -> Result<(), DagError> {
+        let mut batch = WriteBatch::new();
+        self.put_edges(&mut batch, hash, parents);
+        self.backend.write(batch).map_err(|_| DagError::Database)
+    }
+
+    /// 아직 자식이 없는 트랜잭션들 (hash 오름차순)
+    pub fn tips(&self) -> Vec<H256> {
+        self.backend.scan(DAG_TIP_COLUMN, &[]).iter()
+            .filter(|(key, _)| key.len() == 32)
+            .map(|(key, _)| H256::from_slice(key.as_slice()))
+            .collect()
+    }
+
+    pub fn parents(&self, hash: &H256) -> Vec<H256> {
+        to_hashes(self.backend.scan(DAG_PARENT_COLUMN, hash.as_bytes()))
+    }
+
+    pub fn children(&self, hash: &H256) -> Vec<H256> {
+        to_hashes(self.backend.scan(DAG_CHILD_COLUMN, hash.as_bytes()))
+    }
+
+    /// hash에서 next 방향으로 도달할 수 있는 모든 트랜잭션 (hash 오름차순)
+    fn reachable(&self, hash: &H256, next: &dyn Fn(&H256) -> Vec<H256>) -> Vec<H256> {
+        let mut visited = BTreeSet::new();
+        let mut queue: VecDeque<H256> = next(hash).into_iter().collect();
+        while let Some(node) = queue.pop_front() {
+            if !visited.insert(node.clone()) { continue; }
+            queue.extend(next(&node).into_iter());
+        }
+        visited.into_iter().collect()
+    }
+
+    /// hash가 직간접적으로 참조하는 모든 트랜잭션 (hash 오름차순)
+    pub fn ancestors(&self, hash: &H256) -> Vec<H256> {
+        self.reachable(hash, &|node| self.parents(node))
+    }
+
+    /// hash를 직간접적으로 참조하는 모든 트랜잭션 (hash 오름차순)
+    pub fn descendants(&self, hash: &H256) -> Vec<H256> {
+        self.reachable(hash, &|node| self.children(node))
+    }
+
+    /// 모든 트랜잭션을 부모가 자식보다 먼저 오도록 정렬한다.
+    /// 순서가 정해지지 않는 트랜잭션끼리는 hash가 작은 것이 먼저 오므로 모든 노드에서 같은 결과를 얻는다.
+    pub fn topological_order(&self) -> Vec<H256> {
+        let mut nodes = BTreeSet::<H256>::new();
+        let mut parents = HashMap::<H256, Vec<H256>>::new();
+        let mut children = HashMap::<H256, Vec<H256>>::new();
+        for (key, _) in self.backend.scan(DAG_PARENT_COLUMN, &[]).into_iter() {
+            if key.len() != 64 { continue; }
+            let child = H256::from_slice(&key[..32]);
+            let parent = H256::from_slice(&key[32..]);
+            nodes.insert(child.clone());
+            nodes.insert(parent.clone());
+            parents.entry(child.clone()).or_insert_with(Vec::new).push(parent.clone());
+            children.entry(parent).or_insert_with(Vec::new).push(child);
+        }
+        // 자식과 부모가 모두 없는 트랜잭션(genesis 하나뿐인 DAG)은 tip에만 나타난다.
+        for hash in self.tips().into_iter() { nodes.insert(hash); }
+
+        let mut remaining: HashMap<H256, usize> = nodes.iter()
+            .map(|node| (node.clone(), parents.get(node).map_or(0, |p| p.len())))
+            .collect();
+        let mut ready: BTreeSet<H256> = nodes.iter()
+            .filter(|node| remaining[*node] == 0).cloned().collect();
+        let mut order = Vec::with_capacity(nodes.len());
+        while let Some(node) = ready.iter().next().cloned() {
+            ready.remove(&node);
+            if let Some(next) = children.get(&node) {
+                for child in next.iter() {
+                    let count = remaining.get_mut(child).unwrap();
+                    *count -= 1;
+                    if *count == 0 { ready.insert(child.clone()); }
+                }
+            }
+            order.push(node);
+        }
+        order
+    }
+}
@@ -0,0 +1,89 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+use crate::device_call::DeviceCallTransaction;
+use crate::milestone::to_height;
+
+/// device address -> rlp(Device)
+pub const DEVICE_COLUMN: &str = "device";
+/// device address -> 다음 device call의 nonce(u64 big endian)
+pub const DEVICE_NONCE_COLUMN: &str = "device_nonce";
+/// tx hash -> rlp(DeviceCallTransaction)
+pub const DEVICE_CALL_COLUMN: &str = "device_call";
+
+/// ledger에 등록된 device
+/// owner는 device를 등록한 계정이며 device에 대한 명령을 서명할 수 있다.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Device {
+    pub address: Address,
+    pub owner: Address,
+    pub name: String,
+}
+
+impl Encodable for Device {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.address);
+        s.append(&self.owner);
+        s.append(&self.name);
+    }
+}
+
+impl Decodable for Device {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Device {
+            address: rlp.val_at(0)?,
+            owner: rlp.val_at(1)?,
+            name: rlp.val_at(2)?,
+        })
+    }
+}
+
+pub struct DeviceTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl DeviceTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        DeviceTableManager { backend }
+    }
+
+    pub fn exist(&self, address: &Address) -> bool {
+        self.backend.exists(DEVICE_COLUMN, address.as_bytes())
+    }
+
+    pub fn get_device(&self, address: &Address) -> Option<Device> {
+        let value = self.backend.get(DEVICE_COLUMN, address.as_bytes())?;
+        rlp::decode(value.as_slice()).ok()
+    }
+
+    /// device를 batch에 기록한다. 이미 등록된 device라면 바뀐다.
+    pub fn put_device(&self, batch: &mut WriteBatch, device: &Device) {
+        batch.put(DEVICE_COLUMN, device.address.as_bytes(), &rlp::encode(device));
+    }
+
+    pub fn insert_device(&self, device: &Device) -> Result<(), ()> {
+        if self.exist(&device.address) { return Err(()); }
+        let mut batch = WriteBatch::new();
+        self.put_device(&mut batch, device);
+        self.backend.write(batch)
+    }
+
+    /// device에 반영된 다음 device call의 nonce
+    pub fn next_nonce(&self, address: &Address) -> u64 {
+        let value = self.backend.get(DEVICE_NONCE_COLUMN, address.as_bytes());
+        value.and_then(|value| to_height(value.as_slice())).unwrap_or(0)
+    }
+
+    /// device call과 device의 다음 nonce를 batch에 기록한다.
+    pub fn put_call(&self, batch: &mut WriteBatch, tx: &DeviceCallTransaction) {
+        batch.put(DEVICE_CALL_COLUMN, tx.hash().as_bytes(), &rlp::encode(tx));
+        batch.put(DEVICE_NONCE_COLUMN, tx.device.as_bytes(), &(tx.nonce + 1).to_be_bytes());
+    }
+
+    pub fn get_call(&self, hash: &H256) -> Option<DeviceCallTransaction> {
+        let value = self.backend.get(DEVICE_CALL_COLUMN, hash.as_bytes())?;
+        rlp::decode(value.as_slice()).ok()
+    }
+}
@@ -0,0 +1,182 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use crate::transaction::recover_sender;
+
+/// device call 트랜잭션의 type (EIP-2718의 범위 안에서 이 chain이 사용하는 값)
+pub const DEVICE_CALL_TX_TYPE: u8 = 0x70;
+
+/// device call을 받지 못하거나 반영하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum DeviceCallError {
+    InvalidSignature,       // 서명에서 sender를 복구할 수 없음
+    ChainId(u64),           // 다른 chain을 위해 서명됨. 이 chain의 id를 갖는다.
+    UnknownDevice(Address), // 등록되지 않은 device
+    NotOwner(Address),      // device의 owner가 아닌 계정이 서명함. owner를 갖는다.
+    EmptyCommand,
+    Nonce(u64),             // device의 다음 nonce와 다름. 기대한 nonce를 갖는다.
+    AlreadyKnown(H256),     // 이미 pool에 있거나 반영된 device call
+    Full,                   // pool이 가득 참
+    Database,
+}
+
+/// device에 명령을 보내는 트랜잭션
+/// 컨트랙트 호출과 달리 DAG와 마일스톤을 거치지 않고 바로 반영되며, device의 owner만 서명할 수 있다.
+/// nonce는 계정이 아닌 device마다 증가한다.
+/// 0x70 || rlp([chainId, nonce, device, command, args, yParity, r, s])
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeviceCallTransaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub device: Address,
+    pub command: String,
+    pub args: Vec<Vec<u8>>,
+    pub y_parity: u8,
+    pub r: Vec<u8>,
+    pub s: Vec<u8>,
+}
+
+impl DeviceCallTransaction {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecoderError> {
+        match bytes.first() {
+            Some(&DEVICE_CALL_TX_TYPE) => { rlp::decode(&bytes[1..]) }
+            _ => { Err(DecoderError::Custom("not a device call")) }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&[DEVICE_CALL_TX_TYPE], rlp::encode(self).as_ref()].concat()
+    }
+
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(self.to_bytes().as_slice()))
+    }
+
+    fn append_payload(&self, s: &mut RlpStream) {
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.device);
+        s.append(&self.command);
+        s.append_list::<Vec<u8>, _>(&self.args);
+    }
+
+    /// keccak256(0x70 || rlp([chainId, nonce, device, command, args]))
+    pub fn signing_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(5);
+        self.append_payload(&mut s);
+        H256::from(crypto::hash::keccak256(&[&[DEVICE_CALL_TX_TYPE], s.out().as_ref()].concat()))
+    }
+
+    pub fn sign(&mut self, sk: &crypto::key::Sk) {
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.y_parity = rec_id as u8;
+        self.r = signature[..32].to_vec();
+        self.s = signature[32..].to_vec();
+    }
+
+    pub fn try_get_sender(&self) -> Option<Address> {
+        if self.y_parity > 1 { return None; }
+        recover_sender(self.y_parity as i32, &self.r, &self.s, &self.signing_hash())
+    }
+}
+
+impl Encodable for DeviceCallTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(8);
+        self.append_payload(s);
+        s.append(&self.y_parity);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for DeviceCallTransaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 8 { return Err(DecoderError::RlpIncorrectListLen); }
+        Ok(DeviceCallTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            device: rlp.val_at(2)?,
+            command: rlp.val_at(3)?,
+            args: rlp.list_at(4)?,
+            y_parity: rlp.val_at(5)?,
+            r: rlp.val_at(6)?,
+            s: rlp.val_at(7)?
+        })
+    }
+}
+
+struct DevicePoolState {
+    calls: BTreeMap<(Address, u64), (H256, Address, DeviceCallTransaction)>,
+    by_hash: HashMap<H256, (Address, u64)>,
+}
+
+/// 반영을 기다리는 device call들
+/// 컨트랙트 호출의 TxPool과 분리되어 있으며, 수수료가 없으므로 device마다 nonce 순서대로만 받고 교체하지 않는다.
+pub struct DeviceCallPool {
+    capacity: usize,
+    state: Mutex<DevicePoolState>,
+}
+
+impl DeviceCallPool {
+    pub fn new() -> Self {
+        DeviceCallPool::with_capacity(1024)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let state = DevicePoolState { calls: BTreeMap::new(), by_hash: HashMap::new() };
+        DeviceCallPool { capacity, state: Mutex::new(state) }
+    }
+
+    pub fn len(&self) -> usize { self.state.lock().unwrap().by_hash.len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub fn contains(&self, hash: &H256) -> bool { self.state.lock().unwrap().by_hash.contains_key(hash) }
+
+    /// pool의 device call까지 포함한 device의 다음 nonce
+    /// * `committed` - 반영된 device의 다음 nonce
+    pub fn pending_nonce(&self, device: &Address, committed: u64) -> u64 {
+        let state = self.state.lock().unwrap();
+        let mut nonce = committed;
+        while state.calls.contains_key(&(device.clone(), nonce)) { nonce += 1; }
+        nonce
+    }
+
+    /// device call을 pool에 추가한다. nonce는 pool까지 포함한 device의 다음 nonce여야 한다.
+    pub fn admit(&self, sender: &Address, tx: DeviceCallTransaction, committed: u64) -> Result<H256, DeviceCallError> {
+        let hash = tx.hash();
+        let pending_nonce = self.pending_nonce(&tx.device, committed);
+        let mut state = self.state.lock().unwrap();
+        if state.by_hash.contains_key(&hash) { return Err(DeviceCallError::AlreadyKnown(hash)); }
+        if tx.nonce != pending_nonce { return Err(DeviceCallError::Nonce(pending_nonce)); }
+        if state.by_hash.len() >= self.capacity { return Err(DeviceCallError::Full); }
+        let key = (tx.device.clone(), tx.nonce);
+        state.by_hash.insert(hash.clone(), key.clone());
+        state.calls.insert(key, (hash.clone(), sender.clone(), tx));
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &H256) -> Option<(Address, DeviceCallTransaction)> {
+        let state = self.state.lock().unwrap();
+        let key = state.by_hash.get(hash)?;
+        state.calls.get(key).map(|(_, sender, tx)| (sender.clone(), tx.clone()))
+    }
+
+    /// 반영할 device call을 최대 limit개 고른다. device와 nonce 순서이다.
+    pub fn select(&self, limit: usize) -> Vec<(Address, DeviceCallTransaction)> {
+        let state = self.state.lock().unwrap();
+        state.calls.values().take(limit).map(|(_, sender, tx)| (sender.clone(), tx.clone())).collect()
+    }
+
+    /// 반영되어 더 이상 실행할 수 없는 device call을 제거한다.
+    pub fn prune(&self, device: &Address, committed: u64) {
+        let mut state = self.state.lock().unwrap();
+        let stale: Vec<(Address, u64)> = state.calls.range((device.clone(), 0)..(device.clone(), committed))
+            .map(|(key, _)| key.clone()).collect();
+        for key in stale.iter() {
+            if let Some((hash, _, _)) = state.calls.remove(key) { state.by_hash.remove(&hash); }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use ethereum_types::{H256, Address};
 use rlp::{Encodable, Decodable, RlpStream, DecoderError, Rlp};
 
@@ -29,22 +29,12 @@ impl DirtyKeyValues {
 
     /// HashMap을 Key 값을 기준으로 오름차 정렬한 vector로 반환하는 메서드
     fn sort_to_vec(&self) -> Vec<DirtyKeyValue> {
-        let mut v = Vec::<DirtyKeyValue>::new();
-        for element in self.0.iter() {
-            let pos = self.get_pos(&v, element.0);
-            v.insert(pos, DirtyKeyValue { 0: element.0.clone(), 1: element.1.clone() });
-        }
+        let mut v: Vec<DirtyKeyValue> = self.0.iter()
+            .map(|(key, value)| DirtyKeyValue { 0: key.clone(), 1: value.clone() })
+            .collect();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
         return v;
     }
-
-    /// 주어진 리스트 l에서 new_key가 들어갈 곳의 index를 반환하는 메서드
-    fn get_pos(&self, l: &Vec<DirtyKeyValue>, new_key: &H256) -> usize {
-        for idx in 0..l.len() {
-            let key = l.get(idx).unwrap().clone().0;
-            if key < new_key.clone() { return idx; }
-        }
-        return 0;
-    }
 }
 
 impl From<HashMap<H256, H256>> for DirtyKeyValues {
@@ -110,54 +100,120 @@ impl Decodable for DirtyState {
 }
 
 
-pub struct DirtyStates(HashMap<Address, DirtyKeyValues>);
+/// 트랜잭션의 상태 변화. storage 변경과 트랜잭션이 끝날 때 제거되는 account(SELFDESTRUCT)로 이루어진다.
+pub struct DirtyStates(HashMap<Address, DirtyKeyValues>, BTreeSet<Address>);
+
+impl Default for DirtyStates {
+    fn default() -> Self { DirtyStates::new() }
+}
 
 impl DirtyStates {
-    pub fn new() -> Self { DirtyStates { 0: HashMap::new() } }
+    pub fn new() -> Self { DirtyStates { 0: HashMap::new(), 1: BTreeSet::new() } }
 
-    fn sort_to_vec(&self) -> Vec<DirtyState> {
-        let mut v: Vec<DirtyState> = vec![];
-        for element in self.0.iter() {
-            let map_key = element.0.clone();
-            let map_vec_pos = self.get_pos(&v, element.0);
-            let kvs = element.1.clone();
-            let state = DirtyState { 0: map_key, 1: kvs };
-            v.insert(map_vec_pos, state);
+    /// 트랜잭션 실행 중 변경된 storage 값을 기록한다.
+    pub fn set_value(&mut self, address: &Address, key: &H256, value: &H256) {
+        let kvs = self.0.entry(address.clone()).or_insert(DirtyKeyValues::new());
+        kvs.0.insert(key.clone(), value.clone());
+    }
+
+    /// 트랜잭션 실행 중 변경된 storage 값을 반환한다. 변경되지 않은 값은 None이다.
+    pub fn get_value(&self, address: &Address, key: &H256) -> Option<H256> {
+        match self.0.get(address) {
+            None => { None }
+            Some(kvs) => { kvs.0.get(key).cloned() }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() && self.1.is_empty() }
+
+    /// account를 제거한다. 제거는 storage 변경보다 나중에 반영되므로 같은 account의 변경은 버려진다.
+    pub fn remove_account(&mut self, address: &Address) {
+        self.1.insert(address.clone());
+    }
+
+    /// 제거되는 account들. address 오름차순이다.
+    pub fn removed(&self) -> Vec<Address> { self.1.iter().cloned().collect() }
+
+    pub fn is_removed(&self, address: &Address) -> bool { self.1.contains(address) }
+
+    /// other의 변경과 제거를 이어서 반영한다.
+    pub fn extend(&mut self, other: &DirtyStates) {
+        for (address, kvs) in other.0.iter() {
+            for (key, value) in kvs.0.iter() { self.set_value(address, key, value); }
+        }
+        for address in other.1.iter() { self.remove_account(address); }
+    }
+
+    pub fn addresses(&self) -> Vec<Address> { self.0.keys().cloned().collect() }
+
+    /// 주어진 address에서 변경된 (key, value) 목록을 반환한다.
+    pub fn changes(&self, address: &Address) -> Vec<(H256, H256)> {
+        match self.0.get(address) {
+            None => { vec![] }
+            Some(kvs) => { kvs.0.iter().map(|(k, v)| (k.clone(), v.clone())).collect() }
         }
+    }
+
+    /// address를 기준으로 오름차 정렬한 vector로 반환하는 메서드
+    fn sort_to_vec(&self) -> Vec<DirtyState> {
+        let mut v: Vec<DirtyState> = self.0.iter()
+            .map(|(address, kvs)| DirtyState { 0: address.clone(), 1: kvs.clone() })
+            .collect();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
         return v;
     }
 
-    fn get_pos(&self, l: &Vec<DirtyState>, new_address: &Address) -> usize {
-        for address_idx in 0..l.len() {
-            let address = l.get(address_idx).unwrap().clone().0;
-            if address < new_address.clone() { return address_idx; }
+    /// 트랜잭션의 state_hash와 비교하는 값이며 keccak256(rlp(self))이다.
+    /// address와 key가 정렬되어 인코딩되므로 같은 변경이라면 항상 같은 값을 갖는다.
+    /// 제거되는 account도 포함되므로 SELFDESTRUCT도 state_hash로 검증된다.
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(rlp::encode(self).as_ref()))
+    }
+}
+
+impl Clone for DirtyStates {
+    fn clone(&self) -> Self {
+        let mut result = DirtyStates::new();
+        for element in self.0.iter() {
+            result.0.insert(element.0.clone(), element.1.clone());
         }
-        return 0;
+        result.1 = self.1.clone();
+        return result;
     }
 }
 
 impl From<HashMap<Address, DirtyKeyValues>> for DirtyStates {
     fn from(account_states: HashMap<Address, DirtyKeyValues>) -> Self {
-        DirtyStates { 0: account_states }
+        DirtyStates { 0: account_states, 1: BTreeSet::new() }
     }
 }
 
+/// 변경된 account마다 [address, [[key, value]]]이며 제거되는 account는 그 뒤에 [address]로 인코딩된다.
+/// 제거되는 account가 없다면 이전과 같은 값이다.
 impl Encodable for DirtyStates {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(self.0.len());
+        s.begin_list(self.0.len() + self.1.len());
         for state in self.sort_to_vec().iter() {
             s.append(state);
         }
+        for address in self.1.iter() {
+            s.begin_list(1);
+            s.append(address);
+        }
     }
 }
 
 impl Decodable for DirtyStates {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
-        let mut result = DirtyStates {0: HashMap::new()};
-        let size = rlp.size();
-        for state_idx in 0..size {
-            let state: DirtyState = rlp.val_at(state_idx).unwrap();
-            result.0.insert(state.0, state.1);
+        let mut result = DirtyStates::new();
+        for item in rlp.iter() {
+            match item.item_count()? {
+                1 => { result.1.insert(item.val_at(0)?); }
+                _ => {
+                    let state: DirtyState = item.as_val()?;
+                    result.0.insert(state.0, state.1);
+                }
+            }
         }
         return Ok(result);
     }
@@ -0,0 +1,335 @@
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use ethereum_types::{U256, Address, H256};
+use crate::transaction::{RawTransaction, Transaction, recover_sender};
+
+/// EIP-2930 access list 트랜잭션의 type
+pub const ACCESS_LIST_TX_TYPE: u8 = 0x01;
+/// EIP-1559 dynamic fee 트랜잭션의 type
+pub const DYNAMIC_FEE_TX_TYPE: u8 = 0x02;
+
+/// 트랜잭션이 접근할 계정과 storage key (EIP-2930)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<H256>,
+}
+
+impl Encodable for AccessListItem {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.address);
+        s.append_list(&self.storage_keys);
+    }
+}
+
+impl Decodable for AccessListItem {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(AccessListItem { address: rlp.val_at(0)?, storage_keys: rlp.list_at(1)? })
+    }
+}
+
+/// EIP-2930 트랜잭션
+/// 0x01 || rlp([chainId, nonce, gasPrice, gas, to, value, data, accessList, yParity, r, s])
+#[derive(Clone)]
+pub struct AccessListTransaction {
+    pub chain_id: u64,
+    pub nonce: usize,
+    pub gas_price: U256,
+    pub gas: U256,
+    pub recipient: Option<Address>,     // 컨트랙트 생성이라면 None
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub y_parity: u8,
+    pub r: Vec<u8>,
+    pub s: Vec<u8>,
+}
+
+impl AccessListTransaction {
+    fn append_payload(&self, s: &mut RlpStream) {
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.gas_price);
+        s.append(&self.gas);
+        append_recipient(s, &self.recipient);
+        s.append(&self.value);
+        s.append(&self.data);
+        s.append_list(&self.access_list);
+    }
+
+    /// keccak256(0x01 || rlp([chainId, nonce, gasPrice, gas, to, value, data, accessList]))
+    pub fn signing_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(8);
+        self.append_payload(&mut s);
+        typed_hash(ACCESS_LIST_TX_TYPE, s.out().as_ref())
+    }
+
+    pub fn sign(&mut self, sk: &crypto::key::Sk) {
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.y_parity = rec_id as u8;
+        self.r = signature[..32].to_vec();
+        self.s = signature[32..].to_vec();
+    }
+}
+
+impl Encodable for AccessListTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(11);
+        self.append_payload(s);
+        s.append(&self.y_parity);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for AccessListTransaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 11 { return Err(DecoderError::RlpIncorrectListLen); }
+        Ok(AccessListTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            gas_price: rlp.val_at(2)?,
+            gas: rlp.val_at(3)?,
+            recipient: decode_recipient(rlp, 4)?,
+            value: rlp.val_at(5)?,
+            data: rlp.val_at(6)?,
+            access_list: rlp.list_at(7)?,
+            y_parity: rlp.val_at(8)?,
+            r: rlp.val_at(9)?,
+            s: rlp.val_at(10)?
+        })
+    }
+}
+
+/// EIP-1559 트랜잭션
+/// 0x02 || rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas, gas, to, value, data, accessList, yParity, r, s])
+#[derive(Clone)]
+pub struct DynamicFeeTransaction {
+    pub chain_id: u64,
+    pub nonce: usize,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas: U256,
+    pub recipient: Option<Address>,     // 컨트랙트 생성이라면 None
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub y_parity: u8,
+    pub r: Vec<u8>,
+    pub s: Vec<u8>,
+}
+
+impl DynamicFeeTransaction {
+    fn append_payload(&self, s: &mut RlpStream) {
+        s.append(&self.chain_id);
+        s.append(&self.nonce);
+        s.append(&self.max_priority_fee_per_gas);
+        s.append(&self.max_fee_per_gas);
+        s.append(&self.gas);
+        append_recipient(s, &self.recipient);
+        s.append(&self.value);
+        s.append(&self.data);
+        s.append_list(&self.access_list);
+    }
+
+    /// keccak256(0x02 || rlp([chainId, nonce, maxPriorityFeePerGas, maxFeePerGas, gas, to, value, data, accessList]))
+    pub fn signing_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(9);
+        self.append_payload(&mut s);
+        typed_hash(DYNAMIC_FEE_TX_TYPE, s.out().as_ref())
+    }
+
+    pub fn sign(&mut self, sk: &crypto::key::Sk) {
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.y_parity = rec_id as u8;
+        self.r = signature[..32].to_vec();
+        self.s = signature[32..].to_vec();
+    }
+}
+
+impl Encodable for DynamicFeeTransaction {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(12);
+        self.append_payload(s);
+        s.append(&self.y_parity);
+        s.append(&self.r);
+        s.append(&self.s);
+    }
+}
+
+impl Decodable for DynamicFeeTransaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 12 { return Err(DecoderError::RlpIncorrectListLen); }
+        Ok(DynamicFeeTransaction {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            max_priority_fee_per_gas: rlp.val_at(2)?,
+            max_fee_per_gas: rlp.val_at(3)?,
+            gas: rlp.val_at(4)?,
+            recipient: decode_recipient(rlp, 5)?,
+            value: rlp.val_at(6)?,
+            data: rlp.val_at(7)?,
+            access_list: rlp.list_at(8)?,
+            y_parity: rlp.val_at(9)?,
+            r: rlp.val_at(10)?,
+            s: rlp.val_at(11)?
+        })
+    }
+}
+
+/// to는 컨트랙트 생성이라면 빈 문자열로 인코딩된다.
+fn append_recipient(s: &mut RlpStream, recipient: &Option<Address>) {
+    match recipient {
+        Some(recipient) => { s.append(recipient); }
+        None => { s.append_empty_data(); }
+    }
+}
+
+fn decode_recipient(rlp: &Rlp, index: usize) -> Result<Option<Address>, DecoderError> {
+    let item = rlp.at(index)?;
+    match item.is_empty() {
+        true => { Ok(None) }
+        false => { item.as_val().map(Some) }
+    }
+}
+
+fn typed_hash(tx_type: u8, payload: &[u8]) -> H256 {
+    H256::from(crypto::hash::keccak256(&[&[tx_type], payload].concat()))
+}
+
+/// EIP-2718 트랜잭션 envelope
+/// legacy 트랜잭션은 rlp list 그대로이며, typed 트랜잭션은 type || rlp(payload)이다.
+#[derive(Clone)]
+pub enum TypedTransaction {
+    Legacy(RawTransaction),
+    AccessList(AccessListTransaction),
+    DynamicFee(DynamicFeeTransaction),
+}
+
+impl TypedTransaction {
+    /// eth_sendRawTransaction으로 받은 bytes를 해석한다.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecoderError> {
+        match bytes.first() {
+            None => { Err(DecoderError::RlpIsTooShort) }
+            Some(&ACCESS_LIST_TX_TYPE) => { Ok(TypedTransaction::AccessList(rlp::decode(&bytes[1..])?)) }
+            Some(&DYNAMIC_FEE_TX_TYPE) => { Ok(TypedTransaction::DynamicFee(rlp::decode(&bytes[1..])?)) }
+            Some(byte) if *byte >= 0xc0 => { Ok(TypedTransaction::Legacy(rlp::decode(bytes)?)) }
+            Some(_) => { Err(DecoderError::Custom("unsupported transaction type")) }
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            TypedTransaction::Legacy(tx) => { rlp::encode(tx).to_vec() }
+            TypedTransaction::AccessList(tx) => { [&[ACCESS_LIST_TX_TYPE], rlp::encode(tx).as_ref()].concat() }
+            TypedTransaction::DynamicFee(tx) => { [&[DYNAMIC_FEE_TX_TYPE], rlp::encode(tx).as_ref()].concat() }
+        }
+    }
+
+    /// EIP-2718 type. legacy는 0이다.
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            TypedTransaction::Legacy(_) => { 0 }
+            TypedTransaction::AccessList(_) => { ACCESS_LIST_TX_TYPE }
+            TypedTransaction::DynamicFee(_) => { DYNAMIC_FEE_TX_TYPE }
+        }
+    }
+
+    /// 트랜잭션 hash (keccak256(envelope)). legacy 트랜잭션은 pool::tx_hash와 같다.
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(self.to_bytes().as_slice()))
+    }
+
+    pub fn nonce(&self) -> u64 {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.nonce as u64 }
+            TypedTransaction::AccessList(tx) => { tx.nonce as u64 }
+            TypedTransaction::DynamicFee(tx) => { tx.nonce as u64 }
+        }
+    }
+
+    /// 서명에 포함된 chain id. EIP-155 이전의 legacy 트랜잭션은 None이다.
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.chain_id() }
+            TypedTransaction::AccessList(tx) => { Some(tx.chain_id) }
+            TypedTransaction::DynamicFee(tx) => { Some(tx.chain_id) }
+        }
+    }
+
+    /// pool의 우선순위와 교체에 사용하는 gas price
+    /// base fee가 없으므로 dynamic fee 트랜잭션은 max_priority_fee_per_gas와 max_fee_per_gas 중 작은 값이다.
+    pub fn gas_price(&self) -> U256 {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.gas_price }
+            TypedTransaction::AccessList(tx) => { tx.gas_price }
+            TypedTransaction::DynamicFee(tx) => { std::cmp::min(tx.max_priority_fee_per_gas, tx.max_fee_per_gas) }
+        }
+    }
+
+    pub fn signing_hash(&self) -> H256 {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.signing_hash() }
+            TypedTransaction::AccessList(tx) => { tx.signing_hash() }
+            TypedTransaction::DynamicFee(tx) => { tx.signing_hash() }
+        }
+    }
+
+    /// 서명한 계정. 서명이 올바르지 않거나 y_parity가 0, 1이 아니라면 None이다.
+    pub fn try_get_sender(&self) -> Option<Address> {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.try_get_sender() }
+            TypedTransaction::AccessList(tx) => {
+                if tx.y_parity > 1 { return None; }
+                recover_sender(tx.y_parity as i32, &tx.r, &tx.s, &self.signing_hash())
+            }
+            TypedTransaction::DynamicFee(tx) => {
+                if tx.y_parity > 1 { return None; }
+                recover_sender(tx.y_parity as i32, &tx.r, &tx.s, &self.signing_hash())
+            }
+        }
+    }
+
+    /// 트랜잭션에 서명한다. legacy 트랜잭션은 chain_id로 EIP-155 서명을 하며 typed 트랜잭션은 자신의 chain id를 사용한다.
+    pub fn sign(&mut self, sk: &crypto::key::Sk, chain_id: u64) {
+        match self {
+            TypedTransaction::Legacy(tx) => { tx.sign(sk, Some(chain_id)) }
+            TypedTransaction::AccessList(tx) => { tx.sign(sk) }
+            TypedTransaction::DynamicFee(tx) => { tx.sign(sk) }
+        }
+    }
+
+    /// DAG에 저장하는 트랜잭션. 컨트랙트 생성이라면 recipient는 0이다.
+    /// typed 트랜잭션의 v는 y_parity(0, 1)이므로 legacy 서명의 v(27, 28 또는 35 이상)와 구분된다.
+    /// chain id, fee, value, access list는 서명된 원본(envelope)으로 함께 저장되어 sender를 다시 복구할 수 있다.
+    pub fn to_transaction(&self) -> Transaction {
+        match self {
+            TypedTransaction::Legacy(tx) => { Transaction::from_raw_transaction(tx) }
+            TypedTransaction::AccessList(tx) => {
+                self.typed_transaction(tx.nonce, &tx.recipient, &tx.data, tx.y_parity, &tx.r, &tx.s)
+            }
+            TypedTransaction::DynamicFee(tx) => {
+                self.typed_transaction(tx.nonce, &tx.recipient, &tx.data, tx.y_parity, &tx.r, &tx.s)
+            }
+        }
+    }
+
+    fn typed_transaction(&self, nonce: usize, recipient: &Option<Address>, data: &Vec<u8>, y_parity: u8, r: &Vec<u8>,
+                         s: &Vec<u8>) -> Transaction {
+        let mut tx = Transaction::default();
+        tx.nonce = nonce;
+        tx.recipient = recipient.clone().unwrap_or_default();
+        tx.data = data.clone();
+        tx.v = y_parity as usize;
+        tx.r = r.clone();
+        tx.s = s.clone();
+        tx.envelope = self.to_bytes();
+        tx
+    }
+}
+
+impl From<RawTransaction> for TypedTransaction {
+    fn from(tx: RawTransaction) -> Self {
+        TypedTransaction::Legacy(tx)
+    }
+}
@@ -0,0 +1,354 @@
+use ethereum_types::{Address, H256};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::account::{AccountNode, ACCOUNT_COLUMN};
+use crate::backend::WriteBatch;
+use crate::dag::{DAG_CHILD_COLUMN, DAG_PARENT_COLUMN, DAG_TIP_COLUMN};
+use crate::dirty_state::DirtyStates;
+use crate::genesis::{Genesis, chain_config, stored_genesis};
+use crate::ledger::Ledger;
+use crate::milestone::{Milestone, MilestoneError, MILESTONE_COLUMN, MILESTONE_HASH_COLUMN, MILESTONE_TX_COLUMN, TX_MILESTONE_COLUMN, to_height};
+use crate::receipt::{Receipt, RECEIPT_COLUMN};
+use crate::transaction::{Transaction, TX_COLUMN};
+use crate::trie::{EMPTY_ROOT, TRIE_NODE_COLUMN};
+
+/// column || 0x00 || key -> 격리된 row의 원래 값
+pub const QUARANTINE_COLUMN: &str = "quarantine";
+
+/// 검사에서 발견된 문제
+#[derive(Debug, Eq, PartialEq)]
+pub enum Problem {
+    Corrupt,                    // 값을 해석할 수 없는 row
+    KeyMismatch,                // key가 값의 hash나 height와 다른 row
+    DanglingReference,          // 존재하지 않는 트랜잭션이나 마일스톤을 가리키는 row
+    MissingParent(H256),        // 저장되지 않은 부모 트랜잭션
+    MissingEdge(H256),          // parent_hash에 있지만 DAG에 연결되지 않은 부모 트랜잭션
+    Unsigned,
+    InvalidSignature,
+    SenderMismatch(Address),    // 서명에서 복원한 sender가 receipt의 sender와 다름
+    StateHash(H256),            // 재실행으로 계산한 state hash가 트랜잭션의 state_hash와 다름
+    Milestone(MilestoneError),
+    UnknownStateRoot(H256),     // trie node가 없는 마일스톤의 state root
+}
+
+impl Problem {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Problem::Corrupt => { "corrupt" }
+            Problem::KeyMismatch => { "key_mismatch" }
+            Problem::DanglingReference => { "dangling_reference" }
+            Problem::MissingParent(_) => { "missing_parent" }
+            Problem::MissingEdge(_) => { "missing_edge" }
+            Problem::Unsigned => { "unsigned" }
+            Problem::InvalidSignature => { "invalid_signature" }
+            Problem::SenderMismatch(_) => { "sender_mismatch" }
+            Problem::StateHash(_) => { "state_hash" }
+            Problem::Milestone(_) => { "invalid_milestone" }
+            Problem::UnknownStateRoot(_) => { "unknown_state_root" }
+        }
+    }
+
+    /// 격리할 수 있는 문제. row 자체가 손상된 경우만 격리하며 나머지는 보고만 한다.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            Problem::Corrupt | Problem::KeyMismatch | Problem::DanglingReference => { true }
+            _ => { false }
+        }
+    }
+
+    fn detail(&self) -> Value {
+        match self {
+            Problem::MissingParent(hash) | Problem::MissingEdge(hash) | Problem::StateHash(hash)
+            | Problem::UnknownStateRoot(hash) => { json!(format!("0x{}", hex::encode(hash.as_bytes()))) }
+            Problem::SenderMismatch(address) => { json!(format!("0x{}", hex::encode(address.as_bytes()))) }
+            Problem::Milestone(err) => { json!(format!("{:?}", err)) }
+            _ => { Value::Null }
+        }
+    }
+}
+
+pub struct Issue {
+    pub column: &'static str,
+    pub key: Vec<u8>,
+    pub problem: Problem,
+    pub quarantined: bool,
+}
+
+/// 검사 결과
+pub struct Report {
+    pub issues: Vec<Issue>,
+    pub transactions: usize,
+    pub milestones: usize,
+    pub replayed: usize,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool { self.issues.is_empty() }
+
+    /// fsck와 같은 종료 코드. 0은 문제 없음, 1은 발견된 문제가 모두 격리됨, 4는 남아있는 문제가 있음이다.
+    pub fn exit_code(&self) -> i32 {
+        if self.issues.iter().any(|issue| !issue.quarantined) { return 4; }
+        if !self.issues.is_empty() { return 1; }
+        0
+    }
+
+    pub fn to_json(&self) -> Value {
+        let issues: Vec<Value> = self.issues.iter().map(|issue| json!({
+            "column": issue.column,
+            "key": format!("0x{}", hex::encode(issue.key.as_slice())),
+            "problem": issue.problem.name(),
+            "detail": issue.problem.detail(),
+            "quarantined": issue.quarantined,
+        })).collect();
+        json!({
+            "clean": self.is_clean(),
+            "transactions": self.transactions,
+            "milestones": self.milestones,
+            "replayed": self.replayed,
+            "issues": issues,
+        })
+    }
+}
+
+/// 트랜잭션을 다시 실행하여 storage 변경을 계산한다. ledger crate는 vm에 의존할 수 없으므로 vm이 구현한다.
+pub trait Replay {
+    /// ledger는 검사하는 ledger이며 state는 앞선 트랜잭션들의 재실행 결과가 반영된 ledger이다.
+    /// 다시 실행할 수 없는 트랜잭션은 None을 반환한다.
+    fn replay(&self, ledger: &Ledger, state: &Arc<Ledger>, tx: &Transaction, receipt: &Receipt) -> Option<DirtyStates>;
+}
+
+pub struct CheckOptions {
+    pub authorities: Vec<Address>,  // 비어있다면 genesis의 authority로 검사하며 genesis도 없다면 서명자를 검사하지 않는다.
+    pub quarantine: bool,           // 손상된 row를 quarantine column으로 옮긴다.
+}
+
+/// Ledger::add_milestone이 거부했을 마일스톤이라면 그 이유
+fn validate_milestone(milestone: &Milestone, previous: Option<&Milestone>, transactions: &HashSet<H256>,
+                      authorities: &Vec<Address>) -> Option<MilestoneError> {
+    let height = previous.map_or(0, |previous| previous.height + 1);
+    if milestone.height != height { return Some(MilestoneError::InvalidHeight(height)); }
+    let signer = match milestone.signer() {
+        Some(signer) => { signer }
+        None => { return Some(MilestoneError::InvalidSignature); }
+    };
+    if !authorities.is_empty() && !authorities.contains(&signer) { return Some(MilestoneError::Unauthorized(signer)); }
+    if previous.map_or(false, |previous| milestone.timestamp < previous.timestamp) {
+        return Some(MilestoneError::InvalidTimestamp);
+    }
+    if milestone.tips.is_empty() { return Some(MilestoneError::NoTips); }
+    milestone.tips.iter().find(|tip| !transactions.contains(tip)).map(|tip| MilestoneError::UnknownTip(tip.clone()))
+}
+
+struct Checker<'a> {
+    ledger: &'a Ledger,
+    genesis: Option<Genesis>,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, column: &'static str, key: &[u8], problem: Problem) {
+        self.issues.push(Issue { column, key: key.to_vec(), problem, quarantined: false });
+    }
+
+    /// 모든 트랜잭션의 hash, 부모, 서명을 검사하고 올바른 트랜잭션의 hash들을 반환한다.
+    fn check_transactions(&mut self) -> HashSet<H256> {
+        let backend = self.ledger.get_backend();
+        let mut valid = HashSet::new();
+        let genesis = self.genesis.as_ref().map(|genesis| genesis.transaction().hash());
+        let rows = backend.scan(TX_COLUMN, &[]);
+        let stored: HashSet<Vec<u8>> = rows.iter().map(|(key, _)| key.clone()).collect();
+        for (key, value) in rows.iter() {
+            let tx = match rlp::decode::<Transaction>(value.as_slice()) {
+                Ok(tx) => { tx }
+                Err(_) => { self.report(TX_COLUMN, key, Problem::Corrupt); continue; }
+            };
+            let hash = tx.hash();
+            if hash.as_bytes() != key.as_slice() { self.report(TX_COLUMN, key, Problem::KeyMismatch); continue; }
+            for parent in tx.parents().iter() {
+                if !stored.contains(parent.as_bytes()) {
+                    self.report(TX_COLUMN, key, Problem::MissingParent(parent.clone()));
+                } else if !backend.exists(DAG_PARENT_COLUMN, &[key.as_slice(), parent.as_bytes()].concat()) {
+                    self.report(TX_COLUMN, key, Problem::MissingEdge(parent.clone()));
+                }
+            }
+            if Some(&hash) == genesis.as_ref() {
+                // genesis 트랜잭션은 서명되지 않는다.
+            } else if tx.r.is_empty() && tx.s.is_empty() {
+                self.report(TX_COLUMN, key, Problem::Unsigned);
+            } else {
+                match tx.try_get_sender() {
+                    None => { self.report(TX_COLUMN, key, Problem::InvalidSignature); }
+                    Some(sender) => {
+                        let receipt = self.ledger.get_receipts().get_receipt(&hash);
+                        if receipt.map_or(false, |receipt| receipt.from != sender) {
+                            self.report(TX_COLUMN, key, Problem::SenderMismatch(sender));
+                        }
+                    }
+                }
+            }
+            valid.insert(hash);
+        }
+        valid
+    }
+
+    /// DAG의 edge와 tip이 올바른 트랜잭션을 가리키는지 검사한다.
+    fn check_dag(&mut self, transactions: &HashSet<H256>) {
+        let backend = self.ledger.get_backend();
+        for (column, mirror) in vec![(DAG_PARENT_COLUMN, DAG_CHILD_COLUMN), (DAG_CHILD_COLUMN, DAG_PARENT_COLUMN)] {
+            for (key, _) in backend.scan(column, &[]).into_iter() {
+                let valid = key.len() == 64
+                    && transactions.contains(&H256::from_slice(&key[..32]))
+                    && transactions.contains(&H256::from_slice(&key[32..]))
+                    && backend.exists(mirror, &[&key[32..], &key[..32]].concat());
+                if !valid { self.report(column, &key, Problem::DanglingReference); }
+            }
+        }
+        // 부모 방향 edge는 자식 트랜잭션의 parent_hash에도 있어야 한다.
+        for (key, _) in backend.scan(DAG_PARENT_COLUMN, &[]).into_iter().filter(|(key, _)| key.len() == 64) {
+            let child = self.ledger.get_transactions().get_transaction(&H256::from_slice(&key[..32]));
+            if child.map_or(false, |child| !child.parents().contains(&H256::from_slice(&key[32..]))) {
+                self.report(DAG_PARENT_COLUMN, &key, Problem::DanglingReference);
+            }
+        }
+        for (key, _) in backend.scan(DAG_TIP_COLUMN, &[]).into_iter() {
+            let valid = key.len() == 32 && transactions.contains(&H256::from_slice(key.as_slice()))
+                && backend.scan(DAG_CHILD_COLUMN, key.as_slice()).is_empty();
+            if !valid { self.report(DAG_TIP_COLUMN, &key, Problem::DanglingReference); }
+        }
+    }
+
+    /// receipt와 account row를 해석할 수 있는지 검사한다.
+    fn check_rows(&mut self) {
+        let backend = self.ledger.get_backend();
+        for (key, value) in backend.scan(RECEIPT_COLUMN, &[]).into_iter() {
+            match rlp::decode::<Receipt>(value.as_slice()) {
+                Ok(receipt) => {
+                    if receipt.tx_hash.as_bytes() != key.as_slice() { self.report(RECEIPT_COLUMN, &key, Problem::KeyMismatch); }
+                }
+                Err(_) => { self.report(RECEIPT_COLUMN, &key, Problem::Corrupt); }
+            }
+        }
+        for (key, value) in backend.scan(ACCOUNT_COLUMN, &[]).into_iter() {
+            if key.len() != 32 || rlp::decode::<AccountNode>(value.as_slice()).is_err() {
+                self.report(ACCOUNT_COLUMN, &key, Problem::Corrupt);
+            }
+        }
+    }
+
+    /// Ledger::add_milestone과 같은 규칙으로 모든 마일스톤을 검사하고 마일스톤의 수를 반환한다.
+    fn check_milestones(&mut self, transactions: &HashSet<H256>, authorities: &Vec<Address>) -> usize {
+        let backend = self.ledger.get_backend();
+        let genesis = chain_config(self.ledger).map(|config| config.genesis);
+        let mut previous: Option<Milestone> = None;
+        let mut count = 0;
+        for (key, value) in backend.scan(MILESTONE_COLUMN, &[]).into_iter() {
+            let milestone = match rlp::decode::<Milestone>(value.as_slice()) {
+                Ok(milestone) => { milestone }
+                Err(_) => { self.report(MILESTONE_COLUMN, &key, Problem::Corrupt); continue; }
+            };
+            if to_height(key.as_slice()) != Some(milestone.height) {
+                self.report(MILESTONE_COLUMN, &key, Problem::KeyMismatch);
+                continue;
+            }
+            count += 1;
+            let problem = match Some(milestone.hash()) == genesis {
+                true => { None }    // genesis 마일스톤은 서명되지 않는다.
+                false => { validate_milestone(&milestone, previous.as_ref(), transactions, authorities) }
+            };
+            if let Some(err) = problem { self.report(MILESTONE_COLUMN, &key, Problem::Milestone(err)); }
+            let root = &milestone.state_root;
+            if !root.is_zero() && root != &EMPTY_ROOT && !backend.exists(TRIE_NODE_COLUMN, root.as_bytes()) {
+                self.report(MILESTONE_COLUMN, &key, Problem::UnknownStateRoot(root.clone()));
+            }
+            previous = Some(milestone);
+        }
+
+        for (key, value) in backend.scan(MILESTONE_HASH_COLUMN, &[]).into_iter() {
+            let milestone = to_height(value.as_slice())
+                .and_then(|height| self.ledger.get_milestones().get_milestone(height));
+            if milestone.map_or(true, |milestone| milestone.hash().as_bytes() != key.as_slice()) {
+                self.report(MILESTONE_HASH_COLUMN, &key, Problem::DanglingReference);
+            }
+        }
+        let latest = previous.map(|milestone| milestone.height);
+        for (key, value) in backend.scan(TX_MILESTONE_COLUMN, &[]).into_iter() {
+            let valid = key.len() == 32 && transactions.contains(&H256::from_slice(key.as_slice()))
+                && to_height(value.as_slice()).map_or(false, |height| latest.map_or(false, |latest| height <= latest));
+            if !valid { self.report(TX_MILESTONE_COLUMN, &key, Problem::DanglingReference); }
+        }
+        for (key, value) in backend.scan(MILESTONE_TX_COLUMN, &[]).into_iter() {
+            let valid = key.len() == 12 && value.len() == 32
+                && self.ledger.get_milestones().finalized_at(&H256::from_slice(value.as_slice()))
+                    .map_or(false, |height| height.to_be_bytes() == key[..8]);
+            if !valid { self.report(MILESTONE_TX_COLUMN, &key, Problem::DanglingReference); }
+        }
+        count
+    }
+
+    /// 트랜잭션들을 DAG 순서로 다시 실행하여 state hash를 비교하고 재실행한 트랜잭션의 수를 반환한다.
+    /// receipt가 없거나 state_hash가 기록되지 않은 트랜잭션은 실행하지 않는다.
+    fn replay(&mut self, replay: &dyn Replay, transactions: &HashSet<H256>) -> usize {
+        let mut state = Ledger::in_memory();
+        if let Some(genesis) = self.genesis.as_ref() { let _ = state.initialize(genesis); }
+        // 재실행은 같은 backend를 읽는 ledger로 하며 결과는 state에 반영한다.
+        let view = Arc::new(Ledger::with_backend(state.get_backend()));
+        let mut count = 0;
+        for hash in self.ledger.get_dag().topological_order().into_iter() {
+            if !transactions.contains(&hash) { continue; }
+            let tx = match self.ledger.get_transactions().get_transaction(&hash) {
+                Some(tx) => { tx }
+                None => { continue; }
+            };
+            let receipt = match self.ledger.get_receipts().get_receipt(&hash) {
+                Some(receipt) if !tx.state_hash.is_zero() => { receipt }
+                _ => { continue; }
+            };
+            let states = match replay.replay(self.ledger, &view, &tx, &receipt) {
+                Some(states) => { states }
+                None => { continue; }
+            };
+            let state_hash = states.hash();
+            if state_hash != tx.state_hash { self.report(TX_COLUMN, hash.as_bytes(), Problem::StateHash(state_hash)); }
+            let _ = state.commit_state(&states);
+            count += 1;
+        }
+        count
+    }
+
+    /// 손상된 row들을 quarantine column으로 옮긴다.
+    fn quarantine(&mut self) {
+        let backend = self.ledger.get_backend();
+        let mut batch = WriteBatch::new();
+        let mut moved = HashSet::new();
+        for issue in self.issues.iter().filter(|issue| issue.problem.is_corruption()) {
+            if !moved.insert((issue.column, issue.key.clone())) { continue; }
+            if let Some(value) = backend.get(issue.column, issue.key.as_slice()) {
+                batch.put(QUARANTINE_COLUMN, &[issue.column.as_bytes(), &[0], issue.key.as_slice()].concat(), &value);
+            }
+            batch.delete(issue.column, issue.key.as_slice());
+        }
+        if batch.is_empty() || backend.write(batch).is_err() { return; }
+        for issue in self.issues.iter_mut().filter(|issue| issue.problem.is_corruption()) { issue.quarantined = true; }
+    }
+}
+
+/// ledger의 데이터베이스를 검사한다.
+/// DAG의 연결과 트랜잭션 서명, 마일스톤, 각 row의 형식을 검사하며 replay가 주어지면 state hash를 재실행으로 검증한다.
+pub fn check(ledger: &Ledger, options: &CheckOptions, replay: Option<&dyn Replay>) -> Report {
+    let mut checker = Checker { ledger, genesis: stored_genesis(ledger), issues: vec![] };
+    let transactions = checker.check_transactions();
+    checker.check_dag(&transactions);
+    checker.check_rows();
+    let authorities = match options.authorities.is_empty() {
+        true => { chain_config(ledger).map_or(vec![], |config| config.authorities) }
+        false => { options.authorities.clone() }
+    };
+    let milestones = checker.check_milestones(&transactions, &authorities);
+    let replayed = match replay {
+        Some(replay) => { checker.replay(replay, &transactions) }
+        None => { 0 }
+    };
+    if options.quarantine { checker.quarantine(); }
+    Report { issues: checker.issues, transactions: transactions.len(), milestones, replayed }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use ethereum_types::{Address, H256, U256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use serde_json::Value;
+use crate::account::{AccountNode, AccountStorage};
+use crate::backend::WriteBatch;
+use crate::device::Device;
+use crate::ledger::Ledger;
+use crate::milestone::Milestone;
+use crate::transaction::Transaction;
+use crate::trie::{PatriciaTrie, PendingTrieStorage, SecureTrie, EMPTY_ROOT};
+
+/// "config" -> rlp(ChainConfig), "genesis" -> rlp(Genesis)
+pub const CHAIN_COLUMN: &str = "chain";
+
+const CONFIG_KEY: &[u8] = b"config";
+const GENESIS_KEY: &[u8] = b"genesis";
+
+/// genesis를 읽거나 적용하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum GenesisError {
+    Parse(String),          // 해석하지 못한 항목
+    Mismatch(H256),         // 다른 genesis. 이미 기록되어 있거나 peer가 보낸 genesis hash
+    ChainId(u64),           // peer의 chain id
+    NetworkId(u64),         // peer의 network id
+    NotEmpty,               // genesis 없이 이미 상태나 트랜잭션이 기록된 ledger
+    Uninitialized,          // genesis로 초기화되지 않은 ledger
+    Io,
+    Database,
+}
+
+/// 미리 배포되는 컨트랙트
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GenesisContract {
+    pub address: Address,
+    pub code: Vec<u8>,
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// chain의 시작 상태
+/// 같은 genesis로 초기화한 노드는 같은 genesis 마일스톤(height 0)을 가지며 그 hash로 chain을 구분한다.
+/// authorities, contracts, devices는 address 오름차순이다.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Genesis {
+    pub chain_id: u64,
+    pub network_id: u64,
+    pub timestamp: u64,
+    pub authorities: Vec<Address>,
+    pub contracts: Vec<GenesisContract>,
+    pub devices: Vec<Device>,
+}
+
+fn parse_error<T>(item: &str) -> Result<T, GenesisError> {
+    Err(GenesisError::Parse(item.to_string()))
+}
+
+fn parse_u64(value: Option<&Value>, item: &str) -> Result<u64, GenesisError> {
+    match value {
+        None => { Ok(0) }
+        Some(Value::Number(number)) => { number.as_u64().map_or_else(|| parse_error(item), Ok) }
+        Some(Value::String(text)) => {
+            let parsed = match text.strip_prefix("0x") {
+                Some(hex) => { u64::from_str_radix(hex, 16).ok() }
+                None => { text.parse().ok() }
+            };
+            parsed.map_or_else(|| parse_error(item), Ok)
+        }
+        Some(_) => { parse_error(item) }
+    }
+}
+
+fn parse_bytes(value: &str, item: &str) -> Result<Vec<u8>, GenesisError> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    let value = if value.len() % 2 == 1 { format!("0{}", value) } else { value.to_string() };
+    hex::decode(value).map_or_else(|_| parse_error(item), Ok)
+}
+
+fn parse_address(value: &str) -> Result<Address, GenesisError> {
+    let bytes = parse_bytes(value, value)?;
+    if bytes.len() != 20 { return parse_error(value); }
+    Ok(Address::from_slice(bytes.as_slice()))
+}
+
+/// 32 bytes보다 짧은 값은 앞을 0으로 채운다.
+fn parse_word(value: &str) -> Result<H256, GenesisError> {
+    let bytes = parse_bytes(value, value)?;
+    if bytes.len() > 32 { return parse_error(value); }
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes.as_slice());
+    Ok(H256::from(word))
+}
+
+fn entries<'a>(value: Option<&'a Value>, item: &str) -> Result<Vec<(&'a String, &'a Value)>, GenesisError> {
+    match value {
+        None => { Ok(vec![]) }
+        Some(Value::Object(map)) => { Ok(map.iter().collect()) }
+        Some(_) => { parse_error(item) }
+    }
+}
+
+impl Genesis {
+    /// JSON genesis를 해석한다.
+    /// ```json
+    /// {
+    ///   "chainId": 9, "networkId": 9, "timestamp": 0,
+    ///   "authorities": ["0x..."],
+    ///   "contracts": { "0x<address>": { "code": "0x...", "storage": { "0x<key>": "0x<value>" } } },
+    ///   "devices": { "0x<address>": { "owner": "0x...", "name": "..." } }
+    /// }
+    /// ```
+    pub fn from_json(json: &str) -> Result<Genesis, GenesisError> {
+        let spec: Value = serde_json::from_str(json).map_err(|err| GenesisError::Parse(err.to_string()))?;
+        if !spec.is_object() { return parse_error("genesis"); }
+        let mut genesis = Genesis {
+            chain_id: parse_u64(spec.get("chainId"), "chainId")?,
+            network_id: parse_u64(spec.get("networkId"), "networkId")?,
+            timestamp: parse_u64(spec.get("timestamp"), "timestamp")?,
+            ..Default::default()
+        };
+        let authorities = match spec.get("authorities") {
+            None => { vec![] }
+            Some(Value::Array(authorities)) => { authorities.clone() }
+            Some(_) => { return parse_error("authorities"); }
+        };
+        for authority in authorities.iter() {
+            match authority.as_str() {
+                Some(address) => { genesis.authorities.push(parse_address(address)?); }
+                None => { return parse_error("authorities"); }
+            }
+        }
+        for (address, contract) in entries(spec.get("contracts"), "contracts")?.into_iter() {
+            let code = match contract.get("code") {
+                None => { vec![] }
+                Some(Value::String(code)) => { parse_bytes(code, address)? }
+                Some(_) => { return parse_error(address); }
+            };
+            let mut storage = BTreeMap::new();
+            for (key, value) in entries(contract.get("storage"), address)?.into_iter() {
+                let value = match value.as_str() {
+                    Some(value) => { parse_word(value)? }
+                    None => { return parse_error(key); }
+                };
+                if !value.is_zero() { storage.insert(parse_word(key)?, value); }
+            }
+            genesis.contracts.push(GenesisContract { address: parse_address(address)?, code, storage });
+        }
+        for (address, device) in entries(spec.get("devices"), "devices")?.into_iter() {
+            let owner = match device.get("owner").and_then(|owner| owner.as_str()) {
+                Some(owner) => { parse_address(owner)? }
+                None => { return parse_error(address); }
+            };
+            let name = device.get("name").and_then(|name| name.as_str()).unwrap_or_default().to_string();
+            genesis.devices.push(Device { address: parse_address(address)?, owner, name });
+        }
+        genesis.normalize()?;
+        Ok(genesis)
+    }
+
+    pub fn load(path: &str) -> Result<Genesis, GenesisError> {
+        let json = std::fs::read_to_string(path).map_err(|_| GenesisError::Io)?;
+        Genesis::from_json(json.as_str())
+    }
+
+    /// 항목들을 address 순서로 정렬한다. 같은 address가 두 번 나오면 실패한다.
+    fn normalize(&mut self) -> Result<(), GenesisError> {
+        self.authorities.sort();
+        self.contracts.sort_by(|a, b| a.address.cmp(&b.address));
+        self.devices.sort_by(|a, b| a.address.cmp(&b.address));
+        let duplicate = self.authorities.windows(2).find(|pair| pair[0] == pair[1]).map(|pair| pair[0].clone())
+            .or_else(|| self.contracts.windows(2).find(|pair| pair[0].address == pair[1].address).map(|pair| pair[0].address.clone()))
+            .or_else(|| self.devices.windows(2).find(|pair| pair[0].address == pair[1].address).map(|pair| pair[0].address.clone()));
+        match duplicate {
+            Some(address) => { parse_error(format!("duplicate 0x{}", hex::encode(address.as_bytes())).as_str()) }
+            None => { Ok(()) }
+        }
+    }
+
+    /// keccak256(rlp(genesis))
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(rlp::encode(self).as_ref()))
+    }
+
+    /// DAG의 첫 트랜잭션이며 data는 genesis의 hash이다.
+    pub fn transaction(&self) -> Transaction {
+        let mut tx = Transaction::default();
+        tx.data = self.hash().as_bytes().to_vec();
+        tx.timestamp = self.timestamp;
+        tx
+    }
+
+    /// genesis 트랜잭션을 참조하는 서명되지 않은 height 0의 마일스톤
+    pub fn milestone(&self, state_root: &H256) -> Milestone {
+        Milestone::new(0, vec![self.transaction().hash()], state_root.clone(), self.timestamp)
+    }
+}
+
+impl Encodable for GenesisContract {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.address);
+        s.append(&self.code);
+        s.begin_list(self.storage.len());
+        for (key, value) in self.storage.iter() {
+            s.begin_list(2);
+            s.append(key);
+            s.append(value);
+        }
+    }
+}
+
+impl Decodable for GenesisContract {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let mut storage = BTreeMap::new();
+        for entry in rlp.at(2)?.iter() { storage.insert(entry.val_at(0)?, entry.val_at(1)?); }
+        Ok(GenesisContract { address: rlp.val_at(0)?, code: rlp.val_at(1)?, storage })
+    }
+}
+
+impl Encodable for Genesis {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(6);
+        s.append(&self.chain_id);
+        s.append(&self.network_id);
+        s.append(&self.timestamp);
+        s.append_list(&self.authorities);
+        s.append_list(&self.contracts);
+        s.append_list(&self.devices);
+    }
+}
+
+impl Decodable for Genesis {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Genesis {
+            chain_id: rlp.val_at(0)?,
+            network_id: rlp.val_at(1)?,
+            timestamp: rlp.val_at(2)?,
+            authorities: rlp.list_at(3)?,
+            contracts: rlp.list_at(4)?,
+            devices: rlp.list_at(5)?,
+        })
+    }
+}
+
+/// 노드가 peer에게 보내는 자신의 chain
+/// chain id, network id, genesis 마일스톤의 hash가 모두 같아야 같은 chain이다.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainStatus {
+    pub chain_id: u64,
+    pub network_id: u64,
+    pub genesis: H256,
+}
+
+impl ChainStatus {
+    /// peer가 같은 chain이 아니라면 다른 값을 반환한다.
+    pub fn check(&self, peer: &ChainStatus) -> Result<(), GenesisError> {
+        if peer.chain_id != self.chain_id { return Err(GenesisError::ChainId(peer.chain_id)); }
+        if peer.network_id != self.network_id { return Err(GenesisError::NetworkId(peer.network_id)); }
+        if peer.genesis != self.genesis { return Err(GenesisError::Mismatch(peer.genesis.clone())); }
+        Ok(())
+    }
+}
+
+impl Encodable for ChainStatus {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.chain_id);
+        s.append(&self.network_id);
+        s.append(&self.genesis);
+    }
+}
+
+impl Decodable for ChainStatus {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(ChainStatus { chain_id: rlp.val_at(0)?, network_id: rlp.val_at(1)?, genesis: rlp.val_at(2)? })
+    }
+}
+
+/// genesis로 초기화된 ledger의 chain 설정
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub network_id: u64,
+    pub authorities: Vec<Address>,  // 처음 authority들
+    pub genesis: H256,              // genesis 마일스톤의 hash
+}
+
+impl ChainConfig {
+    pub fn status(&self) -> ChainStatus {
+        ChainStatus { chain_id: self.chain_id, network_id: self.network_id, genesis: self.genesis.clone() }
+    }
+}
+
+impl Encodable for ChainConfig {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.chain_id);
+        s.append(&self.network_id);
+        s.append_list(&self.authorities);
+        s.append(&self.genesis);
+    }
+}
+
+impl Decodable for ChainConfig {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(ChainConfig {
+            chain_id: rlp.val_at(0)?,
+            network_id: rlp.val_at(1)?,
+            authorities: rlp.list_at(2)?,
+            genesis: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// ledger가 초기화된 genesis의 chain 설정. 초기화되지 않았다면 None이다.
+pub fn chain_config(ledger: &Ledger) -> Option<ChainConfig> {
+    let value = ledger.get_backend().get(CHAIN_COLUMN, CONFIG_KEY)?;
+    rlp::decode(value.as_slice()).ok()
+}
+
+/// ledger를 초기화한 genesis
+pub fn stored_genesis(ledger: &Ledger) -> Option<Genesis> {
+    let value = ledger.get_backend().get(CHAIN_COLUMN, GENESIS_KEY)?;
+    rlp::decode(value.as_slice()).ok()
+}
+
+/// 비어있는 ledger에 genesis의 컨트랙트, device, genesis 트랜잭션과 마일스톤을 하나의 batch로 기록하고
+/// genesis 마일스톤의 hash를 반환한다. 같은 genesis로 이미 초기화된 ledger라면 아무것도 기록하지 않는다.
+pub fn initialize(ledger: &mut Ledger, genesis: &Genesis) -> Result<H256, GenesisError> {
+    if let Some(config) = chain_config(ledger) {
+        return match stored_genesis(ledger) {
+            Some(stored) if stored == *genesis => { Ok(config.genesis) }
+            _ => { Err(GenesisError::Mismatch(config.genesis)) }
+        };
+    }
+    if ledger.state_root() != EMPTY_ROOT || !ledger.get_dag().is_empty() || ledger.latest_milestone().is_some() {
+        return Err(GenesisError::NotEmpty);
+    }
+
+    let mut batch = WriteBatch::new();
+    let nodes = PendingTrieStorage::new(ledger.get_tries());
+    let mut world = PatriciaTrie::new(&nodes);
+    for contract in genesis.contracts.iter() {
+        let mut trie = SecureTrie::from_root(&nodes, &EMPTY_ROOT);
+        let storage = ledger.account_state(&contract.address);
+        for (key, value) in contract.storage.iter() {
+            let encoded = rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec();
+            trie.insert(key.as_bytes(), encoded).map_err(|_| GenesisError::Database)?;
+            storage.put_storage_value(&mut batch, &AccountStorage { key: key.clone(), value: value.clone() });
+        }
+        let node = AccountNode {
+            key: H256::from(crypto::hash::keccak256(contract.address.as_bytes())),
+            nonce: 0,
+            storage_root: trie.commit(),
+            codehash: contract.code.clone(),
+        };
+        world.insert(node.key.as_bytes(), node.trie_value()).map_err(|_| GenesisError::Database)?;
+        ledger.get_accounts().put_account(&mut batch, &node);
+    }
+    let root = world.commit();
+    drop(world);
+    nodes.into_batch(&mut batch);
+    ledger.get_tries().put_state_root(&mut batch, &root);
+
+    for device in genesis.devices.iter() { ledger.get_devices().put_device(&mut batch, device); }
+    let tx = genesis.transaction();
+    let hash = tx.hash();
+    ledger.get_transactions().put_transaction(&mut batch, &tx);
+    ledger.get_dag().put_edges(&mut batch, &hash, &vec![]);
+    let milestone = genesis.milestone(&root);
+    ledger.get_milestones().put_milestone(&mut batch, &milestone, &vec![hash]);
+    let config = ChainConfig {
+        chain_id: genesis.chain_id,
+        network_id: genesis.network_id,
+        authorities: genesis.authorities.clone(),
+        genesis: milestone.hash(),
+    };
+    batch.put(CHAIN_COLUMN, CONFIG_KEY, &rlp::encode(&config));
+    batch.put(CHAIN_COLUMN, GENESIS_KEY, &rlp::encode(genesis));
+    if ledger.get_backend().write(batch).is_err() { return Err(GenesisError::Database); }
+    Ok(config.genesis)
+}
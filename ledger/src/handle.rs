@@ -0,0 +1,24 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::ledger::Ledger;
+
+/// RPC, consensus, P2P가 함께 사용하는 ledger
+/// clone한 handle은 같은 ledger(같은 데이터베이스 연결과 TxPool)를 가리킨다.
+/// 읽기는 동시에 할 수 있으며 pool이나 상태를 바꾸는 작업은 write()로 하나씩 처리한다.
+/// Ledger의 변경 함수는 &mut self를 받으므로 read()로 얻은 ledger로는 호출할 수 없다.
+#[derive(Clone)]
+pub struct LedgerHandle(Arc<RwLock<Ledger>>);
+
+impl LedgerHandle {
+    pub fn new(ledger: Ledger) -> Self {
+        LedgerHandle(Arc::new(RwLock::new(ledger)))
+    }
+
+    /// 다른 thread가 panic하여 lock이 poisoned 되더라도 ledger의 변경은 batch 단위로 반영되므로 계속 사용한다.
+    pub fn read(&self) -> RwLockReadGuard<'_, Ledger> {
+        self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, Ledger> {
+        self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -1,61 +1,583 @@
-use crate::account::{WorldStateTable, AccountNode, WorldStateTableManager, StorageTableManager, AccountState};
-use ethereum_types::{Address, H256};
-use crate::transaction::{TransactionTable, TransactionTableManager};
-use std::collections::HashMap;
-use crate::pool::TxPool;
-use crate::table::{Table, Container};
+use crate::account::{AccountNode, WorldStateTableManager, StorageTableManager, AccountState, AccountStorage};
+use ethereum_types::{Address, H256, U256};
+use crate::transaction::{TransactionTableManager, Transaction};
+use crate::envelope::TypedTransaction;
+use crate::dag::{DagTableManager, DagError};
+use crate::milestone::{MilestoneTableManager, Milestone, MilestoneError};
+use crate::receipt::{ReceiptTableManager, Receipt};
+use crate::log_index::{LogIndexManager, LogFilter, IndexedLog};
+use crate::archive::{ArchiveManager, ArchiveError, BlockTag, HistoricalBackend};
+use crate::device::DeviceTableManager;
+use crate::device_call::{DeviceCallPool, DeviceCallTransaction, DeviceCallError};
+use crate::batch::{BatchTableManager, BatchTransaction, BatchOutcome};
+use crate::conflict::{ConflictManager, Evidence, TxStatus, choose_winner};
+use crate::genesis::{ChainConfig, Genesis, GenesisError};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use crate::pool::{TxPool, PoolError};
+use crate::backend::{Backend, WriteBatch, SqliteBackend, MemoryBackend};
 use crate::dirty_state::DirtyStates;
+use crate::migration::MigrationError;
+use common::datadir::DataDir;
 use std::sync::Arc;
+use crate::trie::{TrieTableManager, SecureTrie, PendingTrieStorage};
 
-const DatabasePath: &str = "biiot.db";
+/// 트랜잭션을 반영하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum CommitError {
+    StateHash(H256),        // dirty state로 계산한 state hash가 트랜잭션의 state_hash와 다름
+    InvalidSignature,       // 서명에서 복구한 sender가 receipt의 from과 다름
+    Transaction(DagError),  // 트랜잭션을 DAG에 연결할 수 없음
+    ReceiptMismatch,        // 다른 트랜잭션의 receipt
+    DuplicateReceipt,       // 이미 receipt가 기록된 트랜잭션
+    Nonce(u64),             // sender의 다음 nonce와 다른 nonce. 기대한 nonce를 갖는다.
+    Trie,
+    Database,
+}
 
 pub struct Ledger {
+    pub backend: Arc<dyn Backend>,
+    pub data_dir: Option<DataDir>,  // 메모리에만 저장하는 ledger는 None이다.
     pub accounts: WorldStateTableManager,
     pub transactions: TransactionTableManager,
     pub pool: TxPool,
     pub dirty_state: Arc<DirtyStates>,
+    pub tries: TrieTableManager,
+    pub dag: DagTableManager,
+    pub milestones: MilestoneTableManager,
+    pub receipts: ReceiptTableManager,
+    pub log_index: LogIndexManager,
+    pub archive: ArchiveManager,
+    pub devices: DeviceTableManager,
+    pub device_pool: DeviceCallPool,
+    pub batches: BatchTableManager,
+    pub conflicts: ConflictManager,
 }
 
 /// Property
 impl Ledger {
+    pub fn get_backend(&self) -> Arc<dyn Backend> { self.backend.clone() }
+    pub fn get_data_dir(&self) -> Option<&DataDir> { self.data_dir.as_ref() }
     pub fn get_accounts(&self) -> &WorldStateTableManager { &self.accounts }
     pub fn get_transactions(&self) -> &TransactionTableManager { &self.transactions }
     pub fn get_pool(&self) -> &TxPool { &self.pool }
-    pub fn get_dirty_state(&mut self) -> Arc<DirtyStates> { self.dirty_state.clone() }
+    pub fn get_dirty_state(&self) -> Arc<DirtyStates> { self.dirty_state.clone() }
+    pub fn get_tries(&self) -> &TrieTableManager { &self.tries }
+    pub fn get_dag(&self) -> &DagTableManager { &self.dag }
+    pub fn get_milestones(&self) -> &MilestoneTableManager { &self.milestones }
+    pub fn get_receipts(&self) -> &ReceiptTableManager { &self.receipts }
+    pub fn get_log_index(&self) -> &LogIndexManager { &self.log_index }
+    pub fn get_archive(&self) -> &ArchiveManager { &self.archive }
+    pub fn get_devices(&self) -> &DeviceTableManager { &self.devices }
+    pub fn get_device_pool(&self) -> &DeviceCallPool { &self.device_pool }
+    pub fn get_batches(&self) -> &BatchTableManager { &self.batches }
+    pub fn get_conflicts(&self) -> &ConflictManager { &self.conflicts }
 }
 
 /// Methods
 impl Ledger {
-    pub fn new() -> Self {
-        let ledger = Ledger {
-            accounts: WorldStateTableManager::new(),
-            transactions: TransactionTableManager::new(),
+    /// data directory의 SQLite 데이터베이스(biiot.db)를 사용하는 ledger. 데이터베이스를 열지 못하면 panic한다.
+    pub fn new(data_dir: &DataDir) -> Self {
+        match Ledger::open(data_dir) {
+            Ok(ledger) => { ledger }
+            Err(err) => { panic!("could not open ledger in {}: {:?}", data_dir.path().display(), err) }
+        }
+    }
+
+    /// data directory의 데이터베이스를 열고 필요하다면 migration한다. 디렉토리가 없다면 만든다.
+    pub fn open(data_dir: &DataDir) -> Result<Self, MigrationError> {
+        if data_dir.create().is_err() { return Err(MigrationError::Database); }
+        let path = Ledger::database_file(data_dir);
+        let backend = SqliteBackend::open(path.to_string_lossy().as_ref())?;
+        let mut ledger = Ledger::with_backend(Arc::new(backend));
+        ledger.data_dir = Some(data_dir.clone());
+        Ok(ledger)
+    }
+
+    /// data directory 안의 데이터베이스 파일 경로
+    pub fn database_file(data_dir: &DataDir) -> std::path::PathBuf {
+        data_dir.file(crate::constant::DatabasePath)
+    }
+
+    /// 메모리에만 저장하는 ledger이며 테스트와 시뮬레이션에 사용한다.
+    pub fn in_memory() -> Self {
+        Ledger::with_backend(Arc::new(MemoryBackend::new()))
+    }
+
+    pub fn with_backend(backend: Arc<dyn Backend>) -> Self {
+        Ledger {
+            accounts: WorldStateTableManager::new(backend.clone()),
+            transactions: TransactionTableManager::new(backend.clone()),
             pool: TxPool::new(),
             dirty_state: Arc::new(DirtyStates::new()),
+            tries: TrieTableManager::new(backend.clone()),
+            dag: DagTableManager::new(backend.clone()),
+            milestones: MilestoneTableManager::new(backend.clone()),
+            receipts: ReceiptTableManager::new(backend.clone()),
+            log_index: LogIndexManager::new(backend.clone()),
+            archive: ArchiveManager::new(backend.clone()),
+            devices: DeviceTableManager::new(backend.clone()),
+            device_pool: DeviceCallPool::new(),
+            batches: BatchTableManager::new(backend.clone()),
+            conflicts: ConflictManager::new(backend.clone()),
+            backend,
+            data_dir: None,
+        }
+    }
+
+    /// 비어있는 ledger를 genesis로 초기화하고 genesis 마일스톤의 hash를 반환한다.
+    /// 같은 genesis로 이미 초기화되었다면 그대로 두며, 다른 genesis로 초기화된 ledger는 Mismatch를 반환한다.
+    pub fn initialize(&mut self, genesis: &Genesis) -> Result<H256, GenesisError> {
+        crate::genesis::initialize(self, genesis)
+    }
+
+    /// genesis로 초기화된 chain의 설정. 초기화되지 않았다면 None이다.
+    pub fn chain_config(&self) -> Option<ChainConfig> {
+        crate::genesis::chain_config(self)
+    }
+
+    pub fn account_state(&self, address: &Address) -> StorageTableManager {
+        return StorageTableManager::new(self.backend.clone(), address);
+    }
+
+    /// 컨트랙트 storage의 값을 반환한다. 기록되지 않은 값은 0이다.
+    pub fn get_storage_value(&self, address: &Address, key: &H256) -> H256 {
+        return self.account_state(address).get_storage_value(key).value;
+    }
+
+    /// 트랜잭션을 저장하고 DAG에 연결한다. 모든 부모 트랜잭션이 이미 저장되어 있어야 하며
+    /// 부모가 없는 트랜잭션은 DAG가 비어있을 때(genesis)만 추가할 수 있다.
+    /// 서명된 트랜잭션은 같은 sender와 nonce의 다른 트랜잭션과의 충돌을 함께 기록한다.
+    pub fn add_transaction(&mut self, tx: &Transaction) -> Result<H256, DagError> {
+        let mut batch = WriteBatch::new();
+        let sender = tx.try_get_sender();
+        let hash = self.put_transaction(&mut batch, tx, sender.as_ref())?;
+        if self.backend.write(batch).is_err() { return Err(DagError::Database); }
+        return Ok(hash);
+    }
+
+    /// 트랜잭션을 검증하고 저장 및 DAG 연결을 batch에 기록한다. sender를 알고 있다면 충돌도 기록한다.
+    pub(crate) fn put_transaction(&self, batch: &mut WriteBatch, tx: &Transaction, sender: Option<&Address>) -> Result<H256, DagError> {
+        let hash = tx.hash();
+        if self.transactions.exist(&hash) { return Err(DagError::Duplicate(hash)); }
+        let parents = tx.parents();
+        if parents.is_empty() && !self.dag.is_empty() { return Err(DagError::NoParents); }
+        for parent in parents.iter() {
+            if !self.transactions.exist(parent) { return Err(DagError::MissingParent(parent.clone())); }
+        }
+        self.transactions.put_transaction(batch, tx);
+        self.dag.put_edges(batch, &hash, &parents);
+        if let Some(sender) = sender { self.put_conflicts(batch, tx, &hash, sender); }
+        return Ok(hash);
+    }
+
+    /// 같은 sender와 nonce로 이미 저장된 트랜잭션이 있다면 이기는 트랜잭션을 정하고 나머지를 거부된 것으로 batch에 기록한다.
+    /// sender가 authority라면 equivocation의 증거도 기록한다.
+    fn put_conflicts(&self, batch: &mut WriteBatch, tx: &Transaction, hash: &H256, sender: &Address) {
+        let nonce = tx.nonce as u64;
+        self.conflicts.put_sender(batch, hash, sender, nonce);
+        let mut hashes = self.conflicts.transactions(sender, nonce);
+        if hashes.is_empty() { return; }
+        hashes.push(hash.clone());
+        let candidates = hashes.iter().map(|hash| (hash.clone(), self.milestones.finalized_at(hash))).collect();
+        let winner = choose_winner(&candidates).unwrap();
+        self.conflicts.put_winner(batch, &winner, &hashes);
+
+        if !self.chain_config().map_or(false, |config| config.authorities.contains(sender)) { return; }
+        let mut transactions: Vec<Transaction> = hashes.iter()
+            .filter(|other| *other != hash)
+            .filter_map(|other| self.transactions.get_transaction(other))
+            .collect();
+        transactions.extend(rlp::decode::<Transaction>(rlp::encode(tx).as_ref()).ok());
+        transactions.sort_by_key(|tx| tx.hash());
+        self.conflicts.put_evidence(batch, &Evidence { sender: sender.clone(), nonce, transactions });
+    }
+
+    /// 마일스톤이 확정하는 트랜잭션들의 충돌을 다시 정하고 거부된 트랜잭션들을 반환한다.
+    /// 충돌하는 트랜잭션이 이전 마일스톤에 이미 포함되었다면 그 트랜잭션이 이긴다.
+    fn resolve_conflicts(&self, batch: &mut WriteBatch, finalized: &Vec<H256>, height: u64) -> HashSet<H256> {
+        let finalizing: HashSet<&H256> = finalized.iter().collect();
+        let mut resolved = HashSet::new();
+        let mut rejected = HashSet::new();
+        for hash in finalized.iter() {
+            let (sender, nonce) = match self.conflicts.sender(hash) {
+                Some(sender) => { sender }
+                None => { continue; }
+            };
+            if !resolved.insert((sender.clone(), nonce)) { continue; }
+            let hashes = self.conflicts.transactions(&sender, nonce);
+            if hashes.len() < 2 { continue; }
+            let candidates = hashes.iter().map(|hash| {
+                let height = if finalizing.contains(hash) { Some(height) } else { self.milestones.finalized_at(hash) };
+                (hash.clone(), height)
+            }).collect();
+            let winner = choose_winner(&candidates).unwrap();
+            self.conflicts.put_winner(batch, &winner, &hashes);
+            rejected.extend(hashes.into_iter().filter(|hash| hash != &winner));
+        }
+        rejected
+    }
+
+    /// 트랜잭션의 상태. 충돌에서 진 트랜잭션은 마일스톤에 포함되었더라도 Rejected이다.
+    pub fn transaction_status(&self, hash: &H256) -> TxStatus {
+        if !self.transactions.exist(hash) { return TxStatus::Unknown; }
+        if let Some(winner) = self.conflicts.rejected_by(hash) { return TxStatus::Rejected(winner); }
+        match self.milestones.finalized_at(hash) {
+            Some(height) => { TxStatus::Final(height) }
+            None => { TxStatus::Pending }
+        }
+    }
+
+    /// authority가 서명한 마일스톤을 검증하고 저장한다.
+    /// height는 마지막 마일스톤의 다음 값이어야 하며(첫 마일스톤은 0), 참조하는 tip은 모두 저장된 트랜잭션이어야 한다.
+    pub fn add_milestone(&mut self, milestone: &Milestone, authorities: &Vec<Address>) -> Result<H256, MilestoneError> {
+        let signer = match milestone.signer() {
+            Some(signer) => { signer }
+            None => { return Err(MilestoneError::InvalidSignature); }
+        };
+        if !authorities.contains(&signer) { return Err(MilestoneError::Unauthorized(signer)); }
+        let latest = self.milestones.latest_milestone();
+        let height = latest.as_ref().map_or(0, |latest| latest.height + 1);
+        if milestone.height != height { return Err(MilestoneError::InvalidHeight(height)); }
+        if latest.map_or(false, |latest| milestone.timestamp < latest.timestamp) {
+            return Err(MilestoneError::InvalidTimestamp);
+        }
+        if milestone.tips.is_empty() { return Err(MilestoneError::NoTips); }
+        for tip in milestone.tips.iter() {
+            if !self.transactions.exist(tip) { return Err(MilestoneError::UnknownTip(tip.clone())); }
+        }
+        let finalized = self.finalization_order(self.unfinalized_ancestry(&milestone.tips));
+        let mut batch = WriteBatch::new();
+        let rejected = self.resolve_conflicts(&mut batch, &finalized, milestone.height);
+        // 거부된 트랜잭션의 log는 색인하지 않는다.
+        let receipts = finalized.iter().map(|hash| match rejected.contains(hash) {
+            true => { None }
+            false => { self.receipts.get_receipt(hash) }
+        }).collect();
+        self.milestones.put_milestone(&mut batch, milestone, &finalized);
+        self.log_index.put_logs(&mut batch, milestone.height, &receipts);
+        if self.backend.write(batch).is_err() { return Err(MilestoneError::Database); }
+        return Ok(milestone.hash());
+    }
+
+    /// tips와 그 조상 중 아직 어떤 마일스톤에도 포함되지 않은 트랜잭션들
+    /// 이미 확정된 트랜잭션의 조상은 모두 확정되어 있으므로 더 따라가지 않는다.
+    fn unfinalized_ancestry(&self, tips: &Vec<H256>) -> Vec<H256> {
+        let mut visited = HashSet::new();
+        let mut queue: VecDeque<H256> = tips.iter().cloned().collect();
+        let mut result = vec![];
+        while let Some(hash) = queue.pop_front() {
+            if !visited.insert(hash.clone()) || self.milestones.finalized_at(&hash).is_some() { continue; }
+            queue.extend(self.dag.parents(&hash).into_iter());
+            result.push(hash);
+        }
+        result
+    }
+
+    /// 마일스톤이 확정하는 트랜잭션들을 부모가 자식보다 먼저 오도록 정렬한다.
+    /// 순서가 정해지지 않는 트랜잭션끼리는 hash가 작은 것이 먼저 오며, 이 순서가 log의 tx index가 된다.
+    fn finalization_order(&self, hashes: Vec<H256>) -> Vec<H256> {
+        let set: HashSet<H256> = hashes.iter().cloned().collect();
+        let mut remaining = HashMap::<H256, usize>::new();
+        let mut children = HashMap::<H256, Vec<H256>>::new();
+        for hash in hashes.iter() {
+            let parents: Vec<H256> = self.dag.parents(hash).into_iter().filter(|parent| set.contains(parent)).collect();
+            remaining.insert(hash.clone(), parents.len());
+            for parent in parents.into_iter() { children.entry(parent).or_insert_with(Vec::new).push(hash.clone()); }
+        }
+        let mut ready: BTreeSet<H256> = hashes.iter().filter(|hash| remaining[*hash] == 0).cloned().collect();
+        let mut order = Vec::with_capacity(hashes.len());
+        while let Some(hash) = ready.iter().next().cloned() {
+            ready.remove(&hash);
+            for child in children.get(&hash).map_or(vec![], |next| next.clone()).into_iter() {
+                let count = remaining.get_mut(&child).unwrap();
+                *count -= 1;
+                if *count == 0 { ready.insert(child); }
+            }
+            order.push(hash);
+        }
+        order
+    }
+
+    /// 확정된 log 중 조건을 만족하는 것들 (마일스톤, 트랜잭션, log 순서)
+    pub fn get_logs(&self, filter: &LogFilter) -> Vec<IndexedLog> {
+        self.log_index.query(filter)
+    }
+
+    pub fn latest_milestone(&self) -> Option<Milestone> {
+        self.milestones.latest_milestone()
+    }
+
+    pub fn get_receipt(&self, tx_hash: &H256) -> Option<Receipt> {
+        self.receipts.get_receipt(tx_hash)
+    }
+
+    /// 마일스톤의 확정 순서에서 트랜잭션의 위치(tx index)와 그 위치까지 receipt의 gas_used 합
+    /// 확정되지 않았거나 확정 순서가 기록되지 않은 트랜잭션은 None이다.
+    pub fn receipt_position(&self, hash: &H256) -> Option<(u32, u64)> {
+        let finalized = self.milestones.finalized(self.milestones.finalized_at(hash)?);
+        let index = finalized.iter().position(|other| other == hash)?;
+        let cumulative = finalized[..=index].iter()
+            .filter_map(|hash| self.receipts.get_receipt(hash))
+            .fold(0u64, |sum, receipt| sum.saturating_add(receipt.gas_used));
+        Some((index as u32, cumulative))
+    }
+
+    /// 마일스톤이 참조하는 트랜잭션이거나 그 조상이라면 확정된 트랜잭션이다.
+    pub fn is_final(&self, hash: &H256) -> bool {
+        self.milestones.finalized_at(hash).is_some()
+    }
+
+    /// 마지막 commit의 world state root
+    pub fn state_root(&self) -> H256 {
+        self.tries.latest_state_root()
+    }
+
+    /// 트랜잭션으로 변경된 storage 값을 반영하고 각 컨트랙트의 storage root와 world state root를 다시 계산한다.
+    /// 계산된 state root는 기록되며 반환된다.
+    pub fn commit_state(&mut self, states: &DirtyStates) -> Result<H256, ()> {
+        let mut batch = WriteBatch::new();
+        let root = self.prepare_commit(&mut batch, states, None).map_err(|_| ())?;
+        self.backend.write(batch)?;
+        return Ok(root);
+    }
+
+    /// 실행된 트랜잭션을 반영한다. 트랜잭션과 receipt, storage 변경, account와 state root가 하나의
+    /// 데이터베이스 트랜잭션으로 기록되며, 검증에 실패하거나 기록에 실패하면 아무것도 반영되지 않는다.
+    /// * `tx` - state_hash가 keccak256(rlp(states))와 같아야 하며 DAG에 연결할 수 있어야 한다.
+    /// * `receipt` - tx의 receipt. from이 tx의 서명에서 복구한 sender이며 tx의 nonce가 sender의 다음 nonce여야 한다.
+    pub fn commit(&mut self, tx: &Transaction, states: &DirtyStates, receipt: &Receipt) -> Result<H256, CommitError> {
+        let mut batch = WriteBatch::new();
+        let root = self.prepare_transaction(&mut batch, tx, states, receipt)?;
+        if self.backend.write(batch).is_err() { return Err(CommitError::Database); }
+        self.pool.prune(&receipt.from, tx.nonce as u64 + 1);
+        return Ok(root);
+    }
+
+    /// batch::execute로 실행한 batch 트랜잭션을 commit과 같이 반영한다.
+    /// batch의 receipt와 함께 entry마다의 sub receipt와 측정값이 같은 데이터베이스 트랜잭션으로 기록된다.
+    /// * `tx` - BatchTransaction::to_transaction으로 만든 트랜잭션이며 state_hash가 keccak256(rlp(outcome.states))와 같아야 한다.
+    pub fn commit_batch(&mut self, tx: &Transaction, outcome: &BatchOutcome) -> Result<H256, CommitError> {
+        match BatchTransaction::from_bytes(tx.data.as_slice()) {
+            Ok(batch_tx) if batch_tx.hash() == outcome.batch => {}
+            _ => { return Err(CommitError::ReceiptMismatch); }
+        }
+        let receipt = Receipt::new(&tx.hash(), outcome.status, &outcome.sender, vec![], 0, None, outcome.logs());
+        let mut batch = WriteBatch::new();
+        let root = self.prepare_transaction(&mut batch, tx, &outcome.states, &receipt)?;
+        self.batches.put_sub_receipts(&mut batch, &receipt.tx_hash, &outcome.sub_receipts);
+        for reading in outcome.readings.iter() { self.batches.put_reading(&mut batch, &outcome.sender, reading); }
+        if self.backend.write(batch).is_err() { return Err(CommitError::Database); }
+        self.pool.prune(&outcome.sender, tx.nonce as u64 + 1);
+        return Ok(root);
+    }
+
+    /// 트랜잭션을 검증하고 DAG 연결, receipt, storage 변경과 state root를 batch에 기록한다.
+    fn prepare_transaction(&self, batch: &mut WriteBatch, tx: &Transaction, states: &DirtyStates,
+                           receipt: &Receipt) -> Result<H256, CommitError> {
+        if tx.try_get_sender().as_ref() != Some(&receipt.from) { return Err(CommitError::InvalidSignature); }
+        let state_hash = states.hash();
+        if tx.state_hash != state_hash { return Err(CommitError::StateHash(state_hash)); }
+        let hash = self.put_transaction(batch, tx, Some(&receipt.from)).map_err(CommitError::Transaction)?;
+        if receipt.tx_hash != hash { return Err(CommitError::ReceiptMismatch); }
+        self.prepare_commit(batch, states, Some((receipt, tx.nonce as u64)))
+    }
+
+    /// world state에 기록된 sender의 다음 nonce
+    pub fn next_nonce(&self, sender: &Address) -> u64 {
+        self.accounts.get_account(sender).nonce
+    }
+
+    /// pool에서 대기중인 트랜잭션까지 포함한 sender의 다음 nonce
+    pub fn pending_nonce(&self, sender: &Address) -> u64 {
+        self.pool.pending_nonce(sender, self.next_nonce(sender))
+    }
+
+    /// 트랜잭션을 pool에 추가한다. 이미 사용된 nonce(replay)나 너무 멀리 떨어진 nonce는 거부된다.
+    pub fn admit_transaction(&mut self, sender: &Address, tx: TypedTransaction) -> Result<H256, PoolError> {
+        self.pool.admit(sender, tx, self.next_nonce(sender))
+    }
+
+    /// device call을 검증하고 device call pool에 추가한다. 컨트랙트 호출의 pool과는 분리되어 있다.
+    pub fn admit_device_call(&mut self, tx: DeviceCallTransaction) -> Result<H256, DeviceCallError> {
+        let sender = self.check_device_call(&tx)?;
+        let committed = self.devices.next_nonce(&tx.device);
+        if tx.nonce < committed { return Err(DeviceCallError::Nonce(committed)); }
+        self.device_pool.admit(&sender, tx, committed)
+    }
+
+    /// device가 실행한 device call을 반영한다. DAG와 마일스톤을 거치지 않으며 world state도 바꾸지 않는다.
+    /// device call과 device의 nonce, 실행 결과(status, output)를 담은 receipt가 하나의 batch로 기록된다.
+    /// * `tx` - nonce가 device의 다음 nonce여야 한다.
+    pub fn commit_device_call(&mut self, tx: &DeviceCallTransaction, status: u8, output: Vec<u8>) -> Result<H256, DeviceCallError> {
+        let sender = self.check_device_call(tx)?;
+        let hash = tx.hash();
+        if self.receipts.exist(&hash) { return Err(DeviceCallError::AlreadyKnown(hash)); }
+        let committed = self.devices.next_nonce(&tx.device);
+        if tx.nonce != committed { return Err(DeviceCallError::Nonce(committed)); }
+        let mut batch = WriteBatch::new();
+        self.devices.put_call(&mut batch, tx);
+        self.receipts.put_receipt(&mut batch, &Receipt::new(&hash, status, &sender, output, 0, None, vec![]));
+        if self.backend.write(batch).is_err() { return Err(DeviceCallError::Database); }
+        self.device_pool.prune(&tx.device, tx.nonce + 1);
+        return Ok(hash);
+    }
+
+    /// device call의 서명, chain id, 명령을 확인하고 서명한 device owner를 반환한다.
+    fn check_device_call(&self, tx: &DeviceCallTransaction) -> Result<Address, DeviceCallError> {
+        if tx.command.is_empty() { return Err(DeviceCallError::EmptyCommand); }
+        let sender = tx.try_get_sender().ok_or(DeviceCallError::InvalidSignature)?;
+        if let Some(config) = self.chain_config() {
+            if tx.chain_id != config.chain_id { return Err(DeviceCallError::ChainId(config.chain_id)); }
+        }
+        let device = match self.devices.get_device(&tx.device) {
+            Some(device) => { device }
+            None => { return Err(DeviceCallError::UnknownDevice(tx.device.clone())); }
         };
-        return ledger;
+        if device.owner != sender { return Err(DeviceCallError::NotOwner(device.owner)); }
+        Ok(sender)
     }
 
-    pub fn account_state(address: &Address) -> StorageTableManager {
-        return StorageTableManager::new(address);
+    /// storage 변경을 반영한 state root를 계산하고 trie node, state root, receipt, account와 storage를 batch에 기록한다.
+    /// batch가 기록되기 전에는 아무것도 반영되지 않는다.
+    fn prepare_commit(&self, batch: &mut WriteBatch, states: &DirtyStates,
+                      receipt: Option<(&Receipt, u64)>) -> Result<H256, CommitError> {
+        let nodes_storage = PendingTrieStorage::new(&self.tries);
+        let mut world = SecureTrie::from_root(&nodes_storage, &self.state_root());
+        let mut addresses: Vec<Address> = states.addresses().into_iter()
+            .filter(|address| !states.is_removed(address)).collect();
+        addresses.sort();
+        let mut nodes = vec![];
+        for address in addresses.iter() {
+            let mut node = self.accounts.get_account(address);
+            let mut storage_trie = SecureTrie::from_root(&nodes_storage, &node.storage_root);
+            for (key, value) in states.changes(address).iter() {
+                match value.is_zero() {
+                    true => { storage_trie.remove(key.as_bytes()).map_err(|_| CommitError::Trie)?; }
+                    false => {
+                        let value = rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec();
+                        storage_trie.insert(key.as_bytes(), value).map_err(|_| CommitError::Trie)?;
+                    }
+                }
+            }
+            node.storage_root = storage_trie.commit();
+            nodes.push((address.clone(), node));
+        }
+        if let Some((receipt, nonce)) = receipt {
+            if self.receipts.exist(&receipt.tx_hash) { return Err(CommitError::DuplicateReceipt); }
+            let sender = nodes.iter().position(|(address, _)| address == &receipt.from);
+            let sender = match sender {
+                Some(idx) => { &mut nodes[idx].1 }
+                None => {
+                    nodes.push((receipt.from.clone(), self.accounts.get_account(&receipt.from)));
+                    &mut nodes.last_mut().unwrap().1
+                }
+            };
+            if sender.nonce != nonce { return Err(CommitError::Nonce(sender.nonce)); }
+            sender.nonce += 1;
+        }
+        nodes.retain(|(address, _)| !states.is_removed(address));
+        for (address, node) in nodes.iter() {
+            world.insert(address.as_bytes(), node.trie_value()).map_err(|_| CommitError::Trie)?;
+        }
+        let removed = states.removed();
+        for address in removed.iter() {
+            world.remove(address.as_bytes()).map_err(|_| CommitError::Trie)?;
+        }
+        let root = world.commit();
+        drop(world);
+        nodes_storage.into_batch(batch);
+        self.tries.put_state_root(batch, &root);
+        if let Some((receipt, _)) = receipt { self.receipts.put_receipt(batch, receipt); }
+        for address in addresses.iter() {
+            let storage = self.account_state(address);
+            for (key, value) in states.changes(address).iter() {
+                storage.put_storage_value(batch, &AccountStorage { key: key.clone(), value: value.clone() });
+            }
+        }
+        for (_, node) in nodes.iter() { self.accounts.put_account(batch, node); }
+        for address in removed.iter() { self.put_remove_account(batch, address); }
+        self.archive.record(batch);
+        return Ok(root);
+    }
+
+    /// 마지막 state root에 대한 account의 merkle proof
+    pub fn prove_account(&self, address: &Address) -> Vec<Vec<u8>> {
+        SecureTrie::from_root(&self.tries, &self.state_root()).prove(address.as_bytes())
     }
 
-    pub fn initialize(&self) {
-        self.accounts.initialize();
-        self.transactions.initialize();
+    /// account의 storage root에 대한 storage 값의 merkle proof
+    pub fn prove_storage(&self, address: &Address, key: &H256) -> Vec<Vec<u8>> {
+        let node = self.accounts.get_account(address);
+        SecureTrie::from_root(&self.tries, &node.storage_root).prove(key.as_bytes())
     }
 
-    pub fn get_account(&mut self, address: &Address) -> AccountState {
+    pub fn get_account(&self, address: &Address) -> AccountState {
         let node = self.accounts.get_account(address);
-        let storage = StorageTableManager::new(address);
-        storage.initialize();
-        return AccountState::new(node, storage);
+        return AccountState::new(node, self.account_state(address));
     }
 
-    pub fn upsert_account(&mut self, node: &AccountNode) {
-        match self.accounts.exist(&node.key) {
-            true => { self.accounts.update_account(node); }
-            false => { self.accounts.insert_account(node); }
+    /// SELFDESTRUCT 등으로 제거된 account를 world state에서 삭제하고 해당 컨트랙트의 storage를 정리한다.
+    /// archive 모드라면 삭제된 account와 storage는 이력으로 남아 과거 height에서 조회할 수 있다.
+    pub fn remove_account(&mut self, address: &Address) -> Result<(), ()> {
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        if !self.accounts.exist(&key) && self.account_state(address).is_empty() { return Err(()); }
+        let mut batch = WriteBatch::new();
+        let nodes_storage = PendingTrieStorage::new(&self.tries);
+        let mut world = SecureTrie::from_root(&nodes_storage, &self.state_root());
+        world.remove(address.as_bytes())?;
+        let root = world.commit();
+        drop(world);
+        nodes_storage.into_batch(&mut batch);
+        self.tries.put_state_root(&mut batch, &root);
+        self.put_remove_account(&mut batch, address);
+        self.archive.record(&mut batch);
+        self.backend.write(batch)
+    }
+
+    /// account와 storage를 삭제하는 변경을 batch에 기록한다. world state trie에서의 제거는 호출하는 쪽에서 한다.
+    fn put_remove_account(&self, batch: &mut WriteBatch, address: &Address) {
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        if self.accounts.exist(&key) { self.accounts.put_delete_account(batch, &key); }
+        self.account_state(address).put_drop_storage(batch);
+    }
+
+    pub fn upsert_account(&mut self, node: &AccountNode) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        self.accounts.put_account(&mut batch, node);
+        self.archive.record(&mut batch);
+        self.backend.write(batch)
+    }
+
+    /// 이후의 storage와 account 변경 이력을 보관하여 과거 상태를 조회할 수 있게 한다.
+    /// 이력을 보관하기 시작한 height를 반환한다.
+    pub fn enable_archive(&mut self) -> Result<u64, ()> {
+        self.archive.enable()
+    }
+
+    /// tag 시점의 상태를 읽는 읽기 전용 ledger
+    /// 과거 height는 archive 모드를 시작한 이후의 이미 추가된 마일스톤만 조회할 수 있다.
+    pub fn state_at(&self, tag: &BlockTag) -> Result<Ledger, ArchiveError> {
+        let height = match tag {
+            BlockTag::Latest | BlockTag::Pending => { return Ok(Ledger::with_backend(self.backend.clone())); }
+            BlockTag::Earliest => { 0 }
+            BlockTag::Number(height) => { *height }
+        };
+        let latest = self.milestones.latest_milestone().map(|milestone| milestone.height);
+        if latest.map_or(true, |latest| height > latest) { return Err(ArchiveError::UnknownHeight(height)); }
+        match self.archive.since() {
+            Some(since) if since <= height => {}
+            _ => { return Err(ArchiveError::NotArchived(height)); }
         }
+        Ok(Ledger::with_backend(Arc::new(HistoricalBackend::new(self.backend.clone(), height))))
+    }
+
+    /// tag 시점의 storage 값
+    pub fn get_storage_value_at(&self, address: &Address, key: &H256, tag: &BlockTag) -> Result<H256, ArchiveError> {
+        Ok(self.state_at(tag)?.get_storage_value(address, key))
+    }
+
+    /// tag 시점의 account
+    pub fn get_account_at(&self, address: &Address, tag: &BlockTag) -> Result<AccountNode, ArchiveError> {
+        Ok(self.state_at(tag)?.accounts.get_account(address))
     }
 }
\ No newline at end of file
@@ -1,24 +1,1250 @@
 pub mod ledger;
 pub mod account;
 pub mod transaction;
+pub mod envelope;
+pub mod batch;
+pub mod dag;
+pub mod milestone;
+pub mod receipt;
 pub mod pool;
-mod table;
-mod sql_util;
+pub mod log;
+pub mod log_index;
+pub mod dirty_state;
+pub mod trie;
+pub mod backend;
+pub mod migration;
+pub mod archive;
+pub mod snapshot;
+pub mod device;
+pub mod device_call;
+pub mod genesis;
+pub mod conflict;
+pub mod handle;
+pub mod fsck;
 mod constant;
-mod dirty_state;
 
 #[cfg(test)]
 mod tests {
     use crate::ledger::Ledger;
 
+    /// ledger의 tip에 연결되고 states를 state_hash로 갖는 서명된 트랜잭션
+    fn signed_transaction(ledger: &Ledger, sk: &crypto::key::Sk, nonce: usize,
+                          states: &crate::dirty_state::DirtyStates) -> crate::transaction::Transaction {
+        let mut raw = crate::transaction::RawTransaction {
+            nonce, gas_price: Default::default(), gas: Default::default(), recipient: Default::default(),
+            value: Default::default(), data: vec![], v: 0, r: vec![], s: vec![],
+        };
+        raw.sign(sk, None);
+        let mut tx = crate::transaction::Transaction::from_raw_transaction(&raw);
+        tx.set_parents(&ledger.get_dag().tips());
+        tx.state_hash = states.hash();
+        tx
+    }
+
     #[test]
     fn create_ledger() {
-        let mut ledger = Ledger::new();
-        ledger.initialize();
+        let ledger = Ledger::in_memory();
+        assert!(ledger.get_dag().is_empty());
+    }
+
+    #[test]
+    fn remove_destructed_account() {
+        use ethereum_types::{Address, H256};
+        use crypto::key::Sk;
+        use crate::archive::BlockTag;
+        use crate::dirty_state::DirtyStates;
+        use crate::milestone::Milestone;
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let authority = Sk::random();
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        let (address, key, value) = (Address::random(), H256::from_low_u64_be(1), H256::from_low_u64_be(7));
+        assert_eq!(ledger.enable_archive(), Ok(0));
+        let mut states = DirtyStates::new();
+        states.set_value(&address, &key, &value);
+        ledger.commit_state(&states).unwrap();
+        let hash = ledger.add_transaction(&Transaction::default()).unwrap();
+        let mut milestone = Milestone::new(0, vec![hash], ledger.state_root(), 0);
+        milestone.sign(&authority);
+        ledger.add_milestone(&milestone, &authorities).unwrap();
+
+        assert!(ledger.remove_account(&address).is_ok());
+        assert!(ledger.account_state(&address).is_empty());
+        assert!(!ledger.get_accounts().exist(&H256::from(crypto::hash::keccak256(address.as_bytes()))));
+        assert!(ledger.remove_account(&address).is_err());
+        // archive 모드라면 제거되기 전의 storage가 이력으로 남는다.
+        assert_eq!(ledger.get_storage_value_at(&address, &key, &BlockTag::Number(0)), Ok(value));
+        assert_eq!(ledger.get_storage_value_at(&address, &key, &BlockTag::Latest), Ok(H256::zero()));
+    }
+
+    #[test]
+    fn storage_backend() {
+        use std::sync::Arc;
+        use crate::backend::{Backend, KvTransaction, MemoryBackend, SqliteBackend, WriteBatch};
+        let sqlite = SqliteBackend::from_connection(rusqlite::Connection::open_in_memory().unwrap()).unwrap();
+        let backends: Vec<Arc<dyn Backend>> = vec![Arc::new(sqlite), Arc::new(MemoryBackend::new())];
+        for backend in backends.into_iter() {
+            let mut batch = WriteBatch::new();
+            batch.put("a", &[1, 1], &[1]);
+            batch.put("a", &[1, 2], &[2]);
+            batch.put("a", &[1, 0xff], &[3]);
+            batch.put("a", &[2], &[4]);
+            batch.put("b", &[1, 1], &[5]);
+            batch.delete("a", &[1, 2]);
+            backend.write(batch).unwrap();
+            assert_eq!(backend.get("a", &[1, 1]), Some(vec![1]));
+            assert!(!backend.exists("a", &[1, 2]));
+            let keys: Vec<Vec<u8>> = backend.scan("a", &[1]).into_iter().map(|(key, _)| key).collect();
+            assert_eq!(keys, vec![vec![1, 1], vec![1, 0xff]]);
+            assert_eq!(backend.range("a", &[1, 0xff], None).len(), 2);
+            assert_eq!(backend.last("a", &[1]), Some((vec![1, 0xff], vec![3])));
+            assert_eq!(backend.last("a", &[]), Some((vec![2], vec![4])));
+            assert_eq!(backend.last("c", &[]), None);
+
+            let mut tx = KvTransaction::new(backend.as_ref());
+            tx.put("b", &[1, 1], &[6]);
+            tx.delete("a", &[2]);
+            assert_eq!(tx.get("b", &[1, 1]), Some(vec![6]));
+            assert!(!tx.exists("a", &[2]));
+            assert_eq!(backend.get("b", &[1, 1]), Some(vec![5]));
+            tx.commit().unwrap();
+            assert_eq!(backend.get("b", &[1, 1]), Some(vec![6]));
+            assert!(!backend.exists("a", &[2]));
+
+            let mut ledger = Ledger::with_backend(backend.clone());
+            let mut tx = crate::transaction::Transaction::default();
+            tx.data = vec![1];
+            let hash = ledger.add_transaction(&tx).unwrap();
+            assert_eq!(Ledger::with_backend(backend).get_dag().tips(), vec![hash]);
+        }
+    }
+
+    #[test]
+    fn schema_migration() {
+        use ethereum_types::{Address, H256};
+        use rusqlite::Connection;
+        use crate::backend::SqliteBackend;
+        use crate::migration::{self, MigrationContext, MigrationError};
+        let directory = std::env::temp_dir().join(format!("ledger-migration-{}", hex::encode(H256::random())));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("biiot.db");
+        let contract = Address::random();
+        let contract_path = directory.join(format!("{}.db", hex::encode(contract.as_bytes())));
+
+        // 처음 버전의 노드가 남긴 데이터베이스. 정수는 32 bytes blob이며 tx 테이블에는 hash column이 없다.
+        let mut tx = crate::transaction::Transaction::default();
+        tx.nonce = 2;
+        tx.timestamp = 7;
+        tx.data = vec![1];
+        let hash = tx.hash();
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE account(key BLOB, nonce INTEGER, storage_root BLOB, codehash BLOB);\
+            CREATE TABLE tx(nonce INTEGER, recipient BLOB, data BLOB, v INTEGER, r BLOB, s BLOB, timestamp INTEGER,\
+                state_hash BLOB, parent_hash BLOB, committer BLOB, validators BLOB);\
+            CREATE TABLE dag_tip(hash BLOB PRIMARY KEY);").unwrap();
+        let account_key = H256::from(crypto::hash::keccak256(contract.as_bytes()));
+        conn.execute("INSERT INTO account (key, nonce, storage_root, codehash) VALUES (?, ?, ?, ?)", rusqlite::params![
+            account_key.as_bytes(), H256::from_low_u64_be(3).as_bytes(), H256::zero().as_bytes(), vec![0x60u8]]).unwrap();
+        conn.execute("INSERT INTO tx (nonce, recipient, data, v, r, s, timestamp, state_hash, parent_hash) \
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)", rusqlite::params![
+            H256::from_low_u64_le(tx.nonce as u64).as_bytes(), tx.recipient.as_bytes(), tx.data,
+            H256::from_low_u64_le(tx.v as u64).as_bytes(), tx.r, tx.s, H256::from_low_u64_le(tx.timestamp).as_bytes(),
+            tx.state_hash.as_bytes(), tx.parent_hash]).unwrap();
+        conn.execute("INSERT INTO dag_tip VALUES (?)", [hash.as_bytes()]).unwrap();
+        drop(conn);
+        let storage = Connection::open(&contract_path).unwrap();
+        storage.execute("CREATE TABLE storage(key BLOB, value BLOB)", []).unwrap();
+        storage.execute("INSERT INTO storage VALUES (?, ?)",
+                        [H256::from_low_u64_be(1).as_bytes(), H256::from_low_u64_be(9).as_bytes()]).unwrap();
+        drop(storage);
+
+        let backend = SqliteBackend::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(backend.schema_version(), migration::latest_version());
+        let ledger = Ledger::with_backend(std::sync::Arc::new(backend));
+        assert_eq!(ledger.next_nonce(&contract), 3);
+        assert_eq!(ledger.get_storage_value(&contract, &H256::from_low_u64_be(1)), H256::from_low_u64_be(9));
+        let imported = ledger.get_transactions().get_transaction(&hash).unwrap();
+        assert_eq!((imported.nonce, imported.timestamp, imported.data), (2, 7, vec![1]));
+        assert_eq!(ledger.get_dag().tips(), vec![hash]);
+        assert!(!contract_path.exists());
+        drop(ledger);
+
+        // 이미 최신 schema라면 다시 migration하지 않는다.
+        let mut conn = Connection::open(&path).unwrap();
+        let context = MigrationContext { directory: Some(directory.clone()) };
+        assert_eq!(migration::migrate(&mut conn, &context), Ok(migration::latest_version()));
+        conn.execute("INSERT INTO schema_version (version) VALUES (?)", [migration::latest_version() + 1]).unwrap();
+        assert_eq!(migration::migrate(&mut conn, &context),
+                   Err(MigrationError::NewerSchema(migration::latest_version() + 1)));
+        drop(conn);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn archive_history() {
+        use ethereum_types::{Address, H256};
+        use crypto::key::Sk;
+        use crate::archive::{ArchiveError, BlockTag};
+        use crate::dirty_state::DirtyStates;
+        use crate::milestone::Milestone;
+        use crate::receipt::Receipt;
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let authority = Sk::random();
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        let sk = Sk::random();
+        let (actuator, sender) = (Address::random(), Address::from(sk.pubkey().address()));
+        let (threshold, unchanged, interval) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2), H256::from_low_u64_be(3));
+        let value = H256::from_low_u64_be;
+        let set = |ledger: &mut Ledger, key: &H256, value: H256| {
+            let mut states = DirtyStates::new();
+            states.set_value(&actuator, key, &value);
+            ledger.commit_state(&states).unwrap();
+        };
+        let seal = |ledger: &mut Ledger, height: u64| {
+            let mut tx = Transaction::default();
+            tx.data = H256::random().as_bytes().to_vec();
+            tx.set_parents(&ledger.get_dag().tips());
+            let hash = ledger.add_transaction(&tx).unwrap();
+            let mut milestone = Milestone::new(height, vec![hash], ledger.state_root(), height);
+            milestone.sign(&authority);
+            ledger.add_milestone(&milestone, &authorities).unwrap();
+        };
+
+        set(&mut ledger, &threshold, value(10));
+        set(&mut ledger, &unchanged, value(20));
+        set(&mut ledger, &interval, value(30));
+        seal(&mut ledger, 0);
+        assert_eq!(ledger.enable_archive(), Ok(1));
+        set(&mut ledger, &threshold, value(11));
+        let tx = signed_transaction(&ledger, &sk, 0, &DirtyStates::new());
+        let receipt = Receipt::new(&tx.hash(), 1, &sender, vec![], 21000, None, vec![]);
+        ledger.commit(&tx, &DirtyStates::new(), &receipt).unwrap();
+        seal(&mut ledger, 1);
+        set(&mut ledger, &threshold, value(12));
+        set(&mut ledger, &interval, value(31));
+        seal(&mut ledger, 2);
+        set(&mut ledger, &threshold, value(13));
+        ledger.remove_account(&actuator).unwrap();
+
+        let at = |key: &H256, tag: BlockTag| ledger.get_storage_value_at(&actuator, key, &tag);
+        assert_eq!(at(&threshold, BlockTag::Number(1)), Ok(value(11)));
+        assert_eq!(at(&threshold, BlockTag::parse("0x2").unwrap()), Ok(value(12)));
+        assert_eq!(at(&threshold, BlockTag::Latest), Ok(H256::zero()));
+        assert_eq!(at(&unchanged, BlockTag::Number(2)), Ok(value(20)));
+        // archive 이후 처음 변경된 값은 변경 전의 값이 남아있다.
+        assert_eq!(at(&interval, BlockTag::Number(1)), Ok(value(30)));
+        assert_eq!(at(&interval, BlockTag::Number(2)), Ok(value(31)));
+        assert_eq!(at(&threshold, BlockTag::Earliest), Err(ArchiveError::NotArchived(0)));
+        assert_eq!(at(&threshold, BlockTag::Number(3)), Err(ArchiveError::UnknownHeight(3)));
+        assert_eq!(ledger.get_account_at(&sender, &BlockTag::Number(1)).unwrap().nonce, 1);
+        assert!(ledger.state_at(&BlockTag::Number(1)).unwrap().upsert_account(&Default::default()).is_err());
+        assert_eq!(ledger.enable_archive(), Ok(1));
+    }
+
+    #[test]
+    fn merkle_patricia_trie() {
+        use crate::trie::{MemoryTrieStorage, PatriciaTrie, EMPTY_ROOT};
+        let storage = MemoryTrieStorage::new();
+        let mut trie = PatriciaTrie::new(&storage);
+        assert_eq!(trie.root_hash(), EMPTY_ROOT);
+        assert_eq!(hex::encode(EMPTY_ROOT.as_bytes()),
+                   "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+        trie.insert(b"do", b"verb".to_vec()).unwrap();
+        trie.insert(b"horse", b"stallion".to_vec()).unwrap();
+        trie.insert(b"doge", b"coin".to_vec()).unwrap();
+        trie.insert(b"dog", b"puppy".to_vec()).unwrap();
+        let root = trie.commit();
+        assert_eq!(hex::encode(root.as_bytes()),
+                   "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84");
+
+        let reopened = PatriciaTrie::from_root(&storage, &root);
+        assert_eq!(reopened.get(b"doge"), Some(b"coin".to_vec()));
+        assert_eq!(reopened.get(b"cat"), None);
+
+        let proof = reopened.prove(b"dog");
+        assert_eq!(PatriciaTrie::verify_proof(&root, b"dog", &proof), Ok(Some(b"puppy".to_vec())));
+        assert_eq!(PatriciaTrie::verify_proof(&root, b"dot", &reopened.prove(b"dot")), Ok(None));
+        assert!(PatriciaTrie::verify_proof(&root, b"horse", &vec![]).is_err());
+
+        let mut trie = PatriciaTrie::from_root(&storage, &root);
+        trie.insert(b"cat", b"meow".to_vec()).unwrap();
+        trie.remove(b"cat").unwrap();
+        assert_eq!(trie.commit(), root);
+        for key in [b"do".as_ref(), b"horse", b"doge", b"dog"].iter() { trie.remove(key).unwrap(); }
+        assert_eq!(trie.commit(), EMPTY_ROOT);
     }
 
     #[test]
-    fn store_data() {
-        let mut ledger = Ledger::new();
+    fn commit_state_roots() {
+        use ethereum_types::{Address, H256};
+        use crate::dirty_state::DirtyStates;
+        use crate::trie::SecureTrie;
+        let mut ledger = Ledger::in_memory();
+        let address = Address::random();
+        let mut states = DirtyStates::new();
+        states.set_value(&address, &H256::from_low_u64_be(1), &H256::from_low_u64_be(0x30));
+        let root = ledger.commit_state(&states).unwrap();
+        assert_eq!(ledger.state_root(), root);
+        assert_eq!(ledger.get_storage_value(&address, &H256::from_low_u64_be(1)), H256::from_low_u64_be(0x30));
+
+        let node = ledger.get_accounts().get_account(&address);
+        assert!(!node.storage_root.is_zero());
+        let proof = ledger.prove_account(&address);
+        assert_eq!(SecureTrie::verify_proof(&root, address.as_bytes(), &proof), Ok(Some(node.trie_value())));
+        let proof = ledger.prove_storage(&address, &H256::from_low_u64_be(1));
+        let value = SecureTrie::verify_proof(&node.storage_root, H256::from_low_u64_be(1).as_bytes(), &proof);
+        assert_eq!(value, Ok(Some(vec![0x30])));
+
+        ledger.remove_account(&address).unwrap();
+        assert_ne!(ledger.state_root(), root);
+        assert_eq!(SecureTrie::verify_proof(&ledger.state_root(), address.as_bytes(),
+                                            &ledger.prove_account(&address)), Ok(None));
+    }
+
+    #[test]
+    fn transaction_dag() {
+        use ethereum_types::H256;
+        use crate::dag::DagError;
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let make = |parents: Vec<H256>| {
+            let mut tx = Transaction::default();
+            tx.data = H256::random().as_bytes().to_vec();
+            tx.set_parents(&parents);
+            tx
+        };
+        let base = match ledger.get_dag().tips().first() {
+            Some(tip) => { tip.clone() }
+            None => { ledger.add_transaction(&make(vec![])).unwrap() }
+        };
+        assert_eq!(ledger.add_transaction(&make(vec![])), Err(DagError::NoParents));
+        let missing = H256::random();
+        assert_eq!(ledger.add_transaction(&make(vec![missing])), Err(DagError::MissingParent(missing)));
+
+        let a = ledger.add_transaction(&make(vec![base])).unwrap();
+        let b = ledger.add_transaction(&make(vec![base])).unwrap();
+        let c_tx = make(vec![a, b]);
+        let c = ledger.add_transaction(&c_tx).unwrap();
+        assert_eq!(ledger.add_transaction(&c_tx), Err(DagError::Duplicate(c)));
+        assert_eq!(ledger.get_transactions().get_transaction(&c).unwrap().parents(), vec![a, b]);
+
+        let dag = ledger.get_dag();
+        let tips = dag.tips();
+        assert!(tips.contains(&c) && !tips.contains(&a) && !tips.contains(&b) && !tips.contains(&base));
+        let ancestors = dag.ancestors(&c);
+        assert!(ancestors.contains(&a) && ancestors.contains(&b) && ancestors.contains(&base));
+        assert_eq!(dag.descendants(&a), vec![c]);
+        let mut children = vec![a, b];
+        children.sort();
+        assert_eq!(dag.children(&base), children);
+
+        let order = dag.topological_order();
+        let pos = |hash: &H256| order.iter().position(|h| h == hash).unwrap();
+        assert!(pos(&base) < pos(&a) && pos(&base) < pos(&b));
+        assert!(pos(&a) < pos(&c) && pos(&b) < pos(&c));
+        assert_eq!(order, dag.topological_order());
+    }
+
+    #[test]
+    fn milestone_checkpoint() {
+        use ethereum_types::{Address, H256};
+        use crypto::key::Sk;
+        use crate::milestone::{Milestone, MilestoneError};
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let mut tx = Transaction::default();
+        tx.data = H256::random().as_bytes().to_vec();
+        tx.set_parents(&ledger.get_dag().tips());
+        let hash = ledger.add_transaction(&tx).unwrap();
+        let receipt = crate::receipt::Receipt::new(&hash, 1, &Address::zero(), vec![], 21000, None, vec![]);
+        ledger.get_receipts().insert_receipt(&receipt).unwrap();
+
+        let authority = Sk::random();
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        let latest = ledger.latest_milestone();
+        let height = latest.as_ref().map_or(0, |latest| latest.height + 1);
+        let timestamp = latest.as_ref().map_or(0, |latest| latest.timestamp) + 1;
+        let root = ledger.state_root();
+        let make = |height: u64, tips: Vec<H256>, sk: &Sk| {
+            let mut milestone = Milestone::new(height, tips, root, timestamp);
+            milestone.sign(sk);
+            milestone
+        };
+
+        let other = Sk::random();
+        assert_eq!(ledger.add_milestone(&make(height, vec![hash], &other), &authorities),
+                   Err(MilestoneError::Unauthorized(Address::from(other.pubkey().address()))));
+        assert_eq!(ledger.add_milestone(&make(height + 1, vec![hash], &authority), &authorities),
+                   Err(MilestoneError::InvalidHeight(height)));
+        let unknown = H256::random();
+        assert_eq!(ledger.add_milestone(&make(height, vec![unknown], &authority), &authorities),
+                   Err(MilestoneError::UnknownTip(unknown)));
+
+        let milestone = make(height, vec![hash], &authority);
+        assert_eq!(ledger.add_milestone(&milestone, &authorities), Ok(milestone.hash()));
+        let latest = ledger.latest_milestone().unwrap();
+        assert_eq!(latest.height, height);
+        assert_eq!(latest.tips, vec![hash]);
+        assert_eq!(latest.signer(), Some(authorities[0]));
+        assert!(ledger.get_milestones().get_milestone_by_hash(&milestone.hash()).is_some());
+        assert!(ledger.is_final(&hash));
+        assert_eq!(ledger.get_receipt(&hash).unwrap().milestone, Some(height));
+        for parent in tx.parents().iter() { assert!(ledger.is_final(parent)); }
+    }
+
+    #[test]
+    fn log_index() {
+        use ethereum_types::{Address, BloomInput, H256};
+        use crypto::key::Sk;
+        use crate::log::Log;
+        use crate::log_index::LogFilter;
+        use crate::milestone::Milestone;
+        use crate::receipt::Receipt;
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let authority = Sk::random();
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        let (sensor, other) = (Address::random(), Address::random());
+        let (temperature, humidity) = (H256::random(), H256::random());
+
+        // 마일스톤마다 sensor와 other가 하나씩 log를 남기는 트랜잭션 두 개를 확정한다.
+        for height in 0..3u64 {
+            let mut tips = vec![];
+            for (address, topic) in vec![(sensor, temperature), (other, humidity)].into_iter() {
+                let mut tx = Transaction::default();
+                tx.data = H256::random().as_bytes().to_vec();
+                tx.set_parents(&ledger.get_dag().tips());
+                let hash = ledger.add_transaction(&tx).unwrap();
+                let logs = vec![Log::new(&address, vec![topic, H256::from_low_u64_be(height)], vec![height as u8])];
+                let receipt = Receipt::new(&hash, 1, &Address::zero(), vec![], 21000, None, logs);
+                ledger.get_receipts().insert_receipt(&receipt).unwrap();
+                tips = vec![hash];
+            }
+            let mut milestone = Milestone::new(height, tips, ledger.state_root(), height);
+            milestone.sign(&authority);
+            ledger.add_milestone(&milestone, &authorities).unwrap();
+        }
+        let bloom = ledger.get_log_index().get_bloom(1);
+        assert!(bloom.contains_input(BloomInput::Raw(sensor.as_bytes())));
+        assert!(bloom.contains_input(BloomInput::Raw(humidity.as_bytes())));
+        assert!(ledger.get_log_index().get_bloom(3).is_empty());
+
+        assert_eq!(ledger.get_logs(&LogFilter::new(0, 2)).len(), 6);
+        let mut filter = LogFilter::new(1, 2);
+        filter.addresses = vec![sensor];
+        let logs = ledger.get_logs(&filter);
+        assert_eq!(logs.iter().map(|log| log.milestone).collect::<Vec<u64>>(), vec![1, 2]);
+        assert!(logs.iter().all(|log| log.log.address == sensor && log.log_index == 0));
+        // 같은 마일스톤 안에서는 부모 트랜잭션이 먼저 온다.
+        assert_eq!(logs[0].tx_index, 0);
+        assert_eq!(ledger.get_receipt(&logs[0].tx_hash).unwrap().logs[0].data, vec![1]);
+
+        let mut filter = LogFilter::new(0, 10);
+        filter.topics = vec![vec![], vec![H256::from_low_u64_be(2)]];
+        let logs = ledger.get_logs(&filter);
+        assert_eq!(logs.len(), 2);
+        assert_eq!((logs[0].milestone, logs[0].tx_index, logs[1].tx_index), (2, 0, 1));
+        assert_eq!(ledger.receipt_position(&logs[0].tx_hash), Some((0, 21000)));
+        assert_eq!(ledger.receipt_position(&logs[1].tx_hash), Some((1, 42000)));
+        assert_eq!(ledger.receipt_position(&H256::random()), None);
+        filter.topics = vec![vec![humidity], vec![H256::from_low_u64_be(2)]];
+        assert_eq!(ledger.get_logs(&filter)[0].log.address, other);
+        filter.addresses = vec![sensor];
+        assert!(ledger.get_logs(&filter).is_empty());
+    }
+
+    #[test]
+    fn chain_snapshot() {
+        use ethereum_types::{Address, H256};
+        use crypto::key::Sk;
+        use crate::dirty_state::DirtyStates;
+        use crate::milestone::Milestone;
+        use crate::archive::BlockTag;
+        use crate::snapshot::{self, SnapshotError};
+        use crate::transaction::Transaction;
+        let mut ledger = Ledger::in_memory();
+        let authority = Sk::random();
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        assert_eq!(snapshot::export(&ledger, &authority, 10).err(), Some(SnapshotError::NoMilestone));
+        ledger.enable_archive().unwrap();
+
+        let contract = Address::random();
+        let mut states = DirtyStates::new();
+        states.set_value(&contract, &H256::from_low_u64_be(1), &H256::from_low_u64_be(2));
+        ledger.commit_state(&states).unwrap();
+        let mut parents = vec![];
+        for idx in 0..3u8 {
+            let mut tx = Transaction::default();
+            tx.data = vec![idx];
+            tx.set_parents(&parents);
+            parents = vec![ledger.add_transaction(&tx).unwrap()];
+        }
+        let mut milestone = Milestone::new(0, parents.clone(), ledger.state_root(), 1);
+        milestone.sign(&authority);
+        ledger.add_milestone(&milestone, &authorities).unwrap();
+        // 마일스톤 이후의 변경은 snapshot에 포함되지 않는다.
+        let root = ledger.state_root();
+        let mut states = DirtyStates::new();
+        states.set_value(&contract, &H256::from_low_u64_be(1), &H256::from_low_u64_be(3));
+        states.set_value(&Address::random(), &H256::from_low_u64_be(1), &H256::from_low_u64_be(4));
+        ledger.commit_state(&states).unwrap();
+        let bytes = snapshot::export(&ledger, &authority, 2).unwrap();
+
+        let mut imported = Ledger::in_memory();
+        let other = Sk::random();
+        assert_eq!(snapshot::import(&mut imported, &bytes, &vec![]).err(),
+                   Some(SnapshotError::Unauthorized(authorities[0])));
+        let mut corrupted = bytes.clone();
+        corrupted[20] ^= 1;
+        assert_eq!(snapshot::import(&mut imported, &corrupted, &authorities).err(), Some(SnapshotError::Checksum));
+        let forged = snapshot::export(&ledger, &other, 2).unwrap();
+        assert_eq!(snapshot::import(&mut imported, &forged, &authorities).err(),
+                   Some(SnapshotError::Unauthorized(Address::from(other.pubkey().address()))));
+
+        // 서명은 올바르지만 state root가 마일스톤과 다른 snapshot
+        let (mut mismatched, _) = snapshot::Snapshot::decode(&bytes).unwrap();
+        mismatched.milestone.state_root = H256::random();
+        mismatched.milestone.sign(&authority);
+        assert_eq!(snapshot::import(&mut imported, &mismatched.encode(&authority), &authorities).err(),
+                   Some(SnapshotError::StateRoot(root)));
+
+        let snapshot = snapshot::import(&mut imported, &bytes, &authorities).unwrap();
+        assert_eq!(snapshot.transactions.len(), 2);
+        assert_eq!(imported.state_root(), root);
+        assert_eq!(imported.get_storage_value(&contract, &H256::from_low_u64_be(1)), H256::from_low_u64_be(2));
+        assert_eq!(imported.latest_milestone().unwrap().hash(), milestone.hash());
+        assert_eq!(imported.get_dag().tips(), parents);
+        assert!(imported.is_final(&parents[0]));
+        assert_eq!(imported.get_accounts().get_account(&contract).storage_root,
+                   ledger.state_at(&BlockTag::Number(0)).unwrap().get_accounts().get_account(&contract).storage_root);
+        assert_eq!(snapshot::import(&mut imported, &bytes, &authorities).err(), Some(SnapshotError::NotEmpty));
+    }
+
+    #[test]
+    fn transaction_receipt() {
+        use ethereum_types::{Address, BloomInput, H256};
+        use crate::dirty_state::DirtyStates;
+        use crate::ledger::CommitError;
+        use crate::log::Log;
+        use crate::receipt::Receipt;
+        let mut ledger = Ledger::in_memory();
+        let sk = crypto::key::Sk::random();
+        let sender = Address::from(sk.pubkey().address());
+        let contract = Address::random();
+        let topic = H256::random();
+        let mut states = DirtyStates::new();
+        states.set_value(&contract, &H256::from_low_u64_be(1), &H256::from_low_u64_be(7));
+        let tx = signed_transaction(&ledger, &sk, 0, &states);
+        let tx_hash = tx.hash();
+        let receipt = Receipt::new(&tx_hash, 1, &sender, vec![1, 2], 53000, Some(contract),
+                                   vec![Log::new(&contract, vec![topic], vec![3])]);
+        ledger.commit(&tx, &states, &receipt).unwrap();
+        assert_eq!(ledger.next_nonce(&receipt.from), 1);
+        assert_eq!(ledger.get_storage_value(&contract, &H256::from_low_u64_be(1)), H256::from_low_u64_be(7));
+
+        let stored = ledger.get_receipt(&tx_hash).unwrap();
+        assert!(stored.is_success());
+        assert_eq!(stored.from, receipt.from);
+        assert_eq!(stored.output, vec![1, 2]);
+        assert_eq!(stored.gas_used, 53000);
+        assert_eq!(stored.contract_address, Some(contract));
+        assert_eq!(stored.logs.len(), 1);
+        assert_eq!(stored.logs[0].topics, vec![topic]);
+        assert_eq!(stored.milestone, None);
+        assert!(stored.logs_bloom.contains_input(BloomInput::Raw(contract.as_bytes())));
+        assert!(stored.logs_bloom.contains_input(BloomInput::Raw(topic.as_bytes())));
+        assert!(ledger.get_receipt(&H256::random()).is_none());
+
+        // receipt를 기록하지 못하면 state 변경도 반영되지 않는다.
+        let mut states = DirtyStates::new();
+        states.set_value(&contract, &H256::from_low_u64_be(1), &H256::from_low_u64_be(8));
+        let mut replayed = signed_transaction(&ledger, &sk, 0, &states);
+        replayed.timestamp = 1;
+        let receipt = Receipt::new(&replayed.hash(), 1, &sender, vec![], 21000, None, vec![]);
+        assert_eq!(ledger.commit(&replayed, &states, &receipt), Err(CommitError::Nonce(1)));
+        assert_eq!(ledger.get_storage_value(&contract, &H256::from_low_u64_be(1)), H256::from_low_u64_be(7));
+        assert_eq!(ledger.next_nonce(&receipt.from), 1);
+    }
+
+    #[test]
+    fn atomic_commit() {
+        use ethereum_types::{Address, H256, U256};
+        use crate::dirty_state::DirtyStates;
+        use crate::ledger::CommitError;
+        use crate::receipt::Receipt;
+        use crate::transaction::{RawTransaction, Transaction};
+        let mut ledger = Ledger::in_memory();
+        let sk = crypto::key::Sk::random();
+        let (contract, sender) = (Address::random(), Address::from(sk.pubkey().address()));
+        let mut states = DirtyStates::new();
+        let mut reversed = DirtyStates::new();
+        let keys: Vec<H256> = (1..6).map(H256::from_low_u64_be).collect();
+        for key in keys.iter() { states.set_value(&contract, key, key); }
+        for key in keys.iter().rev() { reversed.set_value(&contract, key, key); }
+        assert_eq!(states.hash(), reversed.hash());
+
+        let make = |nonce: usize, state_hash: H256| {
+            let mut raw = RawTransaction {
+                nonce, gas_price: U256::zero(), gas: U256::zero(), recipient: contract,
+                value: U256::zero(), data: vec![], v: 0, r: vec![], s: vec![],
+            };
+            raw.sign(&sk, None);
+            let mut tx = Transaction::from_raw_transaction(&raw);
+            tx.state_hash = state_hash;
+            tx
+        };
+        let receipt = |tx: &Transaction| Receipt::new(&tx.hash(), 1, &sender, vec![], 21000, None, vec![]);
+        let root = ledger.state_root();
+        let wrong = make(0, H256::random());
+        assert_eq!(ledger.commit(&wrong, &states, &receipt(&wrong)), Err(CommitError::StateHash(states.hash())));
+        let gapped = make(1, states.hash());
+        assert_eq!(ledger.commit(&gapped, &states, &receipt(&gapped)), Err(CommitError::Nonce(0)));
+        let tx = make(0, states.hash());
+        assert_eq!(ledger.commit(&tx, &states, &receipt(&wrong)), Err(CommitError::ReceiptMismatch));
+        // 서명이 없거나 다른 account가 서명한 트랜잭션은 receipt의 sender로 반영할 수 없다.
+        let mut unsigned = Transaction::default();
+        unsigned.state_hash = states.hash();
+        assert_eq!(ledger.commit(&unsigned, &states, &receipt(&unsigned)), Err(CommitError::InvalidSignature));
+        let forged = Receipt::new(&tx.hash(), 1, &Address::random(), vec![], 21000, None, vec![]);
+        assert_eq!(ledger.commit(&tx, &states, &forged), Err(CommitError::InvalidSignature));
+        // 실패한 commit은 아무것도 기록하지 않는다.
+        assert_eq!(ledger.state_root(), root);
+        assert!(ledger.get_dag().is_empty());
+        assert_eq!(ledger.get_storage_value(&contract, &keys[0]), H256::zero());
+
+        let root = ledger.commit(&tx, &states, &receipt(&tx)).unwrap();
+        assert_eq!(ledger.state_root(), root);
+        assert_eq!(ledger.get_dag().tips(), vec![tx.hash()]);
+        assert!(ledger.get_receipt(&tx.hash()).unwrap().is_success());
+        assert_eq!(ledger.get_storage_value(&contract, &keys[4]), keys[4]);
+        assert_eq!(ledger.next_nonce(&sender), 1);
+        assert_eq!(ledger.commit(&tx, &states, &receipt(&tx)),
+                   Err(CommitError::Transaction(crate::dag::DagError::Duplicate(tx.hash()))));
+    }
+
+    #[test]
+    fn sender_nonce() {
+        use ethereum_types::{Address, U256};
+        use crate::dirty_state::DirtyStates;
+        use crate::pool::PoolError;
+        use crate::receipt::Receipt;
+        use crate::transaction::RawTransaction;
+        use crate::ledger::CommitError;
+        let mut ledger = Ledger::in_memory();
+        let sk = crypto::key::Sk::random();
+        let sender = Address::from(sk.pubkey().address());
+        let raw_tx = |nonce: usize| RawTransaction {
+            nonce, gas_price: U256::zero(), gas: U256::zero(), recipient: Address::zero(),
+            value: U256::zero(), data: vec![], v: 0, r: vec![], s: vec![],
+        };
+        assert_eq!(ledger.next_nonce(&sender), 0);
+        let max_gap = ledger.get_pool().config().max_nonce_gap as usize;
+        assert_eq!(ledger.admit_transaction(&sender, raw_tx(max_gap).into()), Err(PoolError::Gap(0)));
+        ledger.admit_transaction(&sender, raw_tx(0).into()).unwrap();
+        let hash = crate::pool::tx_hash(&raw_tx(0));
+        assert_eq!(ledger.admit_transaction(&sender, raw_tx(0).into()), Err(PoolError::AlreadyKnown(hash)));
+        ledger.admit_transaction(&sender, raw_tx(1).into()).unwrap();
+        assert_eq!(ledger.pending_nonce(&sender), 2);
+        assert_eq!(ledger.next_nonce(&sender), 0);
+
+        // nonce와 sender는 서명된 트랜잭션에서 얻는다.
+        let commit = |ledger: &mut Ledger, nonce: usize, timestamp: u64| {
+            let mut tx = signed_transaction(ledger, &sk, nonce, &DirtyStates::new());
+            tx.timestamp = timestamp;
+            let receipt = Receipt::new(&tx.hash(), 1, &sender, vec![], 21000, None, vec![]);
+            ledger.commit(&tx, &DirtyStates::new(), &receipt)
+        };
+        assert_eq!(commit(&mut ledger, 1, 0), Err(CommitError::Nonce(0)));
+        commit(&mut ledger, 0, 0).unwrap();
+        assert_eq!(ledger.next_nonce(&sender), 1);
+        assert_eq!(ledger.pending_nonce(&sender), 2);
+        // 이미 반영된 트랜잭션은 다시 실행할 수 없다.
+        assert_eq!(commit(&mut ledger, 0, 1), Err(CommitError::Nonce(1)));
+        assert_eq!(ledger.admit_transaction(&sender, raw_tx(0).into()), Err(PoolError::Duplicate(2)));
+        assert!(!ledger.get_pool().contains(&hash));
+    }
+
+    #[test]
+    fn transaction_pool() {
+        use ethereum_types::{Address, U256};
+        use crate::pool::{PoolConfig, PoolError, TxPool};
+        use crate::transaction::RawTransaction;
+        let raw_tx = |nonce: usize, gas_price: u64| RawTransaction {
+            nonce, gas_price: U256::from(gas_price), gas: U256::zero(), recipient: Address::zero(),
+            value: U256::zero(), data: vec![], v: 0, r: vec![], s: vec![],
+        };
+        let pool = TxPool::with_config(PoolConfig { capacity: 4, ..PoolConfig::default() });
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        pool.admit(&a, raw_tx(0, 10).into(), 0).unwrap();
+        pool.admit(&a, raw_tx(1, 30).into(), 0).unwrap();
+        pool.admit(&b, raw_tx(0, 20).into(), 0).unwrap();
+        pool.admit(&b, raw_tx(2, 50).into(), 0).unwrap();
+        assert_eq!(pool.counts(), (3, 1));
+
+        // 같은 nonce는 gas price를 충분히 올려야 교체된다.
+        let old = crate::pool::tx_hash(&raw_tx(0, 10));
+        assert_eq!(pool.admit(&a, raw_tx(0, 10).into(), 0), Err(PoolError::AlreadyKnown(old)));
+        let mut same_price = raw_tx(0, 10);
+        same_price.data = vec![1];
+        assert_eq!(pool.admit(&a, same_price.into(), 0), Err(PoolError::Underpriced));
+        pool.admit(&a, raw_tx(0, 12).into(), 0).unwrap();
+        assert!(!pool.contains(&old));
+        assert_eq!(pool.len(), 4);
+
+        // pool이 가득 차면 future 트랜잭션이 먼저 밀려난다.
+        pool.admit(&c, raw_tx(0, 15).into(), 0).unwrap();
+        assert!(!pool.contains(&crate::pool::tx_hash(&raw_tx(2, 50))));
+        // 그 다음은 gas price가 가장 낮은 마지막 트랜잭션이다.
+        assert_eq!(pool.admit(&c, raw_tx(1, 1).into(), 0), Err(PoolError::Full));
+
+        let selected: Vec<(Address, u64)> = pool.select(10).into_iter()
+            .map(|(sender, tx)| (sender, tx.nonce())).collect();
+        assert_eq!(selected, vec![(b, 0), (c, 0), (a, 0), (a, 1)]);
+        assert_eq!(pool.select(2).len(), 2);
+        pool.prune(&a, 1);
+        assert_eq!(pool.pending_nonce(&a, 1), 2);
+        assert_eq!(pool.len(), 3);
+
+        // 최대에 가까운 gas price도 교체할 수 있으며 계산이 넘치지 않는다.
+        let pool = TxPool::new();
+        let mut near_max = raw_tx(0, 0);
+        near_max.gas_price = U256::MAX - 1;
+        pool.admit(&a, near_max.clone().into(), 0).unwrap();
+        near_max.data = vec![1];
+        assert_eq!(pool.admit(&a, near_max.clone().into(), 0), Err(PoolError::Underpriced));
+        near_max.gas_price = U256::MAX;
+        pool.admit(&a, near_max.into(), 0).unwrap();
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn data_directory() {
+        use ethereum_types::H256;
+        use common::datadir::DataDir;
+        let root = std::env::temp_dir().join(format!("ledger-datadir-{}", hex::encode(H256::random())));
+        let first = DataDir::new(root.join("first"));
+        let second = DataDir::new(root.join("second"));
+        let mut a = Ledger::new(&first);
+        let b = Ledger::new(&second);
+        assert_eq!(a.get_data_dir(), Some(&first));
+        assert!(first.file("biiot.db").exists() && second.file("biiot.db").exists());
+
+        // 같은 프로세스의 ledger라도 data directory가 다르면 서로의 데이터를 볼 수 없다.
+        let mut tx = crate::transaction::Transaction::default();
+        tx.data = vec![1];
+        let hash = a.add_transaction(&tx).unwrap();
+        assert!(a.get_transactions().exist(&hash));
+        assert!(!b.get_transactions().exist(&hash));
+        drop(a);
+        assert!(Ledger::open(&first).unwrap().get_transactions().exist(&hash));
+        assert!(Ledger::in_memory().get_data_dir().is_none());
+        drop(b);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn integrity_check() {
+        use std::sync::Arc;
+        use ethereum_types::{Address, H256};
+        use crypto::key::Sk;
+        use crate::dirty_state::DirtyStates;
+        use crate::fsck::{self, CheckOptions, Problem, Replay, QUARANTINE_COLUMN};
+        use crate::milestone::Milestone;
+        use crate::receipt::{Receipt, RECEIPT_COLUMN};
+        use crate::transaction::Transaction;
+        use crate::dag::DAG_TIP_COLUMN;
+
+        // 항상 storage 1번 값을 1로 바꾸는 실행
+        struct SetOne;
+        impl Replay for SetOne {
+            fn replay(&self, _: &Ledger, _: &Arc<Ledger>, tx: &Transaction, _: &Receipt) -> Option<DirtyStates> {
+                let mut states = DirtyStates::new();
+                states.set_value(&tx.recipient, &H256::from_low_u64_be(1), &H256::from_low_u64_be(1));
+                Some(states)
+            }
+        }
+
+        let mut ledger = Ledger::in_memory();
+        let sender = Address::random();
+        let contract = Address::random();
+        let mut hashes = vec![];
+        for (nonce, value) in vec![1u64, 2].into_iter().enumerate() {
+            let mut states = DirtyStates::new();
+            states.set_value(&contract, &H256::from_low_u64_be(1), &H256::from_low_u64_be(value));
+            let mut tx = Transaction::default();
+            tx.nonce = nonce;
+            tx.recipient = contract;
+            tx.state_hash = states.hash();
+            tx.set_parents(&ledger.get_dag().tips());
+            // 손상된 서명. commit은 서명을 확인하므로 각 row를 따로 기록한다.
+            tx.r = vec![0; 32];
+            tx.s = vec![0; 32];
+            let receipt = Receipt::new(&tx.hash(), 1, &sender, vec![], 21000, None, vec![]);
+            ledger.add_transaction(&tx).unwrap();
+            ledger.commit_state(&states).unwrap();
+            ledger.get_receipts().insert_receipt(&receipt).unwrap();
+            hashes.push(tx.hash());
+        }
+        let authority = Sk::random();
+        let mut milestone = Milestone::new(0, vec![hashes[1]], ledger.state_root(), 1);
+        milestone.sign(&authority);
+        let authorities = vec![Address::from(authority.pubkey().address())];
+        ledger.add_milestone(&milestone, &authorities).unwrap();
+
+        let options = CheckOptions { authorities: authorities.clone(), quarantine: false };
+        let report = fsck::check(&ledger, &options, Some(&SetOne));
+        assert_eq!((report.transactions, report.milestones, report.replayed), (2, 1, 2));
+        let problems: Vec<&Problem> = report.issues.iter().map(|issue| &issue.problem).collect();
+        assert_eq!(problems, vec![&Problem::InvalidSignature, &Problem::InvalidSignature, &Problem::StateHash(
+            ledger.get_transactions().get_transaction(&hashes[0]).unwrap().state_hash)]);
+        assert_eq!(report.issues[2].key, hashes[1].as_bytes().to_vec());
+        assert_eq!(report.exit_code(), 4);
+        let options = CheckOptions { authorities: vec![Address::random()], quarantine: false };
+        assert!(fsck::check(&ledger, &options, None).issues.iter()
+            .any(|issue| issue.problem == Problem::Milestone(crate::milestone::MilestoneError::Unauthorized(authorities[0]))));
+
+        // 전원이 꺼지며 손상된 row들은 격리된다.
+        let backend = ledger.get_backend();
+        backend.put(RECEIPT_COLUMN, H256::random().as_bytes(), &[0xc1]).unwrap();
+        backend.put(DAG_TIP_COLUMN, H256::random().as_bytes(), &[]).unwrap();
+        let options = CheckOptions { authorities, quarantine: true };
+        let report = fsck::check(&ledger, &options, None);
+        let corrupt: Vec<&str> = report.issues.iter().filter(|issue| issue.quarantined).map(|issue| issue.problem.name()).collect();
+        assert_eq!(corrupt, vec!["dangling_reference", "corrupt"]);
+        assert_eq!(report.to_json()["issues"][2]["quarantined"], true);
+        assert_eq!(backend.scan(QUARANTINE_COLUMN, &[]).len(), 2);
+        assert_eq!(fsck::check(&ledger, &options, None).issues.len(), 2);
+
+        // fee와 value가 있는 서명된 legacy, typed 트랜잭션은 서명 문제로 보고되지 않는다.
+        use ethereum_types::U256;
+        use crate::envelope::{DynamicFeeTransaction, TypedTransaction};
+        use crate::transaction::RawTransaction;
+        let mut ledger = Ledger::in_memory();
+        let sk = Sk::random();
+        let signer = Address::from(sk.pubkey().address());
+        let mut legacy: TypedTransaction = RawTransaction {
+            nonce: 0, gas_price: U256::from(20), gas: U256::from(21000), recipient: contract,
+            value: U256::from(5), data: vec![1], v: 0, r: vec![], s: vec![],
+        }.into();
+        let mut typed = TypedTransaction::DynamicFee(DynamicFeeTransaction {
+            chain_id: 7, nonce: 1, max_priority_fee_per_gas: U256::from(3), max_fee_per_gas: U256::from(20),
+            gas: U256::from(21000), recipient: Some(contract), value: U256::from(5), data: vec![2], access_list: vec![],
+            y_parity: 0, r: vec![], s: vec![],
+        });
+        for signed in vec![&mut legacy, &mut typed] {
+            signed.sign(&sk, 7);
+            let mut tx = signed.to_transaction();
+            tx.set_parents(&ledger.get_dag().tips());
+            tx.state_hash = DirtyStates::new().hash();
+            let receipt = Receipt::new(&tx.hash(), 1, &signer, vec![], 21000, None, vec![]);
+            ledger.commit(&tx, &DirtyStates::new(), &receipt).unwrap();
+        }
+        let options = CheckOptions { authorities: vec![], quarantine: false };
+        let report = fsck::check(&ledger, &options, None);
+        assert_eq!(report.transactions, 2);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.exit_code(), 0);
+    }
+
+    #[test]
+    fn genesis_initialization() {
+        use ethereum_types::{Address, H256};
+        use crate::fsck::{self, CheckOptions};
+        use crate::genesis::{Genesis, GenesisError};
+        use crate::milestone::Milestone;
+        let authority = crypto::key::Sk::random();
+        let authority_address = Address::from(authority.pubkey().address());
+        let json = format!(r#"{{
+            "chainId": 1337, "networkId": "0x10", "timestamp": 100,
+            "authorities": ["0x{}"],
+            "contracts": {{ "0x00000000000000000000000000000000000000aa": {{
+                "code": "0x600160005500", "storage": {{ "0x01": "0x2a", "0x02": "0x00" }} }} }},
+            "devices": {{ "0x00000000000000000000000000000000000000d1": {{
+                "owner": "0x00000000000000000000000000000000000000b0", "name": "sensor" }} }}
+        }}"#, hex::encode(authority_address.as_bytes()));
+        let genesis = Genesis::from_json(json.as_str()).unwrap();
+        assert_eq!((genesis.chain_id, genesis.network_id, genesis.contracts[0].storage.len()), (1337, 16, 1));
+
+        // 같은 genesis로 초기화한 노드는 같은 genesis 마일스톤을 갖는다.
+        let (mut first, mut second) = (Ledger::in_memory(), Ledger::in_memory());
+        let hash = first.initialize(&genesis).unwrap();
+        assert_eq!(second.initialize(&genesis).unwrap(), hash);
+        assert_eq!(first.initialize(&genesis).unwrap(), hash);
+        assert_eq!(first.latest_milestone().unwrap().hash(), hash);
+        let contract = Address::from_low_u64_be(0xaa);
+        assert_eq!(first.get_storage_value(&contract, &H256::from_low_u64_be(1)), H256::from_low_u64_be(0x2a));
+        assert_eq!(first.get_accounts().get_account(&contract).codehash, vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00]);
+        assert_eq!(first.get_devices().get_device(&Address::from_low_u64_be(0xd1)).unwrap().owner, Address::from_low_u64_be(0xb0));
+        assert_eq!(first.chain_config().unwrap().authorities, vec![authority_address]);
+        assert!(fsck::check(&first, &CheckOptions { authorities: vec![], quarantine: false }, None).is_clean());
+
+        // 다른 genesis의 ledger와 peer는 거부된다.
+        let mut other = genesis.clone();
+        other.timestamp += 1;
+        assert_eq!(first.initialize(&other), Err(GenesisError::Mismatch(hash)));
+        let mut third = Ledger::in_memory();
+        let other_hash = third.initialize(&other).unwrap();
+        assert_ne!(other_hash, hash);
+        let status = first.chain_config().unwrap().status();
+        assert_eq!(status.check(&second.chain_config().unwrap().status()), Ok(()));
+        assert_eq!(status.check(&third.chain_config().unwrap().status()), Err(GenesisError::Mismatch(other_hash)));
+        assert!(Genesis::from_json(r#"{"authorities": ["0x01"]}"#).is_err());
+
+        // genesis 이후의 마일스톤은 genesis의 authority가 서명한다.
+        let tx = genesis.transaction();
+        let mut child = crate::transaction::Transaction::default();
+        child.parent_hash = tx.hash().as_bytes().to_vec();
+        child.timestamp = 101;
+        let child = first.add_transaction(&child).unwrap();
+        let mut milestone = Milestone::new(1, vec![child], first.state_root(), 101);
+        milestone.sign(&authority);
+        first.add_milestone(&milestone, &first.chain_config().unwrap().authorities).unwrap();
+    }
+
+    #[test]
+    fn transaction_conflicts() {
+        use ethereum_types::Address;
+        use crate::backend::WriteBatch;
+        use crate::conflict::TxStatus;
+        use crate::genesis::Genesis;
+        use crate::milestone::Milestone;
+        use crate::transaction::Transaction;
+        let authority = crypto::key::Sk::random();
+        let validator = Address::from(authority.pubkey().address());
+        let mut ledger = Ledger::in_memory();
+        let genesis = Genesis { authorities: vec![validator], ..Default::default() };
+        ledger.initialize(&genesis).unwrap();
+        let root = genesis.transaction().hash();
+
+        // 같은 nonce의 두 트랜잭션이 서로 다른 가지에 추가된다.
+        let add = |data: u8, parents: Vec<_>| {
+            let mut tx = Transaction::default();
+            tx.data = vec![data];
+            tx.set_parents(&parents);
+            let mut batch = WriteBatch::new();
+            let hash = ledger.put_transaction(&mut batch, &tx, Some(&validator)).unwrap();
+            ledger.get_backend().write(batch).unwrap();
+            hash
+        };
+        let (first, second) = (add(1, vec![root]), add(2, vec![root]));
+        let (lower, higher) = if first < second { (first, second) } else { (second, first) };
+        assert_eq!(ledger.transaction_status(&lower), TxStatus::Pending);
+        assert_eq!(ledger.transaction_status(&higher), TxStatus::Rejected(lower));
+        let evidence = ledger.get_conflicts().get_evidence(&validator, 0).unwrap();
+        let hashes: Vec<_> = evidence.transactions.iter().map(|tx| tx.hash()).collect();
+        assert_eq!(hashes, vec![lower, higher]);
+
+        // 먼저 마일스톤에 포함된 트랜잭션이 이기며 이후에 가지가 합쳐져도 바뀌지 않는다.
+        let authorities = vec![validator];
+        let mut milestone = Milestone::new(1, vec![higher], ledger.state_root(), 1);
+        milestone.sign(&authority);
+        ledger.add_milestone(&milestone, &authorities).unwrap();
+        assert_eq!(ledger.transaction_status(&higher), TxStatus::Final(1));
+        assert_eq!(ledger.transaction_status(&lower), TxStatus::Rejected(higher));
+        let mut merge = Transaction::default();
+        merge.set_parents(&vec![lower, higher]);
+        let merge = ledger.add_transaction(&merge).unwrap();
+        let mut milestone = Milestone::new(2, vec![merge], ledger.state_root(), 2);
+        milestone.sign(&authority);
+        ledger.add_milestone(&milestone, &authorities).unwrap();
+        assert_eq!(ledger.transaction_status(&lower), TxStatus::Rejected(higher));
+        assert_eq!(ledger.transaction_status(&merge), TxStatus::Final(2));
+        assert_eq!(ledger.transaction_status(&Transaction::default().hash()), TxStatus::Unknown);
+
+        // 서명된 트랜잭션은 fee와 value가 있어도 서명한 계정으로 충돌이 기록된다.
+        use ethereum_types::U256;
+        use crate::envelope::{DynamicFeeTransaction, TypedTransaction};
+        use crate::transaction::RawTransaction;
+        let sk = crypto::key::Sk::random();
+        let signer = Address::from(sk.pubkey().address());
+        let mut legacy: TypedTransaction = RawTransaction {
+            nonce: 0, gas_price: U256::from(20), gas: U256::from(21000), recipient: Address::random(),
+            value: U256::from(5), data: vec![1], v: 0, r: vec![], s: vec![],
+        }.into();
+        let mut typed = TypedTransaction::DynamicFee(DynamicFeeTransaction {
+            chain_id: 7, nonce: 0, max_priority_fee_per_gas: U256::from(3), max_fee_per_gas: U256::from(20),
+            gas: U256::from(21000), recipient: Some(Address::random()), value: U256::from(5), data: vec![2], access_list: vec![],
+            y_parity: 0, r: vec![], s: vec![],
+        });
+        let mut hashes = vec![];
+        for signed in vec![&mut legacy, &mut typed] {
+            signed.sign(&sk, 7);
+            let mut tx = signed.to_transaction();
+            tx.set_parents(&vec![merge]);
+            hashes.push(ledger.add_transaction(&tx).unwrap());
+        }
+        assert_eq!(ledger.get_conflicts().sender(&hashes[0]), Some((signer, 0)));
+        assert_eq!(ledger.get_conflicts().sender(&hashes[1]), Some((signer, 0)));
+        let (lower, higher) = if hashes[0] < hashes[1] { (hashes[0], hashes[1]) } else { (hashes[1], hashes[0]) };
+        assert_eq!(ledger.transaction_status(&lower), TxStatus::Pending);
+        assert_eq!(ledger.transaction_status(&higher), TxStatus::Rejected(lower));
+    }
+
+    #[test]
+    fn shared_ledger_handle() {
+        use ethereum_types::{Address, U256};
+        use crate::handle::LedgerHandle;
+        use crate::transaction::RawTransaction;
+        let handle = LedgerHandle::new(Ledger::in_memory());
+        let sender = Address::random();
+        // 여러 thread에서 같은 pool에 트랜잭션을 추가하고 읽는다.
+        let writers: Vec<_> = (0..4usize).map(|nonce| {
+            let handle = handle.clone();
+            std::thread::spawn(move || {
+                let raw_tx = RawTransaction {
+                    nonce, gas_price: U256::zero(), gas: U256::zero(), recipient: Address::zero(),
+                    value: U256::zero(), data: vec![], v: 0, r: vec![], s: vec![],
+                };
+                handle.write().admit_transaction(&sender, raw_tx.into()).unwrap();
+                handle.read().pending_nonce(&sender)
+            })
+        }).collect();
+        for writer in writers.into_iter() { assert!(writer.join().unwrap() >= 1); }
+        let (first, second) = (handle.read(), handle.read());
+        assert_eq!(first.pending_nonce(&sender), 4);
+        assert_eq!(second.get_pool().len(), 4);
+    }
+
+    #[test]
+    fn sender_recovery() {
+        use ethereum_types::{Address, H256, U256};
+        use std::str::FromStr;
+        use crate::transaction::RawTransaction;
+        // EIP-155의 예제 트랜잭션 (chain id 1)
+        let encoded = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let raw_tx: RawTransaction = rlp::decode(encoded.as_slice()).unwrap();
+        assert_eq!(raw_tx.chain_id(), Some(1));
+        assert_eq!(raw_tx.signing_hash(), H256::from_str("daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53").unwrap());
+        let sender = Address::from_str("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap();
+        assert_eq!(raw_tx.try_get_sender(), Some(sender));
+
+        let sk = crypto::key::Sk::new(&[0x46; 32]);
+        let mut unsigned = RawTransaction {
+            nonce: 9, gas_price: U256::from(20_000_000_000u64), gas: U256::from(21000), recipient: Address::from([0x35; 20]),
+            value: U256::from(1_000_000_000_000_000_000u64), data: vec![], v: 0, r: vec![], s: vec![],
+        };
+        assert_eq!(unsigned.try_get_sender(), None);
+        unsigned.sign(&sk, None);
+        assert_eq!(unsigned.chain_id(), None);
+        assert_eq!(unsigned.try_get_sender(), Some(sender));
+        unsigned.sign(&sk, Some(1));
+        assert_eq!(unsigned.try_get_sender(), Some(sender));
+        // 다른 chain id로 바꾸면 다른 sender가 복구된다.
+        unsigned.v += 2;
+        assert_ne!(unsigned.try_get_sender(), Some(sender));
+        // s가 curve order의 절반보다 크면 거부한다.
+        let mut high_s = raw_tx;
+        high_s.s = vec![0xff; 32];
+        assert_eq!(high_s.try_get_sender(), None);
+    }
+
+    #[test]
+    fn typed_transaction_envelope() {
+        use ethereum_types::{Address, H256, U256};
+        use crate::envelope::{AccessListItem, AccessListTransaction, DynamicFeeTransaction, TypedTransaction};
+        use crate::pool::TxPool;
+        let sk = crypto::key::Sk::new(&[0x46; 32]);
+        let sender = Address::from(sk.pubkey().address());
+        let access_list = vec![AccessListItem { address: Address::from([0x35; 20]), storage_keys: vec![H256::from_low_u64_be(1)] }];
+        let mut access_list_tx = TypedTransaction::AccessList(AccessListTransaction {
+            chain_id: 7, nonce: 0, gas_price: U256::from(10), gas: U256::from(21000), recipient: Some(Address::from([0x35; 20])),
+            value: U256::zero(), data: vec![1, 2], access_list: access_list.clone(), y_parity: 0, r: vec![], s: vec![],
+        });
+        let mut dynamic_fee_tx = TypedTransaction::DynamicFee(DynamicFeeTransaction {
+            chain_id: 7, nonce: 1, max_priority_fee_per_gas: U256::from(3), max_fee_per_gas: U256::from(20), gas: U256::from(21000),
+            recipient: Some(Address::from([0x35; 20])), value: U256::zero(), data: vec![3], access_list, y_parity: 0, r: vec![], s: vec![],
+        });
+        access_list_tx.sign(&sk, 7);
+        dynamic_fee_tx.sign(&sk, 7);
+
+        for tx in vec![&access_list_tx, &dynamic_fee_tx] {
+            let bytes = tx.to_bytes();
+            assert_eq!(bytes[0], tx.tx_type());
+            let decoded = TypedTransaction::from_bytes(bytes.as_slice()).unwrap();
+            assert_eq!(decoded.to_bytes(), bytes);
+            assert_eq!(decoded.hash(), tx.hash());
+            assert_eq!(decoded.chain_id(), Some(7));
+            assert_eq!(decoded.try_get_sender(), Some(sender));
+            // 서명이 바뀌면 같은 sender가 복구되지 않는다.
+            let mut tampered = bytes.clone();
+            tampered[bytes.len() - 40] ^= 1;
+            assert_ne!(TypedTransaction::from_bytes(tampered.as_slice()).ok().and_then(|tx| tx.try_get_sender()), Some(sender));
+        }
+        // 컨트랙트 생성 트랜잭션의 to는 빈 문자열이다.
+        let mut creations = vec![
+            TypedTransaction::AccessList(AccessListTransaction {
+                chain_id: 7, nonce: 2, gas_price: U256::from(10), gas: U256::from(53000), recipient: None,
+                value: U256::zero(), data: vec![0x60, 0x00], access_list: vec![], y_parity: 0, r: vec![], s: vec![],
+            }),
+            TypedTransaction::DynamicFee(DynamicFeeTransaction {
+                chain_id: 7, nonce: 3, max_priority_fee_per_gas: U256::from(3), max_fee_per_gas: U256::from(20), gas: U256::from(53000),
+                recipient: None, value: U256::zero(), data: vec![0x60, 0x00], access_list: vec![], y_parity: 0, r: vec![], s: vec![],
+            }),
+        ];
+        for creation in creations.iter_mut() {
+            creation.sign(&sk, 7);
+            let bytes = creation.to_bytes();
+            let decoded = TypedTransaction::from_bytes(bytes.as_slice()).unwrap();
+            let recipient = match &decoded {
+                TypedTransaction::AccessList(tx) => { tx.recipient }
+                TypedTransaction::DynamicFee(tx) => { tx.recipient }
+                TypedTransaction::Legacy(_) => { unreachable!() }
+            };
+            assert_eq!(recipient, None);
+            assert_eq!(decoded.to_bytes(), bytes);
+            assert_eq!(decoded.try_get_sender(), Some(sender));
+            let tx = decoded.to_transaction();
+            assert_eq!((tx.recipient, tx.try_get_sender()), (Address::zero(), Some(sender)));
+        }
+        assert!(TypedTransaction::from_bytes(&[0x03, 0xc0]).is_err());
+        assert!(TypedTransaction::from_bytes(&[]).is_err());
+
+        // legacy 트랜잭션은 rlp list 그대로이다.
+        let legacy_bytes = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let legacy = TypedTransaction::from_bytes(legacy_bytes.as_slice()).unwrap();
+        assert_eq!(legacy.tx_type(), 0);
+        assert_eq!(legacy.to_bytes(), legacy_bytes);
+        assert_eq!(legacy.try_get_sender(), Some(sender));
+
+        // DAG에 저장되는 트랜잭션은 서명된 원본을 함께 가지므로 fee와 value가 있어도 sender를 복구할 수 있다.
+        let mut tx = dynamic_fee_tx.to_transaction();
+        assert_eq!((tx.nonce, tx.data.clone(), tx.recipient), (1, vec![3], Address::from([0x35; 20])));
+        assert!(tx.v <= 1);
+        assert_eq!(tx.envelope, dynamic_fee_tx.to_bytes());
+        assert_eq!(tx.try_get_sender(), Some(sender));
+        let decoded: crate::transaction::Transaction = rlp::decode(&rlp::encode(&tx)).unwrap();
+        assert_eq!((decoded.hash(), decoded.try_get_sender()), (tx.hash(), Some(sender)));
+        assert_eq!(legacy.to_transaction().try_get_sender(), Some(sender));
+        // 원본과 다른 내용의 트랜잭션은 원본의 서명으로 sender가 복구되지 않는다.
+        tx.data = vec![4];
+        assert_eq!(tx.try_get_sender(), None);
+
+        // base fee가 없으므로 dynamic fee 트랜잭션의 우선순위는 max_priority_fee_per_gas이다.
+        assert_eq!(dynamic_fee_tx.gas_price(), U256::from(3));
+        let pool = TxPool::new();
+        let hash = pool.admit(&sender, dynamic_fee_tx.clone(), 1).unwrap();
+        assert_eq!(hash, dynamic_fee_tx.hash());
+        assert_eq!(pool.get(&hash).unwrap().1.to_bytes(), dynamic_fee_tx.to_bytes());
+    }
+
+    #[test]
+    fn device_call() {
+        use ethereum_types::Address;
+        use crate::device::Device;
+        use crate::device_call::{DeviceCallError, DeviceCallTransaction};
+        use crate::receipt::STATUS_SUCCESS;
+        let mut ledger = Ledger::in_memory();
+        let (owner, other) = (crypto::key::Sk::new(&[0x11; 32]), crypto::key::Sk::new(&[0x22; 32]));
+        let device = Device { address: Address::random(), owner: Address::from(owner.pubkey().address()), name: "lamp".to_string() };
+        ledger.get_devices().insert_device(&device).unwrap();
+        let state_root = ledger.state_root();
+        let call = |nonce: u64, sk: &crypto::key::Sk| {
+            let mut tx = DeviceCallTransaction {
+                chain_id: 1, nonce, device: device.address.clone(), command: "switch".to_string(),
+                args: vec![vec![1]], y_parity: 0, r: vec![], s: vec![],
+            };
+            tx.sign(sk);
+            tx
+        };
+        let tx = call(0, &owner);
+        assert_eq!(DeviceCallTransaction::from_bytes(tx.to_bytes().as_slice()).unwrap(), tx);
+        assert_eq!(ledger.admit_device_call(call(0, &other)), Err(DeviceCallError::NotOwner(device.owner.clone())));
+        let mut unknown = call(0, &owner);
+        unknown.device = Address::random();
+        unknown.sign(&owner);
+        assert_eq!(ledger.admit_device_call(unknown.clone()), Err(DeviceCallError::UnknownDevice(unknown.device)));
+        assert_eq!(ledger.admit_device_call(call(1, &owner)), Err(DeviceCallError::Nonce(0)));
+
+        // device call은 컨트랙트 호출의 pool이 아닌 device call pool로 간다.
+        let hash = ledger.admit_device_call(tx.clone()).unwrap();
+        assert_eq!(ledger.admit_device_call(tx.clone()), Err(DeviceCallError::AlreadyKnown(hash)));
+        ledger.admit_device_call(call(1, &owner)).unwrap();
+        assert_eq!((ledger.get_device_pool().len(), ledger.get_pool().len()), (2, 0));
+
+        assert_eq!(ledger.commit_device_call(&call(1, &owner), STATUS_SUCCESS, vec![]), Err(DeviceCallError::Nonce(0)));
+        assert_eq!(ledger.commit_device_call(&tx, STATUS_SUCCESS, vec![0x01]), Ok(hash));
+        let receipt = ledger.get_receipt(&hash).unwrap();
+        assert!(receipt.is_success());
+        assert_eq!((receipt.from, receipt.output, receipt.milestone), (device.owner.clone(), vec![0x01], None));
+        assert_eq!(ledger.get_devices().get_call(&hash), Some(tx.clone()));
+        assert_eq!(ledger.get_devices().next_nonce(&device.address), 1);
+        assert!(!ledger.get_device_pool().contains(&hash));
+        assert_eq!(ledger.get_device_pool().select(10).len(), 1);
+        // DAG와 world state는 바뀌지 않는다.
+        assert!(ledger.get_dag().is_empty());
+        assert_eq!(ledger.state_root(), state_root);
+        assert_eq!(ledger.commit_device_call(&tx, STATUS_SUCCESS, vec![]), Err(DeviceCallError::AlreadyKnown(hash)));
+    }
+
+    #[test]
+    fn batch_transaction() {
+        use ethereum_types::{Address, H256};
+        use crate::batch::{self, BatchEntry, BatchError, BatchTransaction, CallExecutor, Reading, SubReceipt};
+        use crate::dirty_state::DirtyStates;
+        use crate::log::Log;
+        use crate::fsck;
+        use crate::receipt::{STATUS_FAILED, STATUS_SUCCESS};
+        // data가 비어있으면 실패하고, 아니라면 storage의 key 0에 data를 기록한다.
+        struct Store;
+        impl CallExecutor for Store {
+            fn call(&self, _: &Ledger, _: &DirtyStates, _: &Address, recipient: &Address, data: &Vec<u8>) -> (SubReceipt, DirtyStates) {
+                let mut states = DirtyStates::new();
+                if data.is_empty() { return (SubReceipt::new(STATUS_FAILED, vec![], vec![]), states); }
+                states.set_value(recipient, &H256::zero(), &H256::from_low_u64_be(data[0] as u64));
+                (SubReceipt::new(STATUS_SUCCESS, data.clone(), vec![Log::new(recipient, vec![], data.clone())]), states)
+            }
+        }
+        let mut ledger = Ledger::in_memory();
+        let sk = crypto::key::Sk::new(&[0x33; 32]);
+        let sender = Address::from(sk.pubkey().address());
+        let contract = Address::random();
+        let reading = |timestamp: u64| BatchEntry::Reading(Reading { timestamp, sensor: "temp".to_string(), value: vec![timestamp as u8] });
+        let call = |data: Vec<u8>| BatchEntry::Call { recipient: contract.clone(), data };
+        let signed = |nonce: u64, atomic: bool, entries: Vec<BatchEntry>| {
+            let mut tx = BatchTransaction { chain_id: 1, nonce, atomic, entries, y_parity: 0, r: vec![], s: vec![] };
+            tx.sign(&sk);
+            tx
+        };
+
+        // entry마다 실행하면 실패한 entry만 반영되지 않는다.
+        let batch_tx = signed(0, false, vec![reading(1), reading(2), reading(2), call(vec![7]), call(vec![])]);
+        assert_eq!(BatchTransaction::from_bytes(batch_tx.to_bytes().as_slice()).unwrap(), batch_tx);
+        assert_eq!(batch_tx.try_get_sender(), Some(sender));
+        let outcome = batch::execute(&ledger, &batch_tx, &Store).unwrap();
+        let statuses: Vec<u8> = outcome.sub_receipts.iter().map(|receipt| receipt.status).collect();
+        assert_eq!(statuses, vec![STATUS_SUCCESS, STATUS_SUCCESS, STATUS_FAILED, STATUS_SUCCESS, STATUS_FAILED]);
+        assert_eq!((outcome.status, outcome.readings.len(), outcome.logs().len()), (STATUS_SUCCESS, 2, 1));
+        let mut tx = batch_tx.to_transaction();
+        tx.state_hash = outcome.states.hash();
+        assert_eq!(tx.try_get_sender(), Some(sender));
+        ledger.commit_batch(&tx, &outcome).unwrap();
+        assert!(ledger.get_receipt(&tx.hash()).unwrap().is_success());
+        assert_eq!(ledger.get_batches().get_sub_receipts(&tx.hash()).unwrap().len(), 5);
+        let timestamps: Vec<u64> = ledger.get_batches().readings(&sender, "temp").iter().map(|reading| reading.timestamp).collect();
+        assert_eq!(timestamps, vec![1, 2]);
+        assert_eq!(ledger.get_storage_value(&contract, &H256::zero()), H256::from_low_u64_be(7));
+        assert_eq!(ledger.next_nonce(&sender), 1);
+        assert_eq!(batch::execute(&ledger, &batch_tx, &Store).err(), Some(BatchError::Nonce(1)));
+
+        // atomic batch는 한 entry라도 실패하면 아무것도 반영되지 않지만 nonce는 사용된다.
+        let atomic_tx = signed(1, true, vec![reading(3), call(vec![9]), reading(2)]);
+        let outcome = batch::execute(&ledger, &atomic_tx, &Store).unwrap();
+        assert_eq!((outcome.status, outcome.readings.len(), outcome.logs().len()), (STATUS_FAILED, 0, 0));
+        assert!(outcome.states.is_empty());
+        let mut tx = atomic_tx.to_transaction();
+        tx.set_parents(&ledger.get_dag().tips());
+        tx.state_hash = outcome.states.hash();
+        ledger.commit_batch(&tx, &outcome).unwrap();
+        assert!(!ledger.get_receipt(&tx.hash()).unwrap().is_success());
+        assert!(ledger.get_batches().get_sub_receipts(&tx.hash()).unwrap()[1].is_success());
+        assert_eq!(ledger.get_batches().last_reading(&sender, "temp").unwrap().timestamp, 2);
+        assert_eq!(ledger.get_storage_value(&contract, &H256::zero()), H256::from_low_u64_be(7));
+        assert_eq!(ledger.next_nonce(&sender), 2);
+        assert_eq!(batch::execute(&ledger, &signed(2, true, vec![]), &Store).err(), Some(BatchError::Empty));
+        // fsck는 data에 담긴 batch의 서명으로 sender를 복구한다.
+        let options = fsck::CheckOptions { authorities: vec![], quarantine: false };
+        assert!(fsck::check(&ledger, &options, None).is_clean());
     }
 }
@@ -0,0 +1,40 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+
+/// 컨트랙트 실행 중 발생한 이벤트(LOG0 ~ LOG4, wasm `log` host function)
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+impl Log {
+    pub fn new(address: &Address, topics: Vec<H256>, data: Vec<u8>) -> Self {
+        Log { address: address.clone(), topics, data }
+    }
+}
+
+impl Clone for Log {
+    fn clone(&self) -> Self {
+        Log { address: self.address.clone(), topics: self.topics.clone(), data: self.data.clone() }
+    }
+}
+
+impl Encodable for Log {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3);
+        s.append(&self.address);
+        s.append_list(&self.topics);
+        s.append(&self.data);
+    }
+}
+
+impl Decodable for Log {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Log {
+            address: rlp.val_at(0)?,
+            topics: rlp.list_at(1)?,
+            data: rlp.val_at(2)?,
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use ethereum_types::{Address, Bloom, BloomInput, H256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+use crate::log::Log;
+use crate::receipt::{Receipt, RECEIPT_COLUMN};
+
+/// height(u64 big endian) -> 마일스톤이 확정한 모든 log의 bloom (2048 bits)
+/// log가 없는 마일스톤은 기록하지 않는다.
+pub const LOG_BLOOM_COLUMN: &str = "log_bloom";
+/// position -> tx hash
+pub const LOG_COLUMN: &str = "log";
+/// address || position -> tx hash
+pub const LOG_ADDRESS_COLUMN: &str = "log_address";
+/// topic 위치(u8) || topic || position -> tx hash
+pub const LOG_TOPIC_COLUMN: &str = "log_topic";
+
+/// 마일스톤 height(u64) || 마일스톤 안에서 트랜잭션의 순서(u32) || receipt 안에서 log의 순서(u32), 모두 big endian
+const POSITION_LEN: usize = 16;
+
+/// 확정된 log와 그 위치
+pub struct IndexedLog {
+    pub log: Log,
+    pub tx_hash: H256,
+    pub milestone: u64,
+    pub tx_index: u32,
+    pub log_index: u32,
+}
+
+/// eth_getLogs의 조건. 비어있는 조건은 모든 값과 일치한다.
+/// addresses는 그 중 하나와, topics[i]는 i번째 topic이 그 중 하나와 일치해야 한다.
+pub struct LogFilter {
+    pub from: u64,                  // 검색할 첫 마일스톤 height
+    pub to: u64,                    // 검색할 마지막 마일스톤 height (포함)
+    pub addresses: Vec<Address>,
+    pub topics: Vec<Vec<H256>>,
+}
+
+impl LogFilter {
+    pub fn new(from: u64, to: u64) -> Self {
+        LogFilter { from, to, addresses: vec![], topics: vec![] }
+    }
+
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) { return false; }
+        for (idx, topics) in self.topics.iter().enumerate() {
+            if topics.is_empty() { continue; }
+            match log.topics.get(idx) {
+                Some(topic) => { if !topics.contains(topic) { return false; } }
+                None => { return false; }
+            }
+        }
+        true
+    }
+
+    /// 마일스톤의 bloom에 조건을 만족하는 log가 있을 수 있다면 true
+    pub fn matches_bloom(&self, bloom: &Bloom) -> bool {
+        let contains = |value: &[u8]| bloom.contains_input(BloomInput::Raw(value));
+        if !self.addresses.is_empty() && !self.addresses.iter().any(|address| contains(address.as_bytes())) {
+            return false;
+        }
+        self.topics.iter().all(|topics| topics.is_empty() || topics.iter().any(|topic| contains(topic.as_bytes())))
+    }
+}
+
+fn position(height: u64, tx_index: u32, log_index: u32) -> Vec<u8> {
+    [&height.to_be_bytes()[..], &tx_index.to_be_bytes(), &log_index.to_be_bytes()].concat()
+}
+
+fn topic_prefix(idx: usize, topic: &H256) -> Vec<u8> {
+    [&[idx as u8][..], topic.as_bytes()].concat()
+}
+
+/// 확정된 log들의 마일스톤별 bloom과 address, topic 색인
+/// 마일스톤이 추가될 때 그 마일스톤이 확정한 트랜잭션들의 receipt로부터 만들어진다.
+pub struct LogIndexManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl LogIndexManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        LogIndexManager { backend }
+    }
+
+    /// 마일스톤이 확정한 트랜잭션들의 log를 batch에 색인한다.
+    /// receipts는 마일스톤 안에서의 트랜잭션 순서이며 receipt가 없는 트랜잭션은 None이다.
+    pub fn put_logs(&self, batch: &mut WriteBatch, height: u64, receipts: &Vec<Option<Receipt>>) {
+        let mut bloom = Bloom::zero();
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let receipt = match receipt {
+                Some(receipt) => { receipt }
+                None => { continue; }
+            };
+            bloom.accrue_bloom(&receipt.logs_bloom);
+            for (log_index, log) in receipt.logs.iter().enumerate() {
+                let position = position(height, tx_index as u32, log_index as u32);
+                let tx_hash = receipt.tx_hash.as_bytes();
+                batch.put(LOG_COLUMN, &position, tx_hash);
+                batch.put(LOG_ADDRESS_COLUMN, &[log.address.as_bytes(), &position].concat(), tx_hash);
+                for (idx, topic) in log.topics.iter().enumerate().take(4) {
+                    batch.put(LOG_TOPIC_COLUMN, &[topic_prefix(idx, topic), position.clone()].concat(), tx_hash);
+                }
+            }
+        }
+        if !bloom.is_empty() { batch.put(LOG_BLOOM_COLUMN, &height.to_be_bytes(), bloom.as_bytes()); }
+    }
+
+    /// 마일스톤이 확정한 log들의 bloom. log가 없다면 비어있는 bloom이다.
+    pub fn get_bloom(&self, height: u64) -> Bloom {
+        match self.backend.get(LOG_BLOOM_COLUMN, &height.to_be_bytes()) {
+            Some(value) if value.len() == 256 => { Bloom::from_slice(value.as_slice()) }
+            _ => { Bloom::zero() }
+        }
+    }
+
+    /// 조건을 만족하는 log들을 위치 순서로 반환한다.
+    /// bloom으로 log가 있을 수 있는 마일스톤을 고른 뒤 가장 좁은 색인을 마일스톤 단위로 읽는다.
+    pub fn query(&self, filter: &LogFilter) -> Vec<IndexedLog> {
+        if filter.from > filter.to { return vec![]; }
+        let end = filter.to.checked_add(1).map(|to| to.to_be_bytes());
+        let heights: Vec<u64> = self.backend
+            .range(LOG_BLOOM_COLUMN, &filter.from.to_be_bytes(), end.as_ref().map(|end| &end[..]))
+            .into_iter()
+            .filter(|(_, bloom)| bloom.len() == 256 && filter.matches_bloom(&Bloom::from_slice(bloom.as_slice())))
+            .filter_map(|(key, _)| crate::milestone::to_height(key.as_slice()))
+            .collect();
+
+        // address 조건이 있으면 address 색인을, 없으면 처음으로 조건이 있는 topic 위치의 색인을 읽는다.
+        let topic = filter.topics.iter().position(|topics| !topics.is_empty());
+        let (column, prefixes): (&str, Vec<Vec<u8>>) = if !filter.addresses.is_empty() {
+            (LOG_ADDRESS_COLUMN, filter.addresses.iter().map(|address| address.as_bytes().to_vec()).collect())
+        } else if let Some(idx) = topic {
+            (LOG_TOPIC_COLUMN, filter.topics[idx].iter().map(|topic| topic_prefix(idx, topic)).collect())
+        } else {
+            (LOG_COLUMN, vec![vec![]])
+        };
+        let mut positions = BTreeMap::<Vec<u8>, H256>::new();
+        for height in heights.iter() {
+            for prefix in prefixes.iter() {
+                let prefix = [prefix.as_slice(), &height.to_be_bytes()].concat();
+                for (key, tx_hash) in self.backend.scan(column, &prefix).into_iter() {
+                    if key.len() < POSITION_LEN || tx_hash.len() != 32 { continue; }
+                    positions.insert(key[key.len() - POSITION_LEN..].to_vec(), H256::from_slice(tx_hash.as_slice()));
+                }
+            }
+        }
+
+        let mut receipts = HashMap::<H256, Option<Receipt>>::new();
+        let mut result = vec![];
+        for (position, tx_hash) in positions.into_iter() {
+            let receipt = receipts.entry(tx_hash.clone()).or_insert_with(|| {
+                let value = self.backend.get(RECEIPT_COLUMN, tx_hash.as_bytes())?;
+                rlp::decode::<Receipt>(value.as_slice()).ok()
+            });
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&position[12..16]);
+            let log_index = u32::from_be_bytes(bytes);
+            let log = match receipt.as_ref().and_then(|receipt| receipt.logs.get(log_index as usize)) {
+                Some(log) => { log }
+                None => { continue; }
+            };
+            if !filter.matches(log) { continue; }
+            bytes.copy_from_slice(&position[8..12]);
+            result.push(IndexedLog {
+                log: log.clone(),
+                tx_hash,
+                milestone: crate::milestone::to_height(&position[..8]).unwrap(),
+                tx_index: u32::from_be_bytes(bytes),
+                log_index,
+            });
+        }
+        result
+    }
+}
@@ -0,0 +1,290 @@
+use std::path::{Path, PathBuf};
+use ethereum_types::{Address, H256, U256};
+use rusqlite::{Connection, OptionalExtension, Transaction as SqlTransaction};
+use rusqlite::types::ValueRef;
+use crate::account::{AccountNode, ACCOUNT_COLUMN, STORAGE_COLUMN};
+use crate::dag::{DAG_PARENT_COLUMN, DAG_CHILD_COLUMN, DAG_TIP_COLUMN};
+use crate::milestone::{Milestone, MILESTONE_COLUMN, MILESTONE_HASH_COLUMN, TX_MILESTONE_COLUMN};
+use crate::receipt::{Receipt, RECEIPT_COLUMN};
+use crate::transaction::{Transaction, Hash160Vector, TX_COLUMN};
+use crate::trie::{TRIE_NODE_COLUMN, STATE_ROOT_COLUMN};
+
+/// 데이터베이스를 열지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum MigrationError {
+    NewerSchema(u32),       // 이 버전이 알지 못하는 (더 새로운) schema version
+    Failed(u32),            // 실패한 migration의 version. 해당 migration의 변경은 반영되지 않는다.
+    Database,
+}
+
+/// 데이터베이스 schema를 한 단계 올리는 작업
+/// 각 migration은 하나의 SQLite 트랜잭션 안에서 실행되며 성공하면 schema_version에 기록된다.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    run: fn(&SqlTransaction, &MigrationContext) -> rusqlite::Result<()>,
+    cleanup: Option<fn(&MigrationContext)>,     // commit된 뒤 데이터베이스 밖의 정리 작업
+}
+
+/// migration이 데이터베이스 밖에서 참조하는 값
+/// * `directory` - 데이터베이스 파일이 있는 디렉토리. 메모리 데이터베이스라면 None이다.
+pub struct MigrationContext {
+    pub directory: Option<PathBuf>,
+}
+
+/// version 순서대로 적용되는 migration들. 새로운 migration은 항상 마지막에 추가한다.
+pub const MIGRATIONS: [Migration; 2] = [
+    Migration { version: 1, description: "create key/value table", run: create_kv_table, cleanup: None },
+    Migration {
+        version: 2,
+        description: "import legacy tables and contract storage files",
+        run: import_legacy,
+        cleanup: Some(remove_legacy_files),
+    },
+];
+
+/// 이 버전의 schema version
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// 데이터베이스에 마지막으로 적용된 migration의 version. 아무것도 적용되지 않았다면 0이다.
+pub fn schema_version(conn: &Connection) -> Result<u32, MigrationError> {
+    let query = "CREATE TABLE IF NOT EXISTS schema_version(\
+        version INTEGER PRIMARY KEY,\
+        description TEXT,\
+        applied_at INTEGER)";
+    if conn.execute(query, []).is_err() { return Err(MigrationError::Database); }
+    let version: Option<i64> = conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+        .map_err(|_| MigrationError::Database)?;
+    Ok(version.unwrap_or(0) as u32)
+}
+
+/// 아직 적용되지 않은 migration을 순서대로 적용하고 최종 schema version을 반환한다.
+pub fn migrate(conn: &mut Connection, context: &MigrationContext) -> Result<u32, MigrationError> {
+    let current = schema_version(conn)?;
+    if current > latest_version() { return Err(MigrationError::NewerSchema(current)); }
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        let tx = conn.transaction().map_err(|_| MigrationError::Database)?;
+        let result = (migration.run)(&tx, context).and_then(|_| {
+            let applied_at = common::timeutil::timestamp_now().unwrap_or(0) as i64;
+            tx.execute("INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+                       rusqlite::params![migration.version, migration.description, applied_at])
+        });
+        // 실패하면 tx가 drop되면서 rollback된다.
+        if result.is_err() { return Err(MigrationError::Failed(migration.version)); }
+        if tx.commit().is_err() { return Err(MigrationError::Failed(migration.version)); }
+        if let Some(cleanup) = migration.cleanup { cleanup(context); }
+    }
+    Ok(latest_version())
+}
+
+fn create_kv_table(tx: &SqlTransaction, _: &MigrationContext) -> rusqlite::Result<()> {
+    tx.execute("CREATE TABLE IF NOT EXISTS kv(\
+        col TEXT,\
+        key BLOB,\
+        value BLOB,\
+        PRIMARY KEY (col, key)) WITHOUT ROWID", []).map(|_| ())
+}
+
+fn put(tx: &SqlTransaction, column: &str, key: &[u8], value: &[u8]) -> rusqlite::Result<()> {
+    let mut stmt = tx.prepare_cached("INSERT OR REPLACE INTO kv (col, key, value) VALUES (?, ?, ?)")?;
+    stmt.execute(rusqlite::params![column, key, value]).map(|_| ())
+}
+
+fn table_exists(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?", [table], |_| Ok(()))
+        .optional()
+        .map(|row| row.is_some())
+}
+
+/// 테이블의 모든 row를 읽어 f로 옮긴 뒤 테이블을 삭제한다. 테이블이 없다면 아무것도 하지 않는다.
+fn import_table(tx: &SqlTransaction, table: &str,
+                f: &dyn Fn(&rusqlite::Row) -> rusqlite::Result<()>) -> rusqlite::Result<()> {
+    if !table_exists(tx, table)? { return Ok(()); }
+    {
+        let mut stmt = tx.prepare(format!("SELECT * FROM {}", table).as_str())?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? { f(row)?; }
+    }
+    tx.execute(format!("DROP TABLE {}", table).as_str(), []).map(|_| ())
+}
+
+fn to_h256(value: &[u8]) -> H256 {
+    H256::from(common::vecutil::copy_to_bytes32(&value.to_vec()))
+}
+
+fn to_error(_: rlp::DecoderError) -> rusqlite::Error {
+    rusqlite::Error::InvalidQuery
+}
+
+/// 정수 column의 값. 이전 schema는 정수를 32 bytes blob으로 저장했으므로 blob은 decode로 읽는다.
+fn to_integer(row: &rusqlite::Row, idx: usize, decode: fn(&H256) -> u64) -> rusqlite::Result<u64> {
+    match row.get_ref(idx)? {
+        ValueRef::Null => { Ok(0) }
+        ValueRef::Integer(value) => { Ok(value as u64) }
+        ValueRef::Blob(value) => { Ok(decode(&to_h256(value))) }
+        _ => { Err(rusqlite::Error::InvalidQuery) }
+    }
+}
+
+/// account의 nonce처럼 H256에 big endian으로 저장된 값
+fn from_word(value: &H256) -> u64 {
+    U256::from_big_endian(value.as_bytes()).low_u64()
+}
+
+/// `H256::from_low_u64_le`로 저장된 값
+fn from_low_u64_le(value: &H256) -> u64 {
+    value.to_low_u64_le()
+}
+
+/// 컨트랙트마다 `<address>.db` 파일에 저장되던 storage 파일들
+fn legacy_storage_files(context: &MigrationContext) -> Vec<(Address, PathBuf)> {
+    let directory = match &context.directory {
+        Some(directory) => { directory }
+        None => { return vec![]; }
+    };
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => { entries }
+        Err(_) => { return vec![]; }
+    };
+    let mut files: Vec<(Address, PathBuf)> = entries.filter_map(|entry| {
+        let path = entry.ok()?.path();
+        let name = path.file_name()?.to_str()?;
+        let address = hex::decode(name.strip_suffix(".db")?).ok()?;
+        if address.len() != 20 { return None; }
+        Some((Address::from_slice(address.as_slice()), path))
+    }).collect();
+    files.sort();
+    files
+}
+
+fn remove_legacy_files(context: &MigrationContext) {
+    for (_, path) in legacy_storage_files(context).iter() { let _ = std::fs::remove_file(path); }
+}
+
+/// SQL 테이블마다 저장되던 이전 schema의 데이터와 컨트랙트별 storage 파일을 kv 테이블로 옮긴다.
+/// storage 파일은 migration이 commit된 뒤에 삭제된다.
+fn import_legacy(tx: &SqlTransaction, context: &MigrationContext) -> rusqlite::Result<()> {
+    import_table(tx, "account", &|row| {
+        let key: Vec<u8> = row.get(0)?;
+        let nonce = to_integer(row, 1, from_word)?;
+        let storage_root: Option<Vec<u8>> = row.get(2)?;
+        let codehash: Option<Vec<u8>> = row.get(3)?;
+        let node = AccountNode {
+            key: to_h256(key.as_slice()),
+            nonce,
+            storage_root: storage_root.map_or(H256::zero(), |root| to_h256(root.as_slice())),
+            codehash: codehash.unwrap_or_default(),
+        };
+        put(tx, ACCOUNT_COLUMN, node.key.as_bytes(), &rlp::encode(&node))
+    })?;
+    import_table(tx, "tx", &|row| {
+        // 처음 schema의 tx 테이블은 정수를 H256::from_low_u64_le로 저장했고 committer, validators가 비어있을 수 있다.
+        let recipient: Vec<u8> = row.get(1)?;
+        let state_hash: Vec<u8> = row.get(7)?;
+        let parent_hash: Option<Vec<u8>> = row.get(8)?;
+        let committer: Option<Vec<u8>> = row.get(9)?;
+        let validators: Option<Vec<u8>> = row.get(10)?;
+        let transaction = Transaction {
+            nonce: to_integer(row, 0, from_low_u64_le)? as usize,
+            recipient: Address::from_slice(recipient.as_slice()),
+            data: row.get(2)?,
+            v: to_integer(row, 3, from_low_u64_le)? as usize,
+            r: row.get(4)?,
+            s: row.get(5)?,
+            timestamp: to_integer(row, 6, from_low_u64_le)?,
+            state_hash: to_h256(state_hash.as_slice()),
+            parent_hash: parent_hash.unwrap_or_default(),
+            committer: committer.filter(|committer| committer.len() == 20)
+                .map_or(Address::zero(), |committer| Address::from_slice(committer.as_slice())),
+            validators: Hash160Vector::from(validators.unwrap_or_default()),
+            envelope: vec![],
+        };
+        // hash column은 이후 schema에서 추가되었으며 없다면 다시 계산한다.
+        let hash = match row.column_index("hash") {
+            Ok(idx) => { to_h256(row.get::<_, Vec<u8>>(idx)?.as_slice()) }
+            Err(_) => { transaction.hash() }
+        };
+        put(tx, TX_COLUMN, hash.as_bytes(), &rlp::encode(&transaction))
+    })?;
+    import_table(tx, "dag_edge", &|row| {
+        let child: Vec<u8> = row.get(0)?;
+        let parent: Vec<u8> = row.get(1)?;
+        put(tx, DAG_PARENT_COLUMN, &[child.as_slice(), parent.as_slice()].concat(), &[])?;
+        put(tx, DAG_CHILD_COLUMN, &[parent.as_slice(), child.as_slice()].concat(), &[])
+    })?;
+    import_table(tx, "dag_tip", &|row| {
+        let hash: Vec<u8> = row.get(0)?;
+        put(tx, DAG_TIP_COLUMN, hash.as_slice(), &[])
+    })?;
+    import_table(tx, "trie_node", &|row| {
+        let hash: Vec<u8> = row.get(0)?;
+        let node: Vec<u8> = row.get(1)?;
+        put(tx, TRIE_NODE_COLUMN, hash.as_slice(), node.as_slice())
+    })?;
+    import_table(tx, "state_root", &|row| {
+        let id: i64 = row.get(0)?;
+        let root: Vec<u8> = row.get(1)?;
+        put(tx, STATE_ROOT_COLUMN, &(id as u64).to_be_bytes(), root.as_slice())
+    })?;
+    import_table(tx, "milestone", &|row| {
+        let height: i64 = row.get(0)?;
+        let hash: Vec<u8> = row.get(1)?;
+        let tips: Vec<u8> = row.get(2)?;
+        let state_root: Vec<u8> = row.get(3)?;
+        let timestamp: i64 = row.get(4)?;
+        let milestone = Milestone {
+            height: height as u64,
+            tips: tips.chunks(32).map(|chunk| H256::from_slice(chunk)).collect(),
+            state_root: to_h256(state_root.as_slice()),
+            timestamp: timestamp as u64,
+            signature: row.get(5)?,
+        };
+        let height = milestone.height.to_be_bytes();
+        put(tx, MILESTONE_COLUMN, &height, &rlp::encode(&milestone))?;
+        put(tx, MILESTONE_HASH_COLUMN, hash.as_slice(), &height)
+    })?;
+    import_table(tx, "receipt", &|row| {
+        let tx_hash: Vec<u8> = row.get(0)?;
+        let status: i64 = row.get(1)?;
+        let from: Vec<u8> = row.get(2)?;
+        let gas_used: i64 = row.get(4)?;
+        let contract_address: Option<Vec<u8>> = row.get(5)?;
+        let logs: Vec<u8> = row.get(6)?;
+        let milestone: Option<i64> = row.get(8)?;
+        let logs = rlp::Rlp::new(logs.as_slice()).as_list().map_err(to_error)?;
+        let receipt = Receipt::new(&to_h256(tx_hash.as_slice()), status as u8, &Address::from_slice(from.as_slice()),
+                                   row.get(3)?, gas_used as u64,
+                                   contract_address.map(|address| Address::from_slice(address.as_slice())), logs);
+        put(tx, RECEIPT_COLUMN, receipt.tx_hash.as_bytes(), &rlp::encode(&receipt))?;
+        match milestone {
+            Some(height) => { put(tx, TX_MILESTONE_COLUMN, receipt.tx_hash.as_bytes(), &(height as u64).to_be_bytes()) }
+            None => { Ok(()) }
+        }
+    })?;
+
+    for (address, path) in legacy_storage_files(context).iter() {
+        let storage = Connection::open(path)?;
+        if !table_exists(&storage, "storage")? { continue; }
+        let mut stmt = storage.prepare("SELECT key, value FROM storage")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let key: Vec<u8> = row.get(0)?;
+            let value: Vec<u8> = row.get(1)?;
+            let value = to_h256(value.as_slice());
+            if value.is_zero() { continue; }
+            let key = [address.as_bytes(), to_h256(key.as_slice()).as_bytes()].concat();
+            put(tx, STORAGE_COLUMN, key.as_slice(), value.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// 데이터베이스 파일이 있는 디렉토리
+pub fn database_directory(path: &str) -> PathBuf {
+    match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => { parent.to_path_buf() }
+        _ => { PathBuf::from(".") }
+    }
+}
@@ -0,0 +1,176 @@
+use ethereum_types::{Address, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+
+/// 마일스톤을 추가하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum MilestoneError {
+    InvalidSignature,
+    Unauthorized(Address),      // authority가 아닌 서명자
+    InvalidHeight(u64),         // 기대한 height
+    InvalidTimestamp,           // 이전 마일스톤보다 이른 timestamp
+    NoTips,
+    UnknownTip(H256),           // DAG에 없는 트랜잭션
+    Database,
+}
+
+/// DAG의 tip들을 참조하는 authority 서명된 checkpoint
+/// 마일스톤이 참조하는 트랜잭션과 그 조상들은 확정(final)된 것으로 본다.
+/// Ethereum 도구에는 height를 블록 번호로 하는 블록처럼 보여진다.
+pub struct Milestone {
+    pub height: u64,
+    pub tips: Vec<H256>,
+    pub state_root: H256,
+    pub timestamp: u64,
+    pub signature: Vec<u8>,     // r(32) || s(32) || recovery id(1)
+}
+
+impl Default for Milestone {
+    fn default() -> Self {
+        Milestone {
+            height: 0,
+            tips: vec![],
+            state_root: H256::zero(),
+            timestamp: 0,
+            signature: vec![],
+        }
+    }
+}
+
+impl Milestone {
+    pub fn new(height: u64, tips: Vec<H256>, state_root: H256, timestamp: u64) -> Self {
+        Milestone { height, tips, state_root, timestamp, signature: vec![] }
+    }
+
+    fn append_unsigned(&self, s: &mut RlpStream) {
+        s.append(&self.height);
+        s.append_list(&self.tips);
+        s.append(&self.state_root);
+        s.append(&self.timestamp);
+    }
+
+    /// 서명의 대상이 되는 hash (signature를 제외한 값들의 hash)
+    pub fn signing_hash(&self) -> H256 {
+        let mut s = RlpStream::new_list(4);
+        self.append_unsigned(&mut s);
+        H256::from(crypto::hash::keccak256(s.out().as_ref()))
+    }
+
+    /// 서명을 포함한 마일스톤의 hash
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(rlp::encode(self).as_ref()))
+    }
+
+    pub fn sign(&mut self, sk: &crypto::key::Sk) {
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.signature = signature.to_vec();
+        self.signature.push(rec_id as u8);
+    }
+
+    /// 서명한 authority의 주소. 서명이 올바르지 않다면 None이다.
+    pub fn signer(&self) -> Option<Address> {
+        if self.signature.len() != 65 { return None; }
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(&self.signature[..64]);
+        let rec_id = self.signature[64] as i32;
+        match crypto::secp256k1::try_recover_from_sig(rec_id, &signature, &self.signing_hash().0) {
+            Ok(pk) => { Some(Address::from(pk.address())) }
+            Err(_) => { None }
+        }
+    }
+}
+
+impl Encodable for Milestone {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5);
+        self.append_unsigned(s);
+        s.append(&self.signature);
+    }
+}
+
+impl Decodable for Milestone {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Milestone {
+            height: rlp.val_at(0)?,
+            tips: rlp.list_at(1)?,
+            state_root: rlp.val_at(2)?,
+            timestamp: rlp.val_at(3)?,
+            signature: rlp.val_at(4)?,
+        })
+    }
+}
+
+/// height(u64 big endian) -> rlp(Milestone)
+pub const MILESTONE_COLUMN: &str = "milestone";
+/// milestone hash -> height
+pub const MILESTONE_HASH_COLUMN: &str = "milestone_hash";
+/// tx hash -> 트랜잭션을 확정한 마일스톤의 height
+pub const TX_MILESTONE_COLUMN: &str = "tx_milestone";
+/// height || tx index(u32 big endian) -> 마일스톤이 확정한 순서대로의 tx hash
+pub const MILESTONE_TX_COLUMN: &str = "milestone_tx";
+
+pub(crate) fn to_height(value: &[u8]) -> Option<u64> {
+    if value.len() != 8 { return None; }
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(value);
+    Some(u64::from_be_bytes(bytes))
+}
+
+pub struct MilestoneTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl MilestoneTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        MilestoneTableManager { backend }
+    }
+
+    /// 마일스톤과 그 마일스톤이 확정한 트랜잭션들을 batch에 기록한다.
+    pub fn put_milestone(&self, batch: &mut WriteBatch, milestone: &Milestone, finalized: &Vec<H256>) {
+        let height = milestone.height.to_be_bytes();
+        batch.put(MILESTONE_COLUMN, &height, &rlp::encode(milestone));
+        batch.put(MILESTONE_HASH_COLUMN, milestone.hash().as_bytes(), &height);
+        for (idx, hash) in finalized.iter().enumerate() {
+            batch.put(TX_MILESTONE_COLUMN, hash.as_bytes(), &height);
+            batch.put(MILESTONE_TX_COLUMN, &[&height[..], &(idx as u32).to_be_bytes()].concat(), hash.as_bytes());
+        }
+    }
+
+    pub fn insert_milestone(&self, milestone: &Milestone) -> Result<(), ()> {
+        if self.backend.exists(MILESTONE_COLUMN, &milestone.height.to_be_bytes()) { return Err(()); }
+        let mut batch = WriteBatch::new();
+        self.put_milestone(&mut batch, milestone, &vec![]);
+        self.backend.write(batch)
+    }
+
+    pub fn get_milestone(&self, height: u64) -> Option<Milestone> {
+        let value = self.backend.get(MILESTONE_COLUMN, &height.to_be_bytes())?;
+        rlp::decode(value.as_slice()).ok()
+    }
+
+    pub fn get_milestone_by_hash(&self, hash: &H256) -> Option<Milestone> {
+        let height = self.backend.get(MILESTONE_HASH_COLUMN, hash.as_bytes())?;
+        self.get_milestone(to_height(height.as_slice())?)
+    }
+
+    /// 가장 최근(height가 가장 큰) 마일스톤
+    pub fn latest_milestone(&self) -> Option<Milestone> {
+        let (_, value) = self.backend.last(MILESTONE_COLUMN, &[])?;
+        rlp::decode(value.as_slice()).ok()
+    }
+
+    /// 마일스톤이 확정한 트랜잭션들이며 확정 순서(log의 tx index 순서)이다.
+    pub fn finalized(&self, height: u64) -> Vec<H256> {
+        self.backend.scan(MILESTONE_TX_COLUMN, &height.to_be_bytes()).into_iter()
+            .filter(|(_, value)| value.len() == 32)
+            .map(|(_, value)| H256::from_slice(value.as_slice()))
+            .collect()
+    }
+
+    /// 트랜잭션을 확정한 마일스톤의 height. 아직 확정되지 않았다면 None이다.
+    pub fn finalized_at(&self, tx_hash: &H256) -> Option<u64> {
+        let height = self.backend.get(TX_MILESTONE_COLUMN, tx_hash.as_bytes())?;
+        to_height(height.as_slice())
+    }
+}
@@ -1,34 +1,267 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::sync::Mutex;
+use ethereum_types::{Address, H256, U256};
+use crate::envelope::TypedTransaction;
 use crate::transaction::RawTransaction;
 
+/// 트랜잭션을 pool에 추가하지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum PoolError {
+    Duplicate(u64),     // 이미 world state에 반영된 nonce (replay). 기대한 다음 nonce를 갖는다.
+    Gap(u64),           // 다음 nonce보다 max_nonce_gap 이상 큰 nonce. 기대한 다음 nonce를 갖는다.
+    AlreadyKnown(H256), // 같은 hash의 트랜잭션이 이미 pool에 있음
+    Underpriced,        // 같은 nonce를 교체하기에 gas price가 부족함
+    Full,               // pool이 가득 찼고 새 트랜잭션보다 우선순위가 낮은 트랜잭션이 없음
+}
+
+/// TxPool의 크기와 교체 정책
+/// * `capacity` - pool 전체에 보관할 수 있는 트랜잭션 수
+/// * `max_per_sender` - 한 sender가 보관할 수 있는 트랜잭션 수
+/// * `max_nonce_gap` - 다음 nonce보다 이만큼 이상 큰 nonce는 future 큐에도 넣지 않는다.
+/// * `price_bump` - 같은 nonce를 교체하기 위해 필요한 gas price 증가율 (%)
+pub struct PoolConfig {
+    pub capacity: usize,
+    pub max_per_sender: usize,
+    pub max_nonce_gap: u64,
+    pub price_bump: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig { capacity: 4096, max_per_sender: 64, max_nonce_gap: 16, price_bump: 10 }
+    }
+}
+
+struct PoolEntry {
+    hash: H256,
+    tx: TypedTransaction,
+    arrival: u64,   // pool에 들어온 순서
+}
+
+/// sender 하나의 트랜잭션들
+/// committed부터 nonce가 이어지는 트랜잭션은 pending(실행 가능), 그 이후는 future이다.
+struct SenderQueue {
+    committed: u64,
+    txs: BTreeMap<u64, PoolEntry>,
+}
+
+impl SenderQueue {
+    fn pending_nonce(&self) -> u64 {
+        let mut nonce = self.committed;
+        while self.txs.contains_key(&nonce) { nonce += 1; }
+        nonce
+    }
+
+    fn pending_len(&self) -> usize { (self.pending_nonce() - self.committed) as usize }
+}
+
+/// 실행할 트랜잭션을 고를 때 사용하는 우선순위. gas price가 높을수록, 같다면 먼저 들어올수록 높다.
+#[derive(Eq, PartialEq)]
+struct Priority {
+    gas_price: U256,
+    arrival: u64,
+    sender: Address,
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.gas_price.cmp(&other.gas_price)
+            .then_with(|| other.arrival.cmp(&self.arrival))
+            .then_with(|| self.sender.cmp(&other.sender))
+    }
+}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+struct PoolState {
+    by_hash: HashMap<H256, (Address, u64)>,
+    queues: HashMap<Address, SenderQueue>,
+    sequence: u64,
+}
+
 /// 현재 노드가 Leader일 경우, TxPool을 통해 전송받은 트랜잭션을 커밋하기 위해 사용한다.
 /// 현재 노드가 Follower일 경우, 커밋된 트랜잭션을 확인하기 위해 사용한다.
+/// 여러 연결에서 동시에 트랜잭션을 추가할 수 있도록 내부 상태는 Mutex로 보호된다.
 pub struct TxPool {
-    value: Vec<RawTransaction>,
+    config: PoolConfig,
+    state: Mutex<PoolState>,
+}
+
+/// pool에서 사용하는 legacy 트랜잭션의 hash이며 TypedTransaction::hash와 같다.
+pub fn tx_hash(raw_tx: &RawTransaction) -> H256 {
+    H256::from(crypto::hash::keccak256(rlp::encode(raw_tx).as_ref()))
 }
 
 impl TxPool {
     pub fn new() -> Self {
-        TxPool { value: vec![] }
+        TxPool::with_config(PoolConfig::default())
+    }
+
+    pub fn with_config(config: PoolConfig) -> Self {
+        let state = PoolState { by_hash: HashMap::new(), queues: HashMap::new(), sequence: 0 };
+        TxPool { config, state: Mutex::new(state) }
+    }
+
+    pub fn config(&self) -> &PoolConfig { &self.config }
+
+    pub fn len(&self) -> usize { self.state.lock().unwrap().by_hash.len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub fn contains(&self, hash: &H256) -> bool { self.state.lock().unwrap().by_hash.contains_key(hash) }
+
+    pub fn get(&self, hash: &H256) -> Option<(Address, TypedTransaction)> {
+        let state = self.state.lock().unwrap();
+        let (sender, nonce) = state.by_hash.get(hash)?;
+        let entry = state.queues.get(sender)?.txs.get(nonce)?;
+        Some((sender.clone(), entry.tx.clone()))
     }
 
-    pub fn push(&mut self, raw_tx: RawTransaction) {
-        self.value.push(raw_tx);
+    /// 실행 가능한 트랜잭션 수와 nonce가 비어 대기중인 트랜잭션 수
+    pub fn counts(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        let pending: usize = state.queues.values().map(|queue| queue.pending_len()).sum();
+        (pending, state.by_hash.len() - pending)
     }
 
-    pub fn remove(&mut self, raw_tx: RawTransaction) {
-        let mut no_error = false;
-        let mut idx = 0;
-        for element in self.value.iter() {
-            if element.recipient == raw_tx.recipient
-                && element.nonce == raw_tx.nonce
-                && element.data == raw_tx.data {
-                no_error = true;
-                break
+    /// pool의 트랜잭션까지 포함한 sender의 다음 nonce
+    /// * `committed` - world state에 기록된 sender의 nonce
+    pub fn pending_nonce(&self, sender: &Address, committed: u64) -> u64 {
+        let state = self.state.lock().unwrap();
+        match state.queues.get(sender) {
+            None => { committed }
+            Some(queue) => {
+                let mut nonce = std::cmp::max(committed, queue.committed);
+                while queue.txs.contains_key(&nonce) { nonce += 1; }
+                nonce
             }
-            idx += 1;
         }
+    }
+
+    /// 트랜잭션을 sender의 큐에 추가하고 hash를 반환한다.
+    /// 이미 반영된 nonce(replay)와 너무 멀리 떨어진 nonce는 거부되며, 같은 nonce는 gas price가
+    /// price_bump% 이상 높을 때만 교체된다. pool이 가득 찼다면 우선순위가 가장 낮은 트랜잭션을 내보낸다.
+    pub fn admit(&self, sender: &Address, tx: TypedTransaction, committed: u64) -> Result<H256, PoolError> {
+        let hash = tx.hash();
+        let nonce = tx.nonce();
+        let gas_price = tx.gas_price();
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        TxPool::prune_queue(state, sender, committed);
+        if state.by_hash.contains_key(&hash) { return Err(PoolError::AlreadyKnown(hash)); }
+
+        let (pending_nonce, replaced, sender_len) = match state.queues.get(sender) {
+            None => { (committed, None, 0) }
+            Some(queue) => { (queue.pending_nonce(), queue.txs.get(&nonce), queue.txs.len()) }
+        };
+        if nonce < committed { return Err(PoolError::Duplicate(pending_nonce)); }
+        if nonce >= pending_nonce.saturating_add(self.config.max_nonce_gap) { return Err(PoolError::Gap(pending_nonce)); }
+        match replaced {
+            Some(old) => {
+                let old_price = old.tx.gas_price();
+                let min_price = old_price.saturating_add(old_price.saturating_mul(self.config.price_bump.into()) / 100);
+                if gas_price < min_price || gas_price == old_price {
+                    return Err(PoolError::Underpriced);
+                }
+                let old_hash = old.hash.clone();
+                state.by_hash.remove(&old_hash);
+            }
+            None => {
+                if sender_len >= self.config.max_per_sender { return Err(PoolError::Full); }
+                if state.by_hash.len() >= self.config.capacity { TxPool::evict(state, &gas_price)?; }
+            }
+        }
+
+        state.sequence += 1;
+        let entry = PoolEntry { hash: hash.clone(), tx, arrival: state.sequence };
+        let queue = state.queues.entry(sender.clone())
+            .or_insert_with(|| SenderQueue { committed, txs: BTreeMap::new() });
+        queue.txs.insert(nonce, entry);
+        state.by_hash.insert(hash.clone(), (sender.clone(), nonce));
+        Ok(hash)
+    }
+
+    /// 각 sender의 마지막 트랜잭션 중 gas price가 가장 낮고, 같다면 가장 오래된 것을 제거한다.
+    /// 마지막 트랜잭션만 제거하므로 남은 트랜잭션의 nonce는 비지 않으며, future 트랜잭션이 먼저 제거된다.
+    fn evict(state: &mut PoolState, gas_price: &U256) -> Result<(), PoolError> {
+        let victim = state.queues.iter()
+            .filter_map(|(sender, queue)| {
+                let (nonce, entry) = queue.txs.iter().next_back()?;
+                let pending = *nonce < queue.pending_nonce();
+                Some(((pending, entry.tx.gas_price(), entry.arrival), sender.clone(), *nonce))
+            })
+            .min_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        let ((pending, victim_price, _), sender, nonce) = match victim {
+            Some(victim) => { victim }
+            None => { return Err(PoolError::Full); }
+        };
+        if pending && victim_price > *gas_price { return Err(PoolError::Full); }
+        TxPool::remove_entry(state, &sender, nonce);
+        Ok(())
+    }
 
-        if !no_error { return }
-        self.value.remove(idx);
+    fn remove_entry(state: &mut PoolState, sender: &Address, nonce: u64) -> Option<TypedTransaction> {
+        let queue = state.queues.get_mut(sender)?;
+        let entry = queue.txs.remove(&nonce)?;
+        if queue.txs.is_empty() { state.queues.remove(sender); }
+        state.by_hash.remove(&entry.hash);
+        Some(entry.tx)
     }
-}
\ No newline at end of file
+
+    fn prune_queue(state: &mut PoolState, sender: &Address, committed: u64) {
+        let stale: Vec<u64> = match state.queues.get_mut(sender) {
+            None => { return; }
+            Some(queue) => {
+                queue.committed = std::cmp::max(queue.committed, committed);
+                queue.txs.range(..queue.committed).map(|(nonce, _)| *nonce).collect()
+            }
+        };
+        for nonce in stale.iter() { TxPool::remove_entry(state, sender, *nonce); }
+    }
+
+    pub fn remove(&self, hash: &H256) -> Option<TypedTransaction> {
+        let mut state = self.state.lock().unwrap();
+        let (sender, nonce) = state.by_hash.get(hash).cloned()?;
+        TxPool::remove_entry(&mut state, &sender, nonce)
+    }
+
+    /// world state에 반영되어 더 이상 실행할 수 없는 sender의 트랜잭션을 제거한다.
+    pub fn prune(&self, sender: &Address, committed: u64) {
+        let mut state = self.state.lock().unwrap();
+        TxPool::prune_queue(&mut state, sender, committed);
+    }
+
+    /// sealing engine이 실행할 트랜잭션을 최대 limit개 고른다.
+    /// 각 sender의 pending 트랜잭션은 nonce 순서를 지키며, sender 사이에서는 gas price가 높은 것이 먼저 온다.
+    pub fn select(&self, limit: usize) -> Vec<(Address, TypedTransaction)> {
+        let state = self.state.lock().unwrap();
+        let mut heads = BinaryHeap::new();
+        for (sender, queue) in state.queues.iter() {
+            if let Some(entry) = queue.txs.get(&queue.committed) {
+                let priority = Priority {
+                    gas_price: entry.tx.gas_price(), arrival: entry.arrival, sender: sender.clone()
+                };
+                heads.push((priority, queue.committed));
+            }
+        }
+        let mut selected = vec![];
+        while selected.len() < limit {
+            let (priority, nonce) = match heads.pop() {
+                Some(head) => { head }
+                None => { break; }
+            };
+            let queue = &state.queues[&priority.sender];
+            selected.push((priority.sender.clone(), queue.txs[&nonce].tx.clone()));
+            if let Some(next) = queue.txs.get(&(nonce + 1)) {
+                let next_priority = Priority {
+                    gas_price: next.tx.gas_price(), arrival: next.arrival, sender: priority.sender.clone()
+                };
+                heads.push((next_priority, nonce + 1));
+            }
+        }
+        selected
+    }
+}
@@ -0,0 +1,129 @@
+use ethereum_types::{Address, Bloom, BloomInput, H256};
+use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+use crate::milestone::{TX_MILESTONE_COLUMN, to_height};
+use crate::log::Log;
+
+pub const STATUS_FAILED: u8 = 0;
+pub const STATUS_SUCCESS: u8 = 1;
+
+/// 트랜잭션 실행 결과
+/// milestone은 트랜잭션을 확정한 마일스톤의 height이며 아직 확정되지 않았다면 None이다.
+pub struct Receipt {
+    pub tx_hash: H256,
+    pub status: u8,
+    pub from: Address,
+    pub output: Vec<u8>,
+    pub gas_used: u64,
+    pub contract_address: Option<Address>,     // 컨트랙트 생성 트랜잭션일 때 생성된 주소
+    pub logs: Vec<Log>,
+    pub logs_bloom: Bloom,
+    pub milestone: Option<u64>,
+}
+
+impl Receipt {
+    pub fn new(tx_hash: &H256, status: u8, from: &Address, output: Vec<u8>, gas_used: u64,
+               contract_address: Option<Address>, logs: Vec<Log>) -> Self {
+        Receipt {
+            tx_hash: tx_hash.clone(),
+            status,
+            from: from.clone(),
+            output,
+            gas_used,
+            contract_address,
+            logs_bloom: Receipt::bloom(&logs),
+            logs,
+            milestone: None,
+        }
+    }
+
+    /// log를 발생시킨 주소와 topic들로 만든 bloom filter
+    pub fn bloom(logs: &Vec<Log>) -> Bloom {
+        let mut bloom = Bloom::zero();
+        for log in logs.iter() {
+            bloom.accrue(BloomInput::Raw(log.address.as_bytes()));
+            for topic in log.topics.iter() { bloom.accrue(BloomInput::Raw(topic.as_bytes())); }
+        }
+        bloom
+    }
+
+    pub fn is_success(&self) -> bool { self.status == STATUS_SUCCESS }
+}
+
+/// 저장되는 값이며 milestone은 확정될 때 바뀌므로 포함하지 않는다.
+impl Encodable for Receipt {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(8);
+        s.append(&self.tx_hash);
+        s.append(&self.status);
+        s.append(&self.from);
+        s.append(&self.output);
+        s.append(&self.gas_used);
+        match &self.contract_address {
+            Some(address) => { s.append(address); }
+            None => { s.append_empty_data(); }
+        }
+        s.append_list(&self.logs);
+        s.append(&self.logs_bloom);
+    }
+}
+
+impl Decodable for Receipt {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let contract_address = rlp.at(5)?;
+        Ok(Receipt {
+            tx_hash: rlp.val_at(0)?,
+            status: rlp.val_at(1)?,
+            from: rlp.val_at(2)?,
+            output: rlp.val_at(3)?,
+            gas_used: rlp.val_at(4)?,
+            contract_address: match contract_address.is_empty() {
+                true => { None }
+                false => { Some(contract_address.as_val()?) }
+            },
+            logs: rlp.list_at(6)?,
+            logs_bloom: rlp.val_at(7)?,
+            milestone: None,
+        })
+    }
+}
+
+/// tx hash -> rlp(Receipt)
+pub const RECEIPT_COLUMN: &str = "receipt";
+
+/// 트랜잭션 receipt들. 확정된 마일스톤은 MilestoneTableManager의 tx_milestone에서 읽어온다.
+pub struct ReceiptTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl ReceiptTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        ReceiptTableManager { backend }
+    }
+
+    /// receipt를 batch에 기록한다.
+    /// state root와 같은 batch에 기록하기 위해 Ledger::commit이 사용한다.
+    pub fn put_receipt(&self, batch: &mut WriteBatch, receipt: &Receipt) {
+        batch.put(RECEIPT_COLUMN, receipt.tx_hash.as_bytes(), &rlp::encode(receipt));
+    }
+
+    pub fn exist(&self, tx_hash: &H256) -> bool {
+        self.backend.exists(RECEIPT_COLUMN, tx_hash.as_bytes())
+    }
+
+    pub fn insert_receipt(&self, receipt: &Receipt) -> Result<(), ()> {
+        if self.exist(&receipt.tx_hash) { return Err(()); }
+        let mut batch = WriteBatch::new();
+        self.put_receipt(&mut batch, receipt);
+        self.backend.write(batch)
+    }
+
+    pub fn get_receipt(&self, tx_hash: &H256) -> Option<Receipt> {
+        let value = self.backend.get(RECEIPT_COLUMN, tx_hash.as_bytes())?;
+        let mut receipt: Receipt = rlp::decode(value.as_slice()).ok()?;
+        let height = self.backend.get(TX_MILESTONE_COLUMN, tx_hash.as_bytes());
+        receipt.milestone = height.and_then(|height| to_height(height.as_slice()));
+        Some(receipt)
+    }
+}
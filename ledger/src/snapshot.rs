@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::sync::Arc;
+use ethereum_types::{Address, H256, U256};
+use rlp::{Encodable, RlpStream, DecoderError, Rlp};
+use crate::account::{AccountNode, AccountStorage, ACCOUNT_COLUMN, STORAGE_COLUMN};
+use crate::archive::{BlockTag, ACCOUNT_HISTORY_COLUMN, STORAGE_HISTORY_COLUMN};
+use crate::backend::{Backend, WriteBatch};
+use crate::ledger::Ledger;
+use crate::milestone::Milestone;
+use crate::transaction::Transaction;
+use crate::trie::{PatriciaTrie, PendingTrieStorage, SecureTrie, EMPTY_ROOT};
+
+/// snapshot 파일의 시작을 나타내는 값
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"BIIOTSNP";
+/// 현재 snapshot 형식의 version. 형식이 바뀌면 올린다.
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// 별도로 지정하지 않았을 때 snapshot에 포함하는 최근 트랜잭션의 수
+pub const DEFAULT_DAG_SUFFIX: usize = 1024;
+
+const HEADER_LENGTH: usize = 8 + 4;
+const CHECKSUM_LENGTH: usize = 32;
+const SIGNATURE_LENGTH: usize = 65;
+
+/// snapshot을 만들거나 가져오지 못한 이유
+#[derive(Debug, Eq, PartialEq)]
+pub enum SnapshotError {
+    InvalidFormat,
+    UnsupportedVersion(u32),
+    Checksum,
+    InvalidSignature,
+    Unauthorized(Address),      // authority가 아닌 서명자 (snapshot 또는 마일스톤)
+    StateRoot(H256),            // snapshot의 account와 storage로 계산한 state root
+    NoMilestone,                // 마일스톤이 없는 ledger는 snapshot을 만들 수 없다.
+    NotArchived(u64),           // 마일스톤 이후 상태가 바뀌었지만 그 height의 상태가 archive되지 않음
+    NotEmpty,                   // 비어있지 않은 ledger에는 snapshot을 가져올 수 없다.
+    Io,
+    Database,
+}
+
+/// 새로운 노드가 genesis부터 모든 트랜잭션을 다시 실행하지 않고 시작하기 위한 ledger의 상태
+/// 파일은 magic(8) || version(4) || rlp(body) || checksum(32) || signature(65)이며
+/// checksum은 앞의 모든 bytes의 keccak256, signature는 checksum에 대한 authority의 서명이다.
+/// * `accounts` - world state의 모든 account (code 포함). key 오름차순이다.
+/// * `storages` - 컨트랙트별 storage. address와 key 오름차순이다.
+/// * `transactions` - DAG의 마지막 트랜잭션들이며 부모가 자식보다 먼저 온다.
+pub struct Snapshot {
+    pub version: u32,
+    pub state_root: H256,
+    pub milestone: Milestone,
+    pub accounts: Vec<AccountNode>,
+    pub storages: Vec<(Address, Vec<(H256, H256)>)>,
+    pub transactions: Vec<Transaction>,
+    pub signature: Vec<u8>,
+}
+
+struct SnapshotAccount<'a>(&'a AccountNode);
+
+impl<'a> Encodable for SnapshotAccount<'a> {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.0.key);
+        s.append(self.0);
+    }
+}
+
+fn decode_account(rlp: &Rlp) -> Result<AccountNode, DecoderError> {
+    let mut node: AccountNode = rlp.val_at(1)?;
+    node.key = rlp.val_at(0)?;
+    Ok(node)
+}
+
+struct SnapshotStorage<'a>(&'a Address, &'a Vec<(H256, H256)>);
+
+impl<'a> Encodable for SnapshotStorage<'a> {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(self.0);
+        s.begin_list(self.1.len());
+        for (key, value) in self.1.iter() {
+            s.begin_list(2);
+            s.append(key);
+            s.append(value);
+        }
+    }
+}
+
+fn decode_storage(rlp: &Rlp) -> Result<(Address, Vec<(H256, H256)>), DecoderError> {
+    let mut values = vec![];
+    for item in rlp.at(1)?.iter() { values.push((item.val_at(0)?, item.val_at(1)?)); }
+    Ok((rlp.val_at(0)?, values))
+}
+
+impl Snapshot {
+    fn body(&self) -> Vec<u8> {
+        let mut s = RlpStream::new_list(5);
+        s.append(&self.state_root);
+        s.append(&self.milestone);
+        s.begin_list(self.accounts.len());
+        for account in self.accounts.iter() { s.append(&SnapshotAccount(account)); }
+        s.begin_list(self.storages.len());
+        for (address, values) in self.storages.iter() { s.append(&SnapshotStorage(address, values)); }
+        s.append_list(&self.transactions);
+        s.out().to_vec()
+    }
+
+    fn decode_body(version: u32, body: &[u8], signature: Vec<u8>) -> Result<Snapshot, DecoderError> {
+        let rlp = Rlp::new(body);
+        let mut accounts = vec![];
+        for item in rlp.at(2)?.iter() { accounts.push(decode_account(&item)?); }
+        let mut storages = vec![];
+        for item in rlp.at(3)?.iter() { storages.push(decode_storage(&item)?); }
+        Ok(Snapshot {
+            version,
+            state_root: rlp.val_at(0)?,
+            milestone: rlp.val_at(1)?,
+            accounts,
+            storages,
+            transactions: rlp.list_at(4)?,
+            signature,
+        })
+    }
+
+    /// magic, version, body의 hash이며 서명의 대상이다.
+    fn checksum(head: &[u8]) -> H256 {
+        H256::from(crypto::hash::keccak256(head))
+    }
+
+    /// 서명한 authority의 주소를 포함한 파일의 bytes
+    pub fn encode(&self, sk: &crypto::key::Sk) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_be_bytes());
+        bytes.extend_from_slice(self.body().as_slice());
+        let checksum = Snapshot::checksum(bytes.as_slice());
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &checksum.0);
+        bytes.extend_from_slice(checksum.as_bytes());
+        bytes.extend_from_slice(&signature);
+        bytes.push(rec_id as u8);
+        bytes
+    }
+
+    /// 파일을 읽고 checksum과 서명을 확인한다. 서명한 authority의 주소를 함께 반환한다.
+    pub fn decode(bytes: &[u8]) -> Result<(Snapshot, Address), SnapshotError> {
+        if bytes.len() < HEADER_LENGTH + CHECKSUM_LENGTH + SIGNATURE_LENGTH || &bytes[..8] != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidFormat);
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&bytes[8..HEADER_LENGTH]);
+        let version = u32::from_be_bytes(version);
+        if version != SNAPSHOT_VERSION { return Err(SnapshotError::UnsupportedVersion(version)); }
+        let body_end = bytes.len() - CHECKSUM_LENGTH - SIGNATURE_LENGTH;
+        let checksum = Snapshot::checksum(&bytes[..body_end]);
+        if checksum.as_bytes() != &bytes[body_end..body_end + CHECKSUM_LENGTH] { return Err(SnapshotError::Checksum); }
+
+        let signature = bytes[body_end + CHECKSUM_LENGTH..].to_vec();
+        let mut compact = [0u8; 64];
+        compact.copy_from_slice(&signature[..64]);
+        let signer = match crypto::secp256k1::try_recover_from_sig(signature[64] as i32, &compact, &checksum.0) {
+            Ok(pk) => { Address::from(pk.address()) }
+            Err(_) => { return Err(SnapshotError::InvalidSignature); }
+        };
+        let snapshot = Snapshot::decode_body(version, &bytes[HEADER_LENGTH..body_end], signature)
+            .map_err(|_| SnapshotError::InvalidFormat)?;
+        Ok((snapshot, signer))
+    }
+}
+
+/// 현재 값이 있거나 변경 이력이 있는 key들. 과거 시점에 값이 있었던 key는 모두 포함된다.
+fn known_keys(backend: &Arc<dyn Backend>, column: &str, history: &str) -> BTreeSet<Vec<u8>> {
+    let mut keys: BTreeSet<Vec<u8>> = backend.scan(column, &[]).into_iter().map(|(key, _)| key).collect();
+    for (key, _) in backend.scan(history, &[]).into_iter() {
+        if key.len() > 8 { keys.insert(key[..key.len() - 8].to_vec()); }
+    }
+    keys
+}
+
+/// 마지막 마일스톤 시점의 상태와 최근 트랜잭션 최대 max_transactions개로 snapshot을 만들고 sk로 서명한다.
+/// 마일스톤 이후 상태가 바뀌었다면 archive에서 마일스톤 시점의 값을 읽는다.
+pub fn export(ledger: &Ledger, sk: &crypto::key::Sk, max_transactions: usize) -> Result<Vec<u8>, SnapshotError> {
+    let milestone = ledger.latest_milestone().ok_or(SnapshotError::NoMilestone)?;
+    let backend = ledger.get_backend();
+    let state = match ledger.state_root() == milestone.state_root {
+        true => { backend.clone() }
+        false => {
+            let state = ledger.state_at(&BlockTag::Number(milestone.height))
+                .map_err(|_| SnapshotError::NotArchived(milestone.height))?;
+            state.get_backend()
+        }
+    };
+    let mut accounts = vec![];
+    for key in known_keys(&backend, ACCOUNT_COLUMN, ACCOUNT_HISTORY_COLUMN).into_iter() {
+        let value = match state.get(ACCOUNT_COLUMN, &key) {
+            Some(value) => { value }
+            None => { continue; }
+        };
+        let mut node: AccountNode = rlp::decode(value.as_slice()).map_err(|_| SnapshotError::Database)?;
+        node.key = H256::from_slice(key.as_slice());
+        accounts.push(node);
+    }
+    let mut storages = BTreeMap::<Address, Vec<(H256, H256)>>::new();
+    for key in known_keys(&backend, STORAGE_COLUMN, STORAGE_HISTORY_COLUMN).into_iter() {
+        let value = match state.get(STORAGE_COLUMN, &key) {
+            Some(value) => { value }
+            None => { continue; }
+        };
+        if key.len() != 52 || value.len() != 32 { return Err(SnapshotError::Database); }
+        storages.entry(Address::from_slice(&key[..20])).or_insert_with(Vec::new)
+            .push((H256::from_slice(&key[20..]), H256::from_slice(value.as_slice())));
+    }
+    let order = ledger.get_dag().topological_order();
+    let mut transactions = vec![];
+    for hash in order[order.len().saturating_sub(max_transactions)..].iter() {
+        transactions.push(ledger.get_transactions().get_transaction(hash).ok_or(SnapshotError::Database)?);
+    }
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        state_root: milestone.state_root.clone(),
+        milestone,
+        accounts,
+        storages: storages.into_iter().collect(),
+        transactions,
+        signature: vec![],
+    };
+    Ok(snapshot.encode(sk))
+}
+
+/// snapshot을 검증하고 비어있는 ledger에 기록한다.
+/// snapshot과 마일스톤은 authorities 중 하나가 서명해야 하며, account와 storage로 다시 계산한
+/// state root가 snapshot과 마일스톤의 state root와 같아야 한다. 모든 데이터는 하나의 batch로 기록된다.
+/// snapshot에 포함되지 않은 오래된 트랜잭션은 DAG에서 부모로만 참조된다.
+pub fn import(ledger: &mut Ledger, bytes: &[u8], authorities: &Vec<Address>) -> Result<Snapshot, SnapshotError> {
+    let (snapshot, signer) = Snapshot::decode(bytes)?;
+    if !authorities.contains(&signer) { return Err(SnapshotError::Unauthorized(signer)); }
+    match snapshot.milestone.signer() {
+        Some(signer) if authorities.contains(&signer) => {}
+        Some(signer) => { return Err(SnapshotError::Unauthorized(signer)); }
+        None => { return Err(SnapshotError::InvalidSignature); }
+    }
+    if ledger.state_root() != EMPTY_ROOT || !ledger.get_dag().is_empty() || ledger.latest_milestone().is_some() {
+        return Err(SnapshotError::NotEmpty);
+    }
+
+    let mut batch = WriteBatch::new();
+    let nodes = PendingTrieStorage::new(ledger.get_tries());
+    let mut storage_roots = BTreeMap::<H256, H256>::new();
+    for (address, values) in snapshot.storages.iter() {
+        let mut trie = SecureTrie::from_root(&nodes, &EMPTY_ROOT);
+        let storage = ledger.account_state(address);
+        for (key, value) in values.iter() {
+            let encoded = rlp::encode(&U256::from_big_endian(value.as_bytes())).to_vec();
+            trie.insert(key.as_bytes(), encoded).map_err(|_| SnapshotError::InvalidFormat)?;
+            storage.put_storage_value(&mut batch, &AccountStorage { key: key.clone(), value: value.clone() });
+        }
+        storage_roots.insert(H256::from(crypto::hash::keccak256(address.as_bytes())), trie.commit());
+    }
+    let mut world = PatriciaTrie::new(&nodes);
+    for node in snapshot.accounts.iter() {
+        let storage_root = storage_roots.remove(&node.key).unwrap_or(EMPTY_ROOT);
+        let expected = if node.storage_root.is_zero() { EMPTY_ROOT } else { node.storage_root.clone() };
+        if storage_root != expected { return Err(SnapshotError::StateRoot(storage_root)); }
+        world.insert(node.key.as_bytes(), node.trie_value()).map_err(|_| SnapshotError::InvalidFormat)?;
+        ledger.get_accounts().put_account(&mut batch, node);
+    }
+    // account가 없는 storage
+    if !storage_roots.is_empty() { return Err(SnapshotError::InvalidFormat); }
+    let root = world.commit();
+    if root != snapshot.state_root || root != snapshot.milestone.state_root {
+        return Err(SnapshotError::StateRoot(root));
+    }
+    drop(world);
+    nodes.into_batch(&mut batch);
+    ledger.get_tries().put_state_root(&mut batch, &root);
+
+    for tx in snapshot.transactions.iter() {
+        ledger.get_transactions().put_transaction(&mut batch, tx);
+        ledger.get_dag().put_edges(&mut batch, &tx.hash(), &tx.parents());
+    }
+    let finalized = finalized_suffix(&snapshot);
+    ledger.get_milestones().put_milestone(&mut batch, &snapshot.milestone, &finalized);
+    if ledger.get_backend().write(batch).is_err() { return Err(SnapshotError::Database); }
+    Ok(snapshot)
+}
+
+/// snapshot의 트랜잭션 중 마일스톤의 tip이거나 그 조상인 트랜잭션들
+fn finalized_suffix(snapshot: &Snapshot) -> Vec<H256> {
+    let parents: BTreeMap<H256, Vec<H256>> = snapshot.transactions.iter()
+        .map(|tx| (tx.hash(), tx.parents()))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<H256> = snapshot.milestone.tips.iter().cloned().collect();
+    while let Some(hash) = queue.pop_front() {
+        if !parents.contains_key(&hash) || !visited.insert(hash.clone()) { continue; }
+        queue.extend(parents[&hash].iter().cloned());
+    }
+    let mut finalized: Vec<H256> = visited.into_iter().collect();
+    finalized.sort();
+    finalized
+}
+
+pub fn export_file(ledger: &Ledger, path: &str, sk: &crypto::key::Sk, max_transactions: usize) -> Result<(), SnapshotError> {
+    let bytes = export(ledger, sk, max_transactions)?;
+    std::fs::write(path, bytes).map_err(|_| SnapshotError::Io)
+}
+
+pub fn import_file(ledger: &mut Ledger, path: &str, authorities: &Vec<Address>) -> Result<Snapshot, SnapshotError> {
+    let bytes = std::fs::read(path).map_err(|_| SnapshotError::Io)?;
+    import(ledger, bytes.as_slice(), authorities)
+}
@@ -1,8 +1,10 @@
 use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
 use ethereum_types::{U256, Address, H256, H160};
-use crate::table::{Table, Container};
-use rusqlite::{Connection, Statement, Row, Error};
-use crate::account::WorldStateTableManager;
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+
+/// hash -> rlp(Transaction)
+pub const TX_COLUMN: &str = "tx";
 
 pub struct Hash160Vector(Vec<H160>);
 
@@ -10,20 +12,22 @@ impl Hash160Vector {
     pub fn new() -> Self { Hash160Vector { 0: vec![] }}
 }
 
+impl Hash160Vector {
+    /// 주소들을 이어붙인 bytes이며 tx 테이블의 validators 컬럼에 저장된다.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut result = vec![];
+        for address in self.0.iter() { result.extend_from_slice(address.as_bytes()); }
+        return result;
+    }
+}
+
 impl From<Vec<u8>> for Hash160Vector {
     fn from(value: Vec<u8>) -> Self {
         if value.len() % 20 != 0 { return Hash160Vector { 0: vec![] }; }
         let mut addresses: Vec<Address> = vec![];
-        let mut temp_vec: Vec<u8> = vec![];
-        for idx in 0..value.len() {
-            if value.len() % 20 == 0 {
-                addresses.push(Address::from_slice(temp_vec.as_slice()));
-                temp_vec.clear();
-            }
-            temp_vec.push(value.get(idx).unwrap().clone());
+        for chunk in value.chunks(20) {
+            addresses.push(Address::from_slice(chunk));
         }
-
-        addresses.push(Address::from_slice(temp_vec.as_slice()));
         return Hash160Vector { 0: addresses };
     }
 }
@@ -65,6 +69,7 @@ impl Decodable for Hash256Vector {
     }
 }
 
+#[derive(Clone)]
 pub struct RawTransaction {
     pub nonce: usize,
     pub gas_price: U256,
@@ -77,22 +82,90 @@ pub struct RawTransaction {
     pub s: Vec<u8>,
 }
 
+/// secp256k1 curve order의 절반. 이보다 큰 s는 EIP-2에 의해 유효하지 않다.
+const SECP256K1_HALF_N: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// rlp로 받은 big endian 정수(앞의 0이 제거될 수 있다)를 32 bytes로 맞춘다.
+fn to_word(value: &[u8]) -> Option<[u8; 32]> {
+    let value = &value[value.iter().position(|byte| *byte != 0).unwrap_or(value.len())..];
+    if value.len() > 32 { return None; }
+    let mut word = [0u8; 32];
+    word[32 - value.len()..].copy_from_slice(value);
+    Some(word)
+}
+
+/// 서명(recovery id, r, s)과 서명된 hash로 서명한 계정의 주소를 복구한다.
+/// s가 curve order의 절반보다 큰 서명(EIP-2)은 올바르지 않은 서명이다.
+pub(crate) fn recover_sender(rec_id: i32, r: &[u8], s: &[u8], hash: &H256) -> Option<Address> {
+    let (r, s) = (to_word(r)?, to_word(s)?);
+    if s > SECP256K1_HALF_N { return None; }
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r);
+    signature[32..].copy_from_slice(&s);
+    let public_key = crypto::secp256k1::try_recover_from_sig(rec_id, &signature, &hash.0).ok()?;
+    Some(Address::from(public_key.address()))
+}
+
 impl RawTransaction {
     pub fn get_sender(&self) -> Address {
-        let rlp_tx = rlp::encode(self).to_vec();
-        let hmsg = crypto::hash::keccak256(rlp_tx.as_slice());
-        let mut r = [0u8;32];
-        let mut s = [0u8;32];
-        for idx in 0..self.r.len() {
-            r[idx] = self.r.get(idx).unwrap().clone();
-            s[idx] = self.s.get(idx).unwrap().clone();
+        self.try_get_sender().expect("invalid transaction signature")
+    }
+
+    /// EIP-155 서명이라면 v에 포함된 chain id. v가 27, 28인 legacy 서명은 None이다.
+    pub fn chain_id(&self) -> Option<u64> {
+        match self.v >= 35 {
+            true => { Some(((self.v - 35) / 2) as u64) }
+            false => { None }
+        }
+    }
+
+    /// v에서 얻은 recovery id (0 또는 1). legacy는 27 + id, EIP-155는 chain_id * 2 + 35 + id이다.
+    fn recovery_id(&self) -> Option<i32> {
+        match self.v {
+            27 | 28 => { Some((self.v - 27) as i32) }
+            v if v >= 35 => { Some(((v - 35) % 2) as i32) }
+            _ => { None }
         }
-        let public_key = crypto::secp256k1::recover_from_vrs(&hmsg, self.v as i32, r, s).to_vec();
-        let mut raw_address = [0u8;20];
-        for idx in 0..public_key.len() {
-            raw_address[idx] = public_key.get(12 + idx).unwrap().clone();
+    }
+
+    /// 서명의 대상이 되는 hash
+    /// legacy는 keccak256(rlp([nonce, gasPrice, gas, to, value, data]))이며
+    /// EIP-155는 keccak256(rlp([nonce, gasPrice, gas, to, value, data, chainId, 0, 0]))이다.
+    pub fn signing_hash(&self) -> H256 {
+        let chain_id = self.chain_id();
+        let mut s = RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+        s.append(&self.nonce);
+        s.append(&self.gas_price);
+        s.append(&self.gas);
+        s.append(&self.recipient);
+        s.append(&self.value);
+        s.append(&self.data);
+        if let Some(chain_id) = chain_id {
+            s.append(&chain_id);
+            s.append(&0u8);
+            s.append(&0u8);
         }
-        Address::from_slice(&raw_address)
+        H256::from(crypto::hash::keccak256(s.out().as_ref()))
+    }
+
+    /// 트랜잭션에 서명한다. chain_id가 주어지면 EIP-155 서명이다.
+    pub fn sign(&mut self, sk: &crypto::key::Sk, chain_id: Option<u64>) {
+        self.v = match chain_id {
+            Some(chain_id) => { chain_id as usize * 2 + 35 }
+            None => { 27 }
+        };
+        let (rec_id, signature) = crypto::secp256k1::sign_recoverable(sk, &self.signing_hash().0);
+        self.v += rec_id as usize;
+        self.r = signature[..32].to_vec();
+        self.s = signature[32..].to_vec();
+    }
+
+    /// get_sender와 같지만 서명이 올바르지 않다면 panic 대신 None을 반환한다.
+    pub fn try_get_sender(&self) -> Option<Address> {
+        recover_sender(self.recovery_id()?, &self.r, &self.s, &self.signing_hash())
     }
 }
 
@@ -140,6 +213,7 @@ pub struct Transaction {
     pub parent_hash: Vec<u8>,
     pub committer: Address,
     pub validators: Hash160Vector,
+    pub envelope: Vec<u8>,          // 서명된 원본 트랜잭션 (legacy rlp 또는 type || rlp(payload)). 없다면 비어있다.
 }
 
 impl Transaction {
@@ -153,8 +227,64 @@ impl Transaction {
         common::vecutil::copy_between(&ptx.s, &mut tx.s);
         tx.committer = Address::zero();
         tx.validators = Hash160Vector::new();
+        tx.envelope = rlp::encode(ptx).to_vec();
         return tx
     }
+
+    /// 서명한 계정
+    /// 서명된 원본이 있다면 원본의 서명에서 복구하며, 원본의 nonce, recipient, data, 서명이 트랜잭션과 다르다면 None이다.
+    /// batch 트랜잭션은 data가 서명된 원본이므로 data에서 복구한다.
+    /// 원본이 없다면 fee와 value가 0인 legacy 트랜잭션으로 보고 복구한다.
+    pub fn try_get_sender(&self) -> Option<Address> {
+        if self.envelope.is_empty() {
+            if let Ok(batch) = crate::batch::BatchTransaction::from_bytes(self.data.as_slice()) {
+                if self.matches(&batch.to_transaction()) { return batch.try_get_sender(); }
+            }
+            return self.to_raw_transaction().try_get_sender();
+        }
+        let signed = crate::envelope::TypedTransaction::from_bytes(self.envelope.as_slice()).ok()?;
+        if !self.matches(&signed.to_transaction()) { return None; }
+        signed.try_get_sender()
+    }
+
+    /// 서명된 원본에서 만든 트랜잭션과 nonce, recipient, data, 서명이 같은지 확인한다.
+    fn matches(&self, expected: &Transaction) -> bool {
+        (expected.nonce, &expected.recipient, &expected.data, expected.v, &expected.r, &expected.s)
+            == (self.nonce, &self.recipient, &self.data, self.v, &self.r, &self.s)
+    }
+
+    /// 서명을 검증하기 위한 RawTransaction. 저장되지 않는 gas_price, gas, value는 0이다.
+    pub fn to_raw_transaction(&self) -> RawTransaction {
+        RawTransaction {
+            nonce: self.nonce,
+            gas_price: U256::zero(),
+            gas: U256::zero(),
+            recipient: self.recipient.clone(),
+            value: U256::zero(),
+            data: self.data.clone(),
+            v: self.v,
+            r: self.r.clone(),
+            s: self.s.clone(),
+        }
+    }
+
+    /// 트랜잭션의 hash이며 DAG에서 트랜잭션을 구분하는 값이다.
+    pub fn hash(&self) -> H256 {
+        H256::from(crypto::hash::keccak256(rlp::encode(self).as_ref()))
+    }
+
+    /// parent_hash에 이어붙여 저장된 부모 트랜잭션들의 hash
+    pub fn parents(&self) -> Vec<H256> {
+        self.parent_hash.chunks(32)
+            .filter(|chunk| chunk.len() == 32)
+            .map(|chunk| H256::from_slice(chunk))
+            .collect()
+    }
+
+    pub fn set_parents(&mut self, parents: &Vec<H256>) {
+        self.parent_hash.clear();
+        for parent in parents.iter() { self.parent_hash.extend_from_slice(parent.as_bytes()); }
+    }
 }
 
 impl Default for Transaction {
@@ -171,36 +301,15 @@ impl Default for Transaction {
             parent_hash: vec![],
             committer: Address::zero(),
             validators: Hash160Vector::new(),
-        }
-    }
-}
-
-impl From<&Row<'_>> for Transaction {
-    fn from(row: &Row) -> Self {
-        let address_vec: Vec<u8> = row.get(1).unwrap();
-        let state_hash_vec: Vec<u8> = row.get(7).unwrap();
-        let committer_vec: Vec<u8> = row.get(9).unwrap();
-        let validators_vec: Vec<u8> = row.get(10).unwrap();
-
-        Transaction {
-            nonce: row.get(0).unwrap(),
-            recipient: Address::from_slice(address_vec.as_slice()),
-            data: row.get(2).unwrap(),
-            v: row.get(3).unwrap(),
-            r: row.get(4).unwrap(),
-            s: row.get(5).unwrap(),
-            timestamp: row.get(6).unwrap(),
-            state_hash: H256::from_slice(state_hash_vec.as_slice()),
-            parent_hash: row.get(8).unwrap(),
-            committer: Address::from_slice(committer_vec.as_slice()),
-            validators: Hash160Vector::from(validators_vec),
+            envelope: vec![],
         }
     }
 }
 
 impl Encodable for Transaction {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(9);
+        // 서명된 원본이 없는 트랜잭션은 원본이 추가되기 전과 같은 값(hash)을 갖는다.
+        s.begin_list(if self.envelope.is_empty() { 11 } else { 12 });
         s.append(&self.nonce);
         s.append(&self.recipient);
         s.append(&self.data);
@@ -212,6 +321,7 @@ impl Encodable for Transaction {
         s.append(&self.parent_hash);
         s.append(&self.committer);
         s.append(&self.validators);
+        if !self.envelope.is_empty() { s.append(&self.envelope); }
     }
 }
 
@@ -228,133 +338,48 @@ impl Decodable for Transaction {
             state_hash: rlp.val_at(7)?,
             parent_hash: rlp.val_at(8)?,
             committer: rlp.val_at(9)?,
-            validators: rlp.val_at(10)?
+            validators: rlp.val_at(10)?,
+            envelope: if rlp.item_count()? > 11 { rlp.val_at(11)? } else { vec![] }
         })
     }
 }
 
-pub struct TransactionTable {
-    connection: Connection
-}
-
-impl TransactionTable {
-    pub fn new() -> Self {
-        let conn = Connection::open(crate::constant::DatabasePath);
-        TransactionTable { connection: conn.unwrap() }
-    }
-}
-
-impl Default for TransactionTable {
-    fn default() -> Self {
-        TransactionTable::new()
-    }
+pub struct TransactionTableManager {
+    backend: Arc<dyn Backend>,
 }
 
-impl Table for TransactionTable {
-    fn get_table_name(&self) -> String {
-        "tx".to_string()
-    }
-
-    fn get_create_table_query(&self) -> String {
-        "CREATE TABLE IF NOT EXISTS tx(\
-            nonce INTEGER,\
-            recipient BLOB,\
-            data BLOB,\
-            v INTEGER,\
-            r BLOB,\
-            s BLOB,\
-            timestamp INTEGER,\
-            state_hash BLOB,\
-            parent_hash BLOB,\
-            committer BLOB,\
-            validators BLOB)"    // rlp-encoded list of address
-            .to_string()
-    }
-
-    fn get_drop_table_query(&self) -> String {
-        "DROP TABLE tx".to_string()
-    }
-
-    fn get_select_query(&self, where_type: &str) -> String {
-        format!("SELECT * FROM tx WHERE {} = ?", where_type)
-    }
-
-    fn get_insert_query(&self) -> String {
-        "INSERT INTO tx \
-            (nonce, recipient, data, v, r, s, timestamp, state_hash, parent_hash, committer, validators) \
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
-            .to_string()
-    }
-
-    fn get_update_query(&self) -> String {
-        "".to_string()
-    }
-
-    fn get_delete_query(&self, where_type: &str) -> String {
-        format!("DELETE FROM tx WHERE {} = ?", where_type)
-    }
-
-    fn make_statement(&self, query: &str) -> Statement {
-        self.connection.prepare(query).unwrap()
+impl TransactionTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        TransactionTableManager { backend }
     }
-}
-
-pub struct TransactionTableManager {
-    table: Vec<Box<dyn std::any::Any>>,
-    pub table_name: String,
-}
 
-impl Container for TransactionTableManager {
-    fn initialize(&self) {
-        let query = self.get_table().get_create_table_query();
-        let mut stmt = self.get_table().make_statement(query.as_str());
-        stmt.execute([]);
+    pub fn put_transaction(&self, batch: &mut WriteBatch, tx: &Transaction) {
+        batch.put(TX_COLUMN, tx.hash().as_bytes(), &rlp::encode(tx));
     }
-}
 
-impl TransactionTableManager {
-    pub fn new() -> Self {
-        let tbl = TransactionTable::new();
-        let mut result = TransactionTableManager {
-            table: vec![],
-            table_name: tbl.get_table_name(),
-        };
-        result.table.push(Box::new(tbl));
-        return result;
+    pub fn insert_transaction(&self, tx: &Transaction) -> Result<(), ()> {
+        if self.exist(&tx.hash()) { return Err(()); }
+        let mut batch = WriteBatch::new();
+        self.put_transaction(&mut batch, tx);
+        self.backend.write(batch)
     }
 
-    pub fn get_table(&self) -> &TransactionTable {
-        self.table.get(0).unwrap().downcast_ref::<TransactionTable>().unwrap()
+    pub fn exist(&self, hash: &H256) -> bool {
+        self.backend.exists(TX_COLUMN, hash.as_bytes())
     }
 
-    pub fn insert_transaction(&self, tx: Transaction) -> Result<(), ()> {
-        let tbl = self.table.get(0).unwrap().downcast_ref::<TransactionTable>().unwrap();
-        let query = tbl.get_insert_query();
-        let mut stmt = tbl.connection.prepare(query.as_str()).unwrap();
-        let cnt = stmt.execute([
-            H256::from_low_u64_le(tx.nonce as u64).as_bytes(),
-            tx.recipient.as_bytes(),
-            tx.data.as_slice(),
-            H256::from_low_u64_le(tx.v as u64).as_bytes(),
-            tx.r.as_slice(),
-            tx.s.as_slice(),
-            H256::from_low_u64_le(tx.timestamp).as_bytes(),
-            tx.state_hash.as_bytes(),
-            tx.parent_hash.as_slice()
-        ]);
-        Ok(())
+    pub fn get_transaction(&self, hash: &H256) -> Option<Transaction> {
+        let value = self.backend.get(TX_COLUMN, hash.as_bytes())?;
+        rlp::decode::<Transaction>(value.as_slice()).ok()
     }
 
     pub fn first_transaction(&self) -> Result<Transaction, ()> {
-        let tbl = self.get_table();
-        let query = "SELECT * FROM transaction ORDER BY timestamp ASC LIMIT 1";
-        let mut stmt = tbl.connection.prepare(query).unwrap();
-        let datum = stmt.query_row([], |row| {
-           Ok(Transaction::from(row))
-        });
-        return match datum {
-            Ok(_) => { Ok(datum.unwrap()) }
-            Err(_) => { Err(())}
+        let first = self.backend.scan(TX_COLUMN, &[]).into_iter()
+            .filter_map(|(_, value)| rlp::decode::<Transaction>(value.as_slice()).ok())
+            .min_by_key(|tx| tx.timestamp);
+        return match first {
+            Some(tx) => { Ok(tx) }
+            None => { Err(()) }
         }
     }
 }
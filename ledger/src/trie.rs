@@ -0,0 +1,563 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use ethereum_types::H256;
+use rlp::{Rlp, RlpStream};
+use std::sync::Arc;
+use crate::backend::{Backend, WriteBatch};
+
+/// 비어있는 trie의 root (keccak256(rlp("")))
+pub const EMPTY_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// trie node를 hash로 저장하고 읽어오는 저장소
+pub trait TrieStorage {
+    fn get_node(&self, hash: &H256) -> Option<Vec<u8>>;
+    fn insert_node(&self, hash: &H256, node: &Vec<u8>);
+}
+
+/// 메모리에만 node를 저장하는 저장소이며 proof 검증 등에 사용한다.
+pub struct MemoryTrieStorage(RefCell<HashMap<H256, Vec<u8>>>);
+
+impl MemoryTrieStorage {
+    pub fn new() -> Self { MemoryTrieStorage { 0: RefCell::new(HashMap::new()) } }
+
+    /// proof에 포함된 node들로 저장소를 만든다.
+    pub fn from_proof(proof: &Vec<Vec<u8>>) -> Self {
+        let storage = MemoryTrieStorage::new();
+        for node in proof.iter() {
+            storage.insert_node(&H256::from(crypto::hash::keccak256(node.as_slice())), node);
+        }
+        return storage;
+    }
+}
+
+impl TrieStorage for MemoryTrieStorage {
+    fn get_node(&self, hash: &H256) -> Option<Vec<u8>> {
+        self.0.borrow().get(hash).cloned()
+    }
+
+    fn insert_node(&self, hash: &H256, node: &Vec<u8>) {
+        self.0.borrow_mut().insert(hash.clone(), node.clone());
+    }
+}
+
+/// hash -> trie node
+pub const TRIE_NODE_COLUMN: &str = "trie_node";
+/// sequence(u64 big endian) -> world state root
+pub const STATE_ROOT_COLUMN: &str = "state_root";
+
+/// world state trie와 모든 컨트랙트의 storage trie의 node, 그리고 commit마다 계산된 world state root를 저장한다.
+/// node는 내용의 hash로 구분되므로 여러 trie가 같은 node를 공유할 수 있다.
+pub struct TrieTableManager {
+    backend: Arc<dyn Backend>,
+}
+
+impl TrieTableManager {
+    pub fn new(backend: Arc<dyn Backend>) -> Self {
+        TrieTableManager { backend }
+    }
+
+    /// 마지막으로 기록된 world state root. 기록이 없다면 EMPTY_ROOT이다.
+    pub fn latest_state_root(&self) -> H256 {
+        match self.backend.last(STATE_ROOT_COLUMN, &[]) {
+            Some((_, root)) if root.len() == 32 => { H256::from_slice(root.as_slice()) }
+            _ => { EMPTY_ROOT }
+        }
+    }
+
+    /// state root를 batch에 기록한다.
+    pub fn put_state_root(&self, batch: &mut WriteBatch, root: &H256) {
+        let sequence = match self.backend.last(STATE_ROOT_COLUMN, &[]) {
+            Some((key, _)) if key.len() == 8 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(key.as_slice());
+                u64::from_be_bytes(bytes) + 1
+            }
+            _ => { 0 }
+        };
+        batch.put(STATE_ROOT_COLUMN, &sequence.to_be_bytes(), root.as_bytes());
+    }
+
+    pub fn insert_state_root(&self, root: &H256) -> Result<(), ()> {
+        let mut batch = WriteBatch::new();
+        self.put_state_root(&mut batch, root);
+        self.backend.write(batch)
+    }
+}
+
+impl TrieStorage for TrieTableManager {
+    fn get_node(&self, hash: &H256) -> Option<Vec<u8>> {
+        self.backend.get(TRIE_NODE_COLUMN, hash.as_bytes())
+    }
+
+    fn insert_node(&self, hash: &H256, node: &Vec<u8>) {
+        let _ = self.backend.put(TRIE_NODE_COLUMN, hash.as_bytes(), node.as_slice());
+    }
+}
+
+/// 새로 만든 node를 저장소에 바로 쓰지 않고 모아두는 저장소
+/// 모아둔 node는 into_batch로 다른 변경과 함께 하나의 batch에 기록한다.
+pub struct PendingTrieStorage<'a> {
+    base: &'a TrieTableManager,
+    nodes: RefCell<HashMap<H256, Vec<u8>>>,
+}
+
+impl<'a> PendingTrieStorage<'a> {
+    pub fn new(base: &'a TrieTableManager) -> Self {
+        PendingTrieStorage { base, nodes: RefCell::new(HashMap::new()) }
+    }
+
+    pub fn into_batch(self, batch: &mut WriteBatch) {
+        for (hash, node) in self.nodes.into_inner().into_iter() {
+            batch.put(TRIE_NODE_COLUMN, hash.as_bytes(), node.as_slice());
+        }
+    }
+}
+
+impl<'a> TrieStorage for PendingTrieStorage<'a> {
+    fn get_node(&self, hash: &H256) -> Option<Vec<u8>> {
+        match self.nodes.borrow().get(hash) {
+            Some(node) => { Some(node.clone()) }
+            None => { self.base.get_node(hash) }
+        }
+    }
+
+    fn insert_node(&self, hash: &H256, node: &Vec<u8>) {
+        self.nodes.borrow_mut().insert(hash.clone(), node.clone());
+    }
+}
+
+/// trie의 node. 아직 읽어오지 않은 하위 node는 Hash로 표현된다.
+/// key는 모두 nibble(4 bits) 단위의 path이다.
+enum Node {
+    Empty,
+    Leaf(Vec<u8>, Vec<u8>),
+    Extension(Vec<u8>, Box<Node>),
+    Branch(Box<[Node; 16]>, Option<Vec<u8>>),
+    Hash(H256),
+}
+
+impl Clone for Node {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Empty => { Node::Empty }
+            Node::Leaf(key, value) => { Node::Leaf(key.clone(), value.clone()) }
+            Node::Extension(key, child) => { Node::Extension(key.clone(), child.clone()) }
+            Node::Branch(children, value) => { Node::Branch(children.clone(), value.clone()) }
+            Node::Hash(hash) => { Node::Hash(hash.clone()) }
+        }
+    }
+}
+
+fn empty_children() -> Box<[Node; 16]> {
+    Box::new([Node::Empty, Node::Empty, Node::Empty, Node::Empty,
+        Node::Empty, Node::Empty, Node::Empty, Node::Empty,
+        Node::Empty, Node::Empty, Node::Empty, Node::Empty,
+        Node::Empty, Node::Empty, Node::Empty, Node::Empty])
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    let mut nibbles = Vec::with_capacity(key.len() * 2);
+    for byte in key.iter() {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+    nibbles
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+/// hex-prefix encoding (yellow paper appendix C)
+fn encode_path(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 0x20 } else { 0x00 };
+    let mut encoded = vec![];
+    let mut rest = nibbles;
+    if nibbles.len() % 2 == 1 {
+        encoded.push(flag | 0x10 | nibbles[0]);
+        rest = &nibbles[1..];
+    } else {
+        encoded.push(flag);
+    }
+    for pair in rest.chunks(2) {
+        encoded.push(pair[0] << 4 | pair[1]);
+    }
+    encoded
+}
+
+fn decode_path(encoded: &[u8]) -> (Vec<u8>, bool) {
+    if encoded.is_empty() { return (vec![], false); }
+    let leaf = encoded[0] & 0x20 != 0;
+    let mut nibbles = vec![];
+    if encoded[0] & 0x10 != 0 { nibbles.push(encoded[0] & 0x0f); }
+    nibbles.extend(to_nibbles(&encoded[1..]));
+    (nibbles, leaf)
+}
+
+fn decode_node(rlp: &Rlp) -> Result<Node, ()> {
+    if rlp.is_data() {
+        if rlp.is_empty() { return Ok(Node::Empty); }
+        let data = rlp.data().map_err(|_| ())?;
+        if data.len() != 32 { return Err(()); }
+        return Ok(Node::Hash(H256::from_slice(data)));
+    }
+    match rlp.item_count().map_err(|_| ())? {
+        2 => {
+            let path: Vec<u8> = rlp.val_at(0).map_err(|_| ())?;
+            let (nibbles, leaf) = decode_path(path.as_slice());
+            if leaf {
+                let value: Vec<u8> = rlp.val_at(1).map_err(|_| ())?;
+                return Ok(Node::Leaf(nibbles, value));
+            }
+            let child = decode_node(&rlp.at(1).map_err(|_| ())?)?;
+            Ok(Node::Extension(nibbles, Box::new(child)))
+        }
+        17 => {
+            let mut children = empty_children();
+            for idx in 0..16 {
+                children[idx] = decode_node(&rlp.at(idx).map_err(|_| ())?)?;
+            }
+            let value: Vec<u8> = rlp.val_at(16).map_err(|_| ())?;
+            let value = if value.is_empty() { None } else { Some(value) };
+            Ok(Node::Branch(children, value))
+        }
+        _ => { Err(()) }
+    }
+}
+
+/// 이더리움과 동일한 Merkle Patricia Trie
+/// 변경 사항은 메모리에 유지되며 commit을 호출해야 저장소에 node가 기록된다.
+pub struct PatriciaTrie<'a> {
+    storage: &'a dyn TrieStorage,
+    root: Node,
+}
+
+impl<'a> PatriciaTrie<'a> {
+    pub fn new(storage: &'a dyn TrieStorage) -> Self {
+        PatriciaTrie { storage, root: Node::Empty }
+    }
+
+    /// 저장소에 기록된 root로부터 trie를 연다.
+    pub fn from_root(storage: &'a dyn TrieStorage, root: &H256) -> Self {
+        let node = if *root == EMPTY_ROOT || root.is_zero() { Node::Empty } else { Node::Hash(root.clone()) };
+        PatriciaTrie { storage, root: node }
+    }
+
+    fn resolve(&self, node: Node) -> Result<Node, ()> {
+        match node {
+            Node::Hash(hash) => {
+                let encoded = match self.storage.get_node(&hash) {
+                    None => { return Err(()); }
+                    Some(encoded) => { encoded }
+                };
+                decode_node(&Rlp::new(encoded.as_slice()))
+            }
+            _ => { Ok(node) }
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let path = to_nibbles(key);
+        let mut node = self.root.clone();
+        let mut depth = 0;
+        loop {
+            node = match self.resolve(node) {
+                Ok(node) => { node }
+                Err(_) => { return None; }
+            };
+            match node {
+                Node::Empty | Node::Hash(_) => { return None; }
+                Node::Leaf(key, value) => {
+                    if key.as_slice() == &path[depth..] { return Some(value); }
+                    return None;
+                }
+                Node::Extension(key, child) => {
+                    if !path[depth..].starts_with(key.as_slice()) { return None; }
+                    depth += key.len();
+                    node = *child;
+                }
+                Node::Branch(mut children, value) => {
+                    if depth == path.len() { return value; }
+                    node = std::mem::replace(&mut children[path[depth] as usize], Node::Empty);
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), ()> {
+        if value.is_empty() { return self.remove(key); }
+        let root = std::mem::replace(&mut self.root, Node::Empty);
+        self.root = self.insert_at(root, to_nibbles(key).as_slice(), value)?;
+        Ok(())
+    }
+
+    fn insert_at(&self, node: Node, path: &[u8], value: Vec<u8>) -> Result<Node, ()> {
+        match self.resolve(node)? {
+            Node::Empty => { Ok(Node::Leaf(path.to_vec(), value)) }
+            Node::Leaf(key, old) => {
+                let c = common_prefix(key.as_slice(), path);
+                if c == key.len() && c == path.len() { return Ok(Node::Leaf(key, value)); }
+                let mut children = empty_children();
+                let mut branch_value = None;
+                if c == key.len() { branch_value = Some(old); }
+                else { children[key[c] as usize] = Node::Leaf(key[c + 1..].to_vec(), old); }
+                if c == path.len() { branch_value = Some(value); }
+                else { children[path[c] as usize] = Node::Leaf(path[c + 1..].to_vec(), value); }
+                let branch = Node::Branch(children, branch_value);
+                if c == 0 { return Ok(branch); }
+                Ok(Node::Extension(key[..c].to_vec(), Box::new(branch)))
+            }
+            Node::Extension(key, child) => {
+                let c = common_prefix(key.as_slice(), path);
+                if c == key.len() {
+                    let child = self.insert_at(*child, &path[c..], value)?;
+                    return Ok(Node::Extension(key, Box::new(child)));
+                }
+                let mut children = empty_children();
+                let mut branch_value = None;
+                children[key[c] as usize] = match key.len() - c {
+                    1 => { *child }
+                    _ => { Node::Extension(key[c + 1..].to_vec(), child) }
+                };
+                if c == path.len() { branch_value = Some(value); }
+                else { children[path[c] as usize] = Node::Leaf(path[c + 1..].to_vec(), value); }
+                let branch = Node::Branch(children, branch_value);
+                if c == 0 { return Ok(branch); }
+                Ok(Node::Extension(key[..c].to_vec(), Box::new(branch)))
+            }
+            Node::Branch(mut children, branch_value) => {
+                if path.is_empty() { return Ok(Node::Branch(children, Some(value))); }
+                let idx = path[0] as usize;
+                let child = std::mem::replace(&mut children[idx], Node::Empty);
+                children[idx] = self.insert_at(child, &path[1..], value)?;
+                Ok(Node::Branch(children, branch_value))
+            }
+            Node::Hash(_) => { Err(()) }
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Result<(), ()> {
+        let root = std::mem::replace(&mut self.root, Node::Empty);
+        self.root = self.remove_at(root, to_nibbles(key).as_slice())?;
+        Ok(())
+    }
+
+    fn remove_at(&self, node: Node, path: &[u8]) -> Result<Node, ()> {
+        match self.resolve(node)? {
+            Node::Empty => { Ok(Node::Empty) }
+            Node::Leaf(key, value) => {
+                if key.as_slice() == path { return Ok(Node::Empty); }
+                Ok(Node::Leaf(key, value))
+            }
+            Node::Extension(key, child) => {
+                if !path.starts_with(key.as_slice()) { return Ok(Node::Extension(key, child)); }
+                let child = self.remove_at(*child, &path[key.len()..])?;
+                self.merge_extension(key, child)
+            }
+            Node::Branch(mut children, mut value) => {
+                if path.is_empty() { value = None; }
+                else {
+                    let idx = path[0] as usize;
+                    let child = std::mem::replace(&mut children[idx], Node::Empty);
+                    children[idx] = self.remove_at(child, &path[1..])?;
+                }
+                self.normalize_branch(children, value)
+            }
+            Node::Hash(_) => { Err(()) }
+        }
+    }
+
+    /// extension의 하위 node가 leaf나 extension이 되었다면 하나의 node로 합친다.
+    fn merge_extension(&self, key: Vec<u8>, child: Node) -> Result<Node, ()> {
+        match self.resolve(child)? {
+            Node::Empty => { Ok(Node::Empty) }
+            Node::Leaf(rest, value) => { Ok(Node::Leaf([key, rest].concat(), value)) }
+            Node::Extension(rest, child) => { Ok(Node::Extension([key, rest].concat(), child)) }
+            branch => { Ok(Node::Extension(key, Box::new(branch))) }
+        }
+    }
+
+    /// 하위 node가 하나만 남은 branch를 leaf 또는 extension으로 바꾼다.
+    fn normalize_branch(&self, mut children: Box<[Node; 16]>, value: Option<Vec<u8>>) -> Result<Node, ()> {
+        let used: Vec<usize> = (0..16).filter(|idx| match children[*idx] { Node::Empty => false, _ => true }).collect();
+        if used.is_empty() {
+            return match value {
+                None => { Ok(Node::Empty) }
+                Some(value) => { Ok(Node::Leaf(vec![], value)) }
+            };
+        }
+        if used.len() == 1 && value.is_none() {
+            let idx = used[0];
+            let child = std::mem::replace(&mut children[idx], Node::Empty);
+            return self.merge_extension(vec![idx as u8], child);
+        }
+        Ok(Node::Branch(children, value))
+    }
+
+    /// node를 rlp로 인코딩한다. 32 bytes 이상인 node는 저장소에 기록하고 hash로 참조한다.
+    fn encode_node(&self, node: &Node, stream: &mut RlpStream, store: bool) {
+        match node {
+            Node::Empty => { stream.append_empty_data(); }
+            Node::Hash(hash) => { stream.append(hash); }
+            _ => {
+                let encoded = self.encode_raw(node, store);
+                if encoded.len() < 32 { stream.append_raw(encoded.as_slice(), 1); }
+                else {
+                    let hash = H256::from(crypto::hash::keccak256(encoded.as_slice()));
+                    if store { self.storage.insert_node(&hash, &encoded); }
+                    stream.append(&hash);
+                }
+            }
+        }
+    }
+
+    fn encode_raw(&self, node: &Node, store: bool) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+        match node {
+            Node::Empty => { stream.append_empty_data(); }
+            Node::Hash(hash) => { stream.append(hash); }
+            Node::Leaf(key, value) => {
+                stream.begin_list(2);
+                stream.append(&encode_path(key.as_slice(), true));
+                stream.append(value);
+            }
+            Node::Extension(key, child) => {
+                stream.begin_list(2);
+                stream.append(&encode_path(key.as_slice(), false));
+                self.encode_node(child, &mut stream, store);
+            }
+            Node::Branch(children, value) => {
+                stream.begin_list(17);
+                for child in children.iter() { self.encode_node(child, &mut stream, store); }
+                match value {
+                    None => { stream.append_empty_data(); }
+                    Some(value) => { stream.append(value); }
+                };
+            }
+        }
+        stream.out().to_vec()
+    }
+
+    fn root_hash_with(&self, store: bool) -> H256 {
+        match &self.root {
+            Node::Empty => { EMPTY_ROOT }
+            Node::Hash(hash) => { hash.clone() }
+            node => {
+                let encoded = self.encode_raw(node, store);
+                let hash = H256::from(crypto::hash::keccak256(encoded.as_slice()));
+                if store { self.storage.insert_node(&hash, &encoded); }
+                hash
+            }
+        }
+    }
+
+    /// 저장소에 기록하지 않고 현재 root hash를 계산한다.
+    pub fn root_hash(&self) -> H256 {
+        self.root_hash_with(false)
+    }
+
+    /// 변경된 node를 저장소에 기록하고 root hash를 반환한다.
+    pub fn commit(&mut self) -> H256 {
+        let root = self.root_hash_with(true);
+        if root != EMPTY_ROOT { self.root = Node::Hash(root.clone()); }
+        root
+    }
+
+    /// key에 이르는 경로의 node들(rlp)을 root부터 순서대로 반환한다.
+    /// commit된 trie에서 호출해야 한다.
+    pub fn prove(&self, key: &[u8]) -> Vec<Vec<u8>> {
+        let path = to_nibbles(key);
+        let mut proof = vec![];
+        let mut node = self.root.clone();
+        let mut depth = 0;
+        loop {
+            if let Node::Hash(hash) = &node {
+                match self.storage.get_node(hash) {
+                    None => { return proof; }
+                    Some(encoded) => { proof.push(encoded); }
+                }
+            }
+            node = match self.resolve(node) {
+                Ok(node) => { node }
+                Err(_) => { return proof; }
+            };
+            match node {
+                Node::Extension(key, child) => {
+                    if !path[depth..].starts_with(key.as_slice()) { return proof; }
+                    depth += key.len();
+                    node = *child;
+                }
+                Node::Branch(mut children, _) => {
+                    if depth == path.len() { return proof; }
+                    node = std::mem::replace(&mut children[path[depth] as usize], Node::Empty);
+                    depth += 1;
+                }
+                _ => { return proof; }
+            }
+        }
+    }
+
+    /// proof로 root에 포함된 key의 값을 확인한다. 값이 없음이 증명되면 Ok(None)이다.
+    /// proof의 node가 부족하거나 root와 맞지 않으면 Err이다.
+    pub fn verify_proof(root: &H256, key: &[u8], proof: &Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, ()> {
+        let storage = MemoryTrieStorage::from_proof(proof);
+        if *root != EMPTY_ROOT && storage.get_node(root).is_none() { return Err(()); }
+        let trie = PatriciaTrie::from_root(&storage, root);
+        let path = to_nibbles(key);
+        let mut node = trie.root.clone();
+        let mut depth = 0;
+        loop {
+            match trie.resolve(node)? {
+                Node::Empty => { return Ok(None); }
+                Node::Hash(_) => { return Err(()); }
+                Node::Leaf(key, value) => {
+                    if key.as_slice() == &path[depth..] { return Ok(Some(value)); }
+                    return Ok(None);
+                }
+                Node::Extension(key, child) => {
+                    if !path[depth..].starts_with(key.as_slice()) { return Ok(None); }
+                    depth += key.len();
+                    node = *child;
+                }
+                Node::Branch(mut children, value) => {
+                    if depth == path.len() { return Ok(value); }
+                    node = std::mem::replace(&mut children[path[depth] as usize], Node::Empty);
+                    depth += 1;
+                }
+            }
+        }
+    }
+}
+
+/// key를 keccak256 hash하여 사용하는 trie. world state와 컨트랙트 storage에 사용한다.
+pub struct SecureTrie<'a>(PatriciaTrie<'a>);
+
+impl<'a> SecureTrie<'a> {
+    pub fn from_root(storage: &'a dyn TrieStorage, root: &H256) -> Self {
+        SecureTrie { 0: PatriciaTrie::from_root(storage, root) }
+    }
+
+    pub fn hash_key(key: &[u8]) -> [u8; 32] { crypto::hash::keccak256(key) }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> { self.0.get(&SecureTrie::hash_key(key)) }
+
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<(), ()> {
+        self.0.insert(&SecureTrie::hash_key(key), value)
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Result<(), ()> { self.0.remove(&SecureTrie::hash_key(key)) }
+
+    pub fn root_hash(&self) -> H256 { self.0.root_hash() }
+
+    pub fn commit(&mut self) -> H256 { self.0.commit() }
+
+    pub fn prove(&self, key: &[u8]) -> Vec<Vec<u8>> { self.0.prove(&SecureTrie::hash_key(key)) }
+
+    pub fn verify_proof(root: &H256, key: &[u8], proof: &Vec<Vec<u8>>) -> Result<Option<Vec<u8>>, ()> {
+        PatriciaTrie::verify_proof(root, &SecureTrie::hash_key(key), proof)
+    }
+}
@@ -6,8 +6,10 @@ mod options;
 #[cfg(test)]
 mod tests {
     use std::io::Write;
-    use std::sync::{Arc, Mutex};
+    use std::sync::Arc;
     use ledger::ledger::Ledger;
+    use ledger::handle::LedgerHandle;
+    use common::datadir::DataDir;
     use crate::p2p::P2pService;
     use crate::rpc::engine::RpcService;
 
@@ -18,11 +20,11 @@ mod tests {
 
     #[test]
     fn run_rpc() {
-        let ledger = Arc::new(Mutex::new(Ledger::new()));
+        let ledger = LedgerHandle::new(Ledger::new(&DataDir::default()));
         let ip = "127.0.0.1";
         let port: u16 = 8545;
         let rpc = Arc::new(
-            RpcService::new(ip, &port)
+            RpcService::new(ip, &port, ledger.clone())
         );
 
         // rpc.run_loop();
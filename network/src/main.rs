@@ -1,6 +1,7 @@
 
-use std::sync::{Arc, Mutex};
 use ledger::ledger::Ledger;
+use ledger::handle::LedgerHandle;
+use common::datadir::DataDir;
 
 mod p2p;
 mod urpc;
@@ -8,15 +9,15 @@ mod rpc;
 mod options;
 
 fn main() {
-    let rpc = Arc::new(crate::rpc::server::RpcServer::new("127.0.0.1", 8545));
+    // RPC, consensus, P2P는 같은 ledger handle을 공유한다.
+    let ledger = LedgerHandle::new(Ledger::new(&DataDir::default()));
     {
-        // let rpc = rpc.clone();
+        let ledger = ledger.clone();
         std::thread::spawn(move || {
-            let rpc = crate::rpc::server::RpcServer::new("127.0.0.1", 8545);
+            let rpc = crate::rpc::server::RpcServer::new("127.0.0.1", 8545, ledger);
             rpc.bind();
         });
     }
-    // let ledger = Arc::new(Mutex::new(Ledger::new()));
     // let ip = "127.0.0.1";
     // let port: u16 = 8545;
     // let rpc = Arc::new(
@@ -1,19 +1,43 @@
 use std::net::{UdpSocket, Ipv4Addr};
 use std::io;
 use std::str::FromStr;
+use ledger::genesis::{ChainStatus, GenesisError};
+use ledger::handle::LedgerHandle;
 
 pub struct P2pService {
     socket: UdpSocket,
-    commands: Vec<u8>
+    commands: Vec<u8>,
+    ledger: LedgerHandle,
 }
 
 impl P2pService {
-    pub fn new() -> Self {
+    pub fn new(ledger: LedgerHandle) -> Self {
         let ip = Ipv4Addr::from_str("").unwrap();
         let socket = UdpSocket::bind("0.0.0.0:8504").unwrap();
         socket.set_broadcast(true);
         socket.set_nonblocking(true);
-        P2pService { socket, commands: vec![] }
+        P2pService { socket, commands: vec![], ledger }
+    }
+
+    /// 이 노드의 chain. 다른 chain의 peer는 연결하지 않는다.
+    pub fn status(&self) -> Result<ChainStatus, GenesisError> {
+        let config = self.ledger.read().chain_config();
+        config.map(|config| config.status()).ok_or(GenesisError::Uninitialized)
+    }
+
+    /// peer에게 처음 보내는 handshake message이며 rlp(ChainStatus)이다.
+    pub fn status_message(&self) -> Result<Vec<u8>, GenesisError> {
+        Ok(rlp::encode(&self.status()?).to_vec())
+    }
+
+    /// peer의 handshake message를 확인한다. chain id, network id, genesis가 다른 peer는 거부한다.
+    pub fn handshake(&self, message: &[u8]) -> Result<ChainStatus, GenesisError> {
+        let peer: ChainStatus = match rlp::decode(message) {
+            Ok(peer) => { peer }
+            Err(_) => { return Err(GenesisError::Parse("status".to_string())); }
+        };
+        self.status()?.check(&peer)?;
+        Ok(peer)
     }
 
     pub async fn run_non_blocking_loop(&mut self) {
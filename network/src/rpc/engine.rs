@@ -5,22 +5,24 @@ use std::collections::HashMap;
 use std::io::{Error, Read, Write};
 use serde_json::Value;
 use std::sync::{Arc, Mutex};
-use ledger::ledger::Ledger;
+use ledger::handle::LedgerHandle;
 use crate::rpc::methods::{Web3ClientVersion, ProcedureCall, Web3Sha3, NetVersion, EthBlockNumber, EthGetBalance};
 use crate::rpc::request::{RpcStringsRequest, RpcEmptyRequest};
 
 pub struct RpcService {
     addr: SocketAddr,
+    ledger: LedgerHandle,
 }
 
 impl RpcService {
-    pub fn new(_ip: &str, _port: &u16) -> Self {
+    pub fn new(_ip: &str, _port: &u16, ledger: LedgerHandle) -> Self {
         let ip = Ipv4Addr::from_str(_ip).unwrap();
         let ipv4 = SocketAddrV4::new(ip, _port.clone());
         let socket: SocketAddr = SocketAddr::from(ipv4);
 
         let rpc = RpcService {
             addr: socket,
+            ledger,
         };
         return rpc;
     }
@@ -58,7 +60,7 @@ impl RpcService {
         match json_body["method"].as_str() {
             None => {}
             Some(method_name) => {
-                let mut read_only_ledger = Ledger::new();
+                let read_only_ledger = self.ledger.read();
                 let mut id = 0;
                 if json_body["id"].as_u64().is_some() { id = json_body["id"].as_u64().unwrap(); }
                 else { return }
@@ -67,7 +69,7 @@ impl RpcService {
                     crate::rpc::method_names::Web3ClientVersion => {
                         let request: Web3ClientVersion = Web3ClientVersion::new(&0x0001);
                             // WEB3CLIENT_VERSION::from(RpcEmptyRequest::from(&json_body));
-                        let response = request.receive(&mut read_only_ledger, vec![]);
+                        let response = request.receive(&read_only_ledger);
                         stream.write(response.as_bytes());
                     }
                     crate::rpc::method_names::Web3Sha3 => {
@@ -75,7 +77,7 @@ impl RpcService {
                     }
                     crate::rpc::method_names::NetVersion => {
                         let request = NetVersion::new(&id);
-                        let response = request.receive(&mut read_only_ledger, vec![]);
+                        let response = request.receive(&read_only_ledger);
                         println!("netVersion::{}", response.as_str());
                         stream.write(response.as_bytes());
                     }
@@ -90,14 +92,14 @@ impl RpcService {
                     crate::rpc::method_names::EthAccounts => {}
                     crate::rpc::method_names::EthBlockNumber => {
                         let request  = EthBlockNumber::new(&id);
-                        let response = request.receive(&mut read_only_ledger, vec![]);
+                        let response = request.receive(&read_only_ledger);
                         println!("blockNumber::{}", response.as_str());
                         stream.write(response.as_bytes());
                     }
                     crate::rpc::method_names::EthGetBalance => {
                         let addr = ethereum_types::Address::from_str("0x21d86d3d81c9b3d8577bb2bf579b9ba8aaef367a").unwrap();
                         let request = EthGetBalance::new(&id, &addr);
-                        let response = request.receive(&mut read_only_ledger, vec![]);
+                        let response = request.receive(&read_only_ledger);
                         println!("getBalance::{}", response.as_str());
                         stream.write(response.as_bytes());
                     }
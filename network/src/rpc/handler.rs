@@ -2,10 +2,12 @@ use basic_http::request::HttpRequest;
 use basic_http::response::HttpResponse;
 use serde_json::Value;
 use basic_http::status::HttpStatusCode;
+use ledger::handle::LedgerHandle;
 use ledger::ledger::Ledger;
 use crate::rpc::methods::ProcedureCall;
 
-pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
+/// JSON-RPC 요청을 처리한다. 읽기만 하는 RPC는 ledger의 read lock을, pool이나 상태를 바꾸는 RPC는 write lock을 잡는다.
+pub fn rpc_handler(ledger: &LedgerHandle, request: HttpRequest, mut response: HttpResponse) {
     let data = request.body().data();
     let rpc_object: Value = serde_json::from_str(data).unwrap();
     let opt_rpc_method = rpc_object.get("method");
@@ -23,8 +25,6 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
     let rpc_params = opt_rpc_params.unwrap().as_array().unwrap();
 
     println!("{} :: {}", request.path(), rpc_method);
-    let mut readonly_ledger = Ledger::new();
-
     // eth_chainId & net_version
     // eth_chainId & net_version
     // eth_gasPrice
@@ -35,7 +35,7 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
                 crate::rpc::method_names::WEB3CLIENT_VERSION
             );
             let data = crate::rpc::methods::Web3ClientVersion::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -45,7 +45,7 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
                 crate::rpc::method_names::NET_VERSION
             );
             let data = crate::rpc::methods::NetVersion::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -56,7 +56,7 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
             crate::rpc::method_names::ETH_PROTOCOL_VERSION
             );
             let data = crate::rpc::methods::EthProtocolVersion::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -69,7 +69,7 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
             crate::rpc::method_names::ETH_GAS_PRICE
             );
             let data = crate::rpc::methods::EthGasPrice::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -79,7 +79,7 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
             crate::rpc::method_names::ETH_BLOCK_NUMBER
             );
             let data = crate::rpc::methods::EthBlockNumber::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -90,17 +90,25 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
                 rpc_params
             );
             let data = crate::rpc::methods::EthGetBalance::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
+            response.set_code(HttpStatusCode::Ok);
+            response.set_data(data.as_str());
+        }
+        crate::rpc::method_names::ETH_STORAGE_AT => {
+            let rpc_request = crate::rpc::request::RpcStringsRequest::new(
+                &rpc_id, "2.0", crate::rpc::method_names::ETH_STORAGE_AT, &rpc_params
+            );
+            let data = crate::rpc::methods::EthStorageAt::from(rpc_request)
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
-        crate::rpc::method_names::ETH_STORAGE_AT => {}
         crate::rpc::method_names::ETH_GET_TX_COUNT => {
             let rpc_request = crate::rpc::request::RpcStringsRequest::new(
                 &rpc_id, "2.0", crate::rpc::method_names::ETH_GET_TX_COUNT, &rpc_params
             );
             let data = crate::rpc::methods::EthGetTransactionCount::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -122,16 +130,38 @@ pub fn rpc_handler(request: HttpRequest, mut response: HttpResponse) {
                 num += 1;
             }
             let data =crate::rpc::methods::EthSendRawTransaction::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(ledger);
+            response.set_code(HttpStatusCode::Ok);
+            response.set_data(data.as_str());
+        }
+        crate::rpc::method_names::ETH_CALL => {
+            let call_object = rpc_params.get(0).cloned().unwrap_or(Value::Null);
+            let mut rpc_request = crate::rpc::request::RpcStringsRequest::from_call_object(&rpc_id, "2.0",
+            crate::rpc::method_names::ETH_CALL, &call_object);
+            let tag = rpc_params.get(1).and_then(|tag| tag.as_str()).unwrap_or("latest");
+            rpc_request.params.push(tag.to_string());
+            // VM 실행 중에는 lock을 잡지 않도록 같은 backend를 읽는 ledger를 만들어 실행한다.
+            let state = Ledger::with_backend(ledger.read().get_backend());
+            let data = crate::rpc::methods::EthCall::from(rpc_request)
+                .receive(&state);
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
-        crate::rpc::method_names::ETH_CALL => {}
         crate::rpc::method_names::ETH_ESTIMATE_GAS => {
-            let rpc_request = crate::rpc::request::RpcEmptyRequest::new(&rpc_id, "2.0",
-            crate::rpc::method_names::ETH_ESTIMATE_GAS);
+            let call_object = rpc_params.get(0).cloned().unwrap_or(Value::Null);
+            let rpc_request = crate::rpc::request::RpcStringsRequest::from_call_object(&rpc_id, "2.0",
+            crate::rpc::method_names::ETH_ESTIMATE_GAS, &call_object);
+            let state = Ledger::with_backend(ledger.read().get_backend());
             let data = crate::rpc::methods::EthEstimatedGas::from(rpc_request)
-                .receive(&mut readonly_ledger);
+                .receive(&state);
+            response.set_code(HttpStatusCode::Ok);
+            response.set_data(data.as_str());
+        }
+        crate::rpc::method_names::ETH_GET_TX_RECEIPT => {
+            let rpc_request = crate::rpc::request::RpcStringsRequest::new(&rpc_id, "2.0",
+            crate::rpc::method_names::ETH_GET_TX_RECEIPT, rpc_params);
+            let data = crate::rpc::methods::EthGetTransactionReceipt::from(rpc_request)
+                .receive(&ledger.read());
             response.set_code(HttpStatusCode::Ok);
             response.set_data(data.as_str());
         }
@@ -12,7 +12,7 @@ pub const ETH_GAS_PRICE:                            &str = "eth_gasPrice";
 pub const ETH_ACCOUNTS:                             &str = "eth_accounts";
 pub const ETH_BLOCK_NUMBER:                         &str = "eth_blockNumber";
 pub const ETH_GET_BALANCE:                          &str = "eth_getBalance";
-pub const ETH_STORAGE_AT:                           &str = "eth_storageAt";
+pub const ETH_STORAGE_AT:                           &str = "eth_getStorageAt";
 pub const ETH_GET_TX_COUNT:                         &str = "eth_getTransactionCount";
 pub const ETH_GET_BLOCK_TX_COUNT_BY_HASH:           &str = "eth_getBlockTransactionCountByHash";
 pub const ETH_GET_BLOCK_TX_COUNT_BY_NUMBER:         &str = "eth_getBlockTransactionCountByNumber";
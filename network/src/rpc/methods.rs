@@ -3,10 +3,16 @@ use rlp::{Decodable, Encodable, RlpStream, DecoderError, Rlp};
 use serde::{Serialize, Deserialize};
 use std::sync::{Mutex, Arc};
 use ledger::ledger::Ledger;
-use ledger::transaction::{RawTransaction, Transaction};
+use ledger::handle::LedgerHandle;
+use ledger::archive::{ArchiveError, BlockTag};
+use ledger::transaction::Transaction;
+use ledger::envelope::TypedTransaction;
+use ledger::device_call::{DeviceCallError, DeviceCallTransaction, DEVICE_CALL_TX_TYPE};
+use ledger::batch::{self, BatchError, BatchTransaction, BATCH_TX_TYPE};
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::rpc::request::{RpcStringsRequest, RpcEmptyRequest};
-use crate::rpc::response::{RpcStringResponse, RpcBoolResponse, RpcMapResponse, RpcStringArrayResponse};
+use crate::rpc::response::{RpcStringResponse, RpcBoolResponse, RpcMapResponse, RpcStringArrayResponse, RpcErrorResponse, RpcObjectResponse};
 use serde_json::Value;
 use crate::rpc::method_names;
 use std::fmt::Write;
@@ -17,7 +23,8 @@ pub trait ProcedureCall {
     /// 노드가 해당 RPC를 호출할 때 사용하는 메서드
     fn call(&self) -> String;
     /// 노드가 해당 RPC를 요청받을 때 사용하는 메서드
-    fn receive(&self, ledger: &mut Ledger) -> String;
+    /// ledger는 handler가 잡은 read lock의 ledger이므로 상태를 바꾸는 RPC는 이 trait으로 구현하지 않는다.
+    fn receive(&self, ledger: &Ledger) -> String;
 }
 
 
@@ -47,7 +54,7 @@ impl ProcedureCall for Web3ClientVersion {
 
     /// receive returns the current client version.
     /// String  - The current client version
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let result = "Biiot/v0.1.0/windows/rust1.52".to_string();
         let res = RpcStringResponse::new(self.0.id, &result);
         return serde_json::to_string(&res).unwrap();
@@ -87,7 +94,7 @@ impl ProcedureCall for Web3Sha3 {
 
     /// receive returns Keccak-256 of the given data
     /// DATA    - the data to convert into a SHA3 hash
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let data = self.0.params.get(0).unwrap().as_bytes();
         let u8a32h = crypto::hash::keccak256(data);
         let result = hex::encode(u8a32h);
@@ -127,8 +134,9 @@ impl ProcedureCall for NetVersion {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        let net_ver: String = "9".to_string();
+    fn receive(&self, ledger: &Ledger) -> String {
+        // genesis로 초기화되지 않은 노드는 이전과 같이 9를 반환한다.
+        let net_ver: String = ledger.chain_config().map_or(9, |config| config.network_id).to_string();
         let res = RpcStringResponse::new(self.0.id, &net_ver);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
@@ -157,7 +165,7 @@ impl ProcedureCall for NetListening {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let res = RpcBoolResponse::new(self.0.id, true);
         return serde_json::to_string::<RpcBoolResponse>(&res).unwrap();
     }
@@ -187,7 +195,7 @@ impl ProcedureCall for NetPeerCount {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let value = "0x00";
         let res = RpcStringResponse::new(self.0.id, value);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
@@ -218,7 +226,7 @@ impl ProcedureCall for EthProtocolVersion {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let proto_ver = "54".to_string();
         let res = RpcStringResponse::new(self.0.id, &proto_ver);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
@@ -250,7 +258,7 @@ impl ProcedureCall for EthSyncing {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         // 본래는 sync 상태 여부에 따라 false, {startingBlock, currentBlock, highestBlock} 중
         // 하나를 반환해야 하지만 이를 판단하기 어려울 정도로 빠르게 업데이트 되므로 맵 타입만 반환한다.
         // 21.05.26 :: 컨트랙트 체인이 아닌 메인 체인에 대한 sync 값을 제공한다.
@@ -286,8 +294,9 @@ impl ProcedureCall for EthCoinbase {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        let account_result = accounts::DeviceAccount::read_account();
+    fn receive(&self, ledger: &Ledger) -> String {
+        let data_dir = ledger.get_data_dir().cloned().unwrap_or_default();
+        let account_result = accounts::DeviceAccount::read_account(&data_dir);
         return match account_result {
             Ok(account) => {
                 let result =
@@ -298,7 +307,7 @@ impl ProcedureCall for EthCoinbase {
             }
             Err(_) => {
                 // 블록체인이 구성되지 않은 노드일 경우에 발생한다.
-                let account = accounts::DeviceAccount::new();
+                let account = accounts::DeviceAccount::new(&data_dir);
                 let result = format!("0x{}", hex::encode(account.address.as_bytes()));
                 let res =
                     RpcStringResponse::new(self.0.id, result.as_str());
@@ -332,7 +341,7 @@ impl ProcedureCall for EthMining {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let res = RpcBoolResponse::new(self.0.id, true);
         return serde_json::to_string::<RpcBoolResponse>(&res).unwrap();
     }
@@ -362,7 +371,7 @@ impl ProcedureCall for EthHashrate {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let res = RpcStringResponse::new(self.0.id, "0x00");
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
@@ -391,7 +400,7 @@ impl ProcedureCall for EthGasPrice {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let price = "0x00".to_string();
         let res = RpcStringResponse::new(self.0.id, &price);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
@@ -421,7 +430,7 @@ impl ProcedureCall for EthAccounts {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         todo!(); // accounts에서 현재 노드가 갖고 있는 account 정보 불러오기
         let result = vec![];
         let res = RpcStringArrayResponse::new(self.0.id, &result);
@@ -452,10 +461,13 @@ impl ProcedureCall for EthBlockNumber {
         return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        // todo!(); // 메인 체인의 최근 블록 번호를 ledger로부터 받아온다.
-        let result = "0x0"; //"0x4b7";
-        let res = RpcStringResponse::new(self.0.id, result);
+    fn receive(&self, ledger: &Ledger) -> String {
+        // 마일스톤의 height를 블록 번호로 사용한다.
+        let result = match ledger.latest_milestone() {
+            Some(milestone) => { format!("0x{:x}", milestone.height) }
+            None => { "0x0".to_string() }
+        };
+        let res = RpcStringResponse::new(self.0.id, result.as_str());
         serde_json::to_string::<RpcStringResponse>(&res).unwrap()
     }
 }
@@ -492,7 +504,7 @@ impl ProcedureCall for EthGetBalance {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         // 제안 솔루션은 화폐를 일절 사용하지 않는다.
         let res = RpcStringResponse::new(self.0.id, &"0x00".to_string());
         serde_json::to_string(&res).unwrap()
@@ -500,8 +512,27 @@ impl ProcedureCall for EthGetBalance {
 }
 
 
+/// block parameter가 가리키는 시점의 상태를 읽는 ledger
+/// 조회할 수 없는 시점이라면 error response를 반환한다.
+fn ledger_at(ledger: &Ledger, id: u64, tag: &str) -> Result<Ledger, String> {
+    let (code, message) = match BlockTag::parse(tag) {
+        Some(tag) => {
+            match ledger.state_at(&tag) {
+                Ok(state) => { return Ok(state); }
+                Err(ArchiveError::NotArchived(height)) => {
+                    (-32000, format!("state at block 0x{:x} is not archived", height))
+                }
+                Err(ArchiveError::UnknownHeight(height)) => { (-32000, format!("unknown block 0x{:x}", height)) }
+            }
+        }
+        None => { (-32602, format!("invalid block parameter: {}", tag)) }
+    };
+    let res = RpcErrorResponse::new(id, code, message.as_str(), None);
+    Err(serde_json::to_string::<RpcErrorResponse>(&res).unwrap())
+}
+
 /// 컨트랙트의 storage 값을 반환하는 RPC
-/// [contract-address, variable-index]로 구성된다.
+/// [contract-address, variable-index, block parameter]로 구성되며 block parameter의 기본값은 "latest"이다.
 pub struct EthStorageAt(RpcStringsRequest);
 
 impl EthStorageAt {
@@ -510,7 +541,7 @@ impl EthStorageAt {
         write!(&mut str_ca, "0x{}", hex::encode(contract_address.as_bytes()));
         let mut str_pos = String::new();
         write!(&mut str_pos, "0x{}", hex::encode(position.as_bytes()));
-        let mut params = vec![Value::from(str_ca), Value::from(str_pos)];
+        let mut params = vec![Value::from(str_ca), Value::from(str_pos), Value::from("latest")];
 
         let request =
             RpcStringsRequest::new(
@@ -537,30 +568,44 @@ impl ProcedureCall for EthStorageAt {
     /// call returns the value from a storage position at a given address.
     /// DATA    - 20bytes address of the storage
     /// QUANTITY- integer of the position in the storage
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        // 해당하는 contract db파일에서 index에 해당하는 변수를 찾는다.
-        let str_address = self.0.params.get(0).unwrap().split_at(2).1;
-        let str_position = self.0.params.get(1).unwrap().split_at(2).1;
-        let address = Address::from_slice(str_address.as_bytes());
-        let index = H256::from_slice(str_position.as_bytes());
-        let state = Ledger::account_state(&address);
-        let result = state.get_storage_value(&index).value;
-        // let node = ledger.get_account(&self.data);
+    /// QUANTITY|TAG - block number, or "latest", "pending", "earliest"
+    fn receive(&self, ledger: &Ledger) -> String {
+        let param = |index: usize| self.0.params.get(index).map_or("", |value| value.as_str());
+        let address = match hex::decode(param(0).trim_start_matches("0x")) {
+            Ok(bytes) if bytes.len() == 20 => { Address::from_slice(bytes.as_slice()) }
+            _ => {
+                let res = RpcErrorResponse::new(self.0.id, -32602, "invalid address", None);
+                return serde_json::to_string::<RpcErrorResponse>(&res).unwrap();
+            }
+        };
+        let index = match U256::from_str_radix(param(1).trim_start_matches("0x"), 16) {
+            Ok(index) => { H256::from(<[u8; 32]>::from(index)) }
+            Err(_) => {
+                let res = RpcErrorResponse::new(self.0.id, -32602, "invalid storage position", None);
+                return serde_json::to_string::<RpcErrorResponse>(&res).unwrap();
+            }
+        };
+        let state = match ledger_at(ledger, self.0.id, param(2)) {
+            Ok(state) => { state }
+            Err(res) => { return res; }
+        };
+        let result = state.get_storage_value(&address, &index);
         let res =
-            RpcStringResponse::new(self.0.id, &hex::encode(result.as_bytes()));
+            RpcStringResponse::new(self.0.id, &format!("0x{}", hex::encode(result.as_bytes())));
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
 }
 
 
 /// 특정 account가 전송한 트랜잭션의 총합을 반환하는 RPC
+/// 두번째 인자로 "latest"(기본값), "pending", "earliest"를 받는다.
 pub struct EthGetTransactionCount(RpcStringsRequest);
 
 impl EthGetTransactionCount {
     pub fn new(id: &u64, address: &Address) -> Self {
         let mut str_addr = String::new();
         write!(&mut str_addr, "0x{}", hex::encode(address.as_bytes()));
-        let params = vec![Value::from(str_addr)];
+        let params = vec![Value::from(str_addr), Value::from("latest")];
         let request =
             RpcStringsRequest::new(
                 id,
@@ -583,7 +628,7 @@ impl ProcedureCall for EthGetTransactionCount {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let str_address = self.0.params.get(0).unwrap().split_at(2).1;
         let hex_address = hex::decode(str_address).unwrap();
         let mut addr_slice = [0u8;20];
@@ -591,7 +636,13 @@ impl ProcedureCall for EthGetTransactionCount {
             addr_slice[i] = hex_address.get(i).unwrap().clone();
         }
         let address = Address::from(addr_slice);
-        let res = RpcStringResponse::new(self.0.id, "0x1");
+        // "pending"은 pool에서 대기중인 트랜잭션까지 포함한다.
+        let count = match self.0.params.get(1).map(|tag| tag.as_str()) {
+            Some("pending") => { ledger.pending_nonce(&address) }
+            Some("earliest") => { 0 }
+            _ => { ledger.next_nonce(&address) }
+        };
+        let res = RpcStringResponse::new(self.0.id, format!("0x{:x}", count).as_str());
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
 }
@@ -626,7 +677,7 @@ impl ProcedureCall for EthGetCode {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let str_address = self.0.params.get(0).unwrap().split_at(2).1;
         let hex_address = hex::decode(str_address).unwrap();
         let address = Address::from_slice(hex_address.as_slice());
@@ -639,6 +690,9 @@ impl ProcedureCall for EthGetCode {
 }
 
 
+/// batch를 실행하는 동안 상태가 바뀌면 다시 실행하는 최대 횟수
+const BATCH_EXECUTION_ATTEMPTS: usize = 3;
+
 /// DATA, The signed transaction data.
 pub struct EthSendRawTransaction(RpcStringsRequest);
 
@@ -656,37 +710,110 @@ impl EthSendRawTransaction {
             );
         return EthSendRawTransaction { 0: request };
     }
-}
 
-impl From<RpcStringsRequest> for EthSendRawTransaction {
-    fn from(request: RpcStringsRequest) -> Self {
-        EthSendRawTransaction { 0: request }
+    fn error(&self, code: i64, message: &str, data: Option<String>) -> String {
+        let res = RpcErrorResponse::new(self.0.id, code, message, data);
+        return serde_json::to_string::<RpcErrorResponse>(&res).unwrap();
     }
-}
 
-impl ProcedureCall for EthSendRawTransaction {
-    fn call(&self) -> String {
+    /// device call은 컨트랙트 호출과 분리된 device call pool로 보낸다.
+    fn receive_device_call(&self, ledger: &LedgerHandle, bytes: &[u8]) -> String {
+        let tx = match DeviceCallTransaction::from_bytes(bytes) {
+            Ok(tx) => { tx }
+            Err(_) => { return self.error(-32602, "invalid device call", None); }
+        };
+        let hash = match ledger.write().admit_device_call(tx) {
+            Ok(hash) => { hash }
+            Err(DeviceCallError::InvalidSignature) => { return self.error(-32602, "invalid transaction signature", None); }
+            Err(err) => { return self.error(-32000, "device call rejected", Some(format!("{:?}", err))); }
+        };
+        let result = format!("0x{}", hex::encode(hash.as_bytes()));
+        let res = RpcStringResponse::new(self.0.id, &result);
+        return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
+    }
+
+    /// batch 트랜잭션은 pool을 거치지 않고 vm으로 실행하여 바로 DAG에 반영한다.
+    /// 실행은 read lock에서 하고 write lock은 반영할 때만 잡는다.
+    fn receive_batch(&self, ledger: &LedgerHandle, bytes: &[u8]) -> String {
+        let batch_tx = match BatchTransaction::from_bytes(bytes) {
+            Ok(tx) => { tx }
+            Err(_) => { return self.error(-32602, "invalid batch transaction", None); }
+        };
+        for _ in 0..BATCH_EXECUTION_ATTEMPTS {
+            let (outcome, root) = {
+                let state = ledger.read();
+                (batch::execute(&state, &batch_tx, &vm::runtime::BatchCallExecutor), state.state_root())
+            };
+            let outcome = match outcome {
+                Ok(outcome) => { outcome }
+                Err(BatchError::InvalidSignature) => { return self.error(-32602, "invalid transaction signature", None); }
+                Err(BatchError::ChainId(_)) => { return self.error(-32602, "invalid chain id", None); }
+                Err(err) => { return self.error(-32000, "batch transaction rejected", Some(format!("{:?}", err))); }
+            };
+            let mut ledger = ledger.write();
+            // 실행하는 동안 다른 트랜잭션이 반영되었다면 바뀐 상태로 다시 실행한다.
+            if ledger.state_root() != root { continue; }
+            let mut tx = batch_tx.to_transaction();
+            tx.set_parents(&ledger.get_dag().tips());
+            tx.timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+            tx.state_hash = outcome.states.hash();
+            if let Err(err) = ledger.commit_batch(&tx, &outcome) {
+                return self.error(-32000, "batch transaction rejected", Some(format!("{:?}", err)));
+            }
+            let result = format!("0x{}", hex::encode(tx.hash().as_bytes()));
+            let res = RpcStringResponse::new(self.0.id, &result);
+            return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
+        }
+        return self.error(-32000, "batch transaction rejected", Some("state changed during execution".to_string()));
+    }
+
+    pub fn call(&self) -> String {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    /// 상태를 바꾸는 RPC이므로 ProcedureCall과 달리 handle을 받아 필요한 동안만 write lock을 잡는다.
+    pub fn receive(&self, ledger: &LedgerHandle) -> String {
         // 외부에서 받은 Raw Transaction은 Memory Pool로 이동된다.
         // Raw Transaction에는 function-call과 device-call이 존재한다.
-        // device call의 경우 별도의 합의 없이 즉각적으로 업데이트 될 수 있다.
-        let raw_tx = self.0.params.get(0).unwrap().split_at(2).1;
-        let mut result = "0x".to_string();
-        // for element in self.0.params.iter() {
-        //     write!(&mut result, "{}", element);
-        // }
-        result.push_str("0000000000000000");
-        result.push_str("0000000000000000");
-        result.push_str("0000000000000000");
-        result.push_str("0000000000000000");
+        // device call의 경우 device call pool로 이동되며 별도의 합의 없이 즉각적으로 반영된다.
+        // batch 트랜잭션은 실행되어 즉시 DAG에 반영된다.
+        let param = self.0.params.get(0).map_or("", |param| param.as_str());
+        let bytes = match hex::decode(param.trim_start_matches("0x")) {
+            Ok(bytes) => { bytes }
+            Err(_) => { return self.error(-32602, "invalid raw transaction", None); }
+        };
+        if bytes.first() == Some(&DEVICE_CALL_TX_TYPE) { return self.receive_device_call(ledger, bytes.as_slice()); }
+        if bytes.first() == Some(&BATCH_TX_TYPE) { return self.receive_batch(ledger, bytes.as_slice()); }
+        // legacy, EIP-2930, EIP-1559 트랜잭션을 받는다.
+        let tx = match TypedTransaction::from_bytes(bytes.as_slice()) {
+            Ok(tx) => { tx }
+            Err(_) => { return self.error(-32602, "invalid raw transaction", None); }
+        };
+        let sender = match tx.try_get_sender() {
+            Some(sender) => { sender }
+            None => { return self.error(-32602, "invalid transaction signature", None); }
+        };
+        let mut ledger = ledger.write();
+        // 다른 chain을 위해 서명된 트랜잭션은 받지 않는다.
+        if let (Some(chain_id), Some(config)) = (tx.chain_id(), ledger.chain_config()) {
+            if chain_id != config.chain_id { return self.error(-32602, "invalid chain id", None); }
+        }
+        let hash = match ledger.admit_transaction(&sender, tx) {
+            Ok(hash) => { hash }
+            Err(err) => { return self.error(-32000, "transaction rejected by pool", Some(format!("{:?}", err))); }
+        };
+        let result = format!("0x{}", hex::encode(hash.as_bytes()));
         let res = RpcStringResponse::new(self.0.id, &result);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
 }
 
+impl From<RpcStringsRequest> for EthSendRawTransaction {
+    fn from(request: RpcStringsRequest) -> Self {
+        EthSendRawTransaction { 0: request }
+    }
+}
+
 pub struct EthCall(RpcStringsRequest);
 
 impl EthCall {
@@ -706,11 +833,25 @@ impl EthCall {
             Value::from(gas_price),
             Value::from("0x00"),
             Value::from(hex::encode(data.as_slice())),
+            Value::from("latest"),
         ];
         let request =
             RpcStringsRequest::new(&id, RPC_VERSION, method_names::ETH_CALL, &params);
         return EthCall { 0: request };
     }
+
+    fn param(&self, index: usize) -> &str {
+        let value = match self.0.params.get(index) {
+            Some(value) => { value.as_str() }
+            None => { "" }
+        };
+        value.trim_start_matches("0x")
+    }
+
+    fn error(&self, code: i64, message: &str, data: Option<String>) -> String {
+        let res = RpcErrorResponse::new(self.0.id, code, message, data);
+        return serde_json::to_string::<RpcErrorResponse>(&res).unwrap();
+    }
 }
 
 impl From<RpcStringsRequest> for EthCall {
@@ -724,30 +865,161 @@ impl ProcedureCall for EthCall {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        let result = "";
-        let res = RpcStringResponse::new(self.0.id, &result);
-        return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
+    /// params는 call object의 [from, to, gas, gasPrice, value, data]와 block parameter 순서이다.
+    /// 실행 결과는 ledger에 반영되지 않으며 block parameter가 가리키는 시점의 상태에서 실행된다.
+    fn receive(&self, ledger: &Ledger) -> String {
+        let sender = match hex::decode(self.param(0)) {
+            Ok(bytes) if bytes.len() == 20 => { Address::from_slice(bytes.as_slice()) }
+            Ok(bytes) if bytes.is_empty() => { Address::zero() }
+            _ => { return self.error(-32602, "invalid from address", None); }
+        };
+        let receiver = match hex::decode(self.param(1)) {
+            Ok(bytes) if bytes.len() == 20 => { Address::from_slice(bytes.as_slice()) }
+            _ => { return self.error(-32602, "invalid to address", None); }
+        };
+        let gas_limit = match self.param(2) {
+            "" => { vm::gas::GasCap }
+            gas => {
+                match u64::from_str_radix(gas, 16) {
+                    Ok(gas) => { gas }
+                    Err(_) => { return self.error(-32602, "invalid gas", None); }
+                }
+            }
+        };
+        let data = match hex::decode(self.param(5)) {
+            Ok(data) => { data }
+            Err(_) => { return self.error(-32602, "invalid data", None); }
+        };
+        let state_ledger = match ledger_at(ledger, self.0.id, self.0.params.get(6).map_or("", |tag| tag.as_str())) {
+            Ok(state) => { Arc::new(state) }
+            Err(res) => { return res; }
+        };
+
+        // 코드가 없는 account로의 호출은 빈 값을 반환한다.
+        let code = state_ledger.get_accounts().get_account(&receiver).codehash;
+        if code.is_empty() {
+            return serde_json::to_string::<RpcStringResponse>(&RpcStringResponse::new(self.0.id, "0x")).unwrap();
+        }
+        let contract = vm::contract::Contract {
+            code, address: receiver, caller: sender.clone(), input: data, ..Default::default()
+        };
+        let result = vm::runtime::call_contract(state_ledger, &sender, contract, Some(gas_limit));
+        let output = result.output.unwrap_or_default();
+        return match result.error {
+            None => {
+                let res = RpcStringResponse::new(self.0.id, format!("0x{}", hex::encode(output.as_slice())).as_str());
+                serde_json::to_string::<RpcStringResponse>(&res).unwrap()
+            }
+            Some(vm::err::RunError::ExecutionReverted) => {
+                let message = match vm::runtime::unpack_revert_reason(&output) {
+                    Some(reason) => { format!("execution reverted: {}", reason) }
+                    None => { "execution reverted".to_string() }
+                };
+                self.error(3, message.as_str(), Some(format!("0x{}", hex::encode(output.as_slice()))))
+            }
+            Some(err) => { self.error(-32000, format!("execution failed: {:?}", err).as_str(), None) }
+        };
     }
 }
 
-pub struct EthEstimatedGas(RpcEmptyRequest);
+/// 트랜잭션을 실제로 실행하여 필요한 gas를 추정하는 RPC
+/// params는 call object의 [from, to, gas, gasPrice, value, data] 순서이며 to가 공백이면 컨트랙트 배포이다.
+/// gas가 주어지면 이를 상한으로, 아니면 vm::gas::GasCap을 상한으로 이진 탐색한다.
+pub struct EthEstimatedGas(RpcStringsRequest);
 
-impl From<RpcEmptyRequest> for EthEstimatedGas {
-    fn from(request: RpcEmptyRequest) -> Self {
+impl EthEstimatedGas {
+    pub fn new(id: &u64, sender: Address, receiver: Option<Address>, data: Vec<u8>) -> Self {
+        let str_receiver = match receiver {
+            Some(receiver) => { format!("0x{}", hex::encode(receiver.as_bytes())) }
+            None => { "".to_string() }
+        };
+        let params = vec![
+            Value::from(format!("0x{}", hex::encode(sender.as_bytes()))),
+            Value::from(str_receiver),
+            Value::from(""),
+            Value::from(""),
+            Value::from("0x0"),
+            Value::from(format!("0x{}", hex::encode(data.as_slice()))),
+        ];
+        let request =
+            RpcStringsRequest::new(&id, RPC_VERSION, method_names::ETH_ESTIMATE_GAS, &params);
+        return EthEstimatedGas { 0: request };
+    }
+
+    fn param(&self, index: usize) -> &str {
+        let value = match self.0.params.get(index) {
+            Some(value) => { value.as_str() }
+            None => { "" }
+        };
+        value.trim_start_matches("0x")
+    }
+
+    fn error(&self, code: i64, message: &str, data: Option<String>) -> String {
+        let res = RpcErrorResponse::new(self.0.id, code, message, data);
+        return serde_json::to_string::<RpcErrorResponse>(&res).unwrap();
+    }
+}
+
+impl From<RpcStringsRequest> for EthEstimatedGas {
+    fn from(request: RpcStringsRequest) -> Self {
         return EthEstimatedGas { 0: request };
     }
 }
 
 impl ProcedureCall for EthEstimatedGas {
     fn call(&self) -> String {
-        return serde_json::to_string::<RpcEmptyRequest>(&self.0).unwrap();
+        return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
-        let result = "0x5208";
-        let res = RpcStringResponse::new(self.0.id, &result);
-        return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
+    fn receive(&self, ledger: &Ledger) -> String {
+        let sender = match hex::decode(self.param(0)) {
+            Ok(bytes) if bytes.len() == 20 => { Address::from_slice(bytes.as_slice()) }
+            Ok(bytes) if bytes.is_empty() => { Address::zero() }
+            _ => { return self.error(-32602, "invalid from address", None); }
+        };
+        let receiver = match hex::decode(self.param(1)) {
+            Ok(bytes) if bytes.len() == 20 => { Some(Address::from_slice(bytes.as_slice())) }
+            Ok(bytes) if bytes.is_empty() => { None }
+            _ => { return self.error(-32602, "invalid to address", None); }
+        };
+        let gas_cap = match self.param(2) {
+            "" => { vm::gas::GasCap }
+            gas => {
+                match u64::from_str_radix(gas, 16) {
+                    Ok(gas) => { gas }
+                    Err(_) => { return self.error(-32602, "invalid gas", None); }
+                }
+            }
+        };
+        let data = match hex::decode(self.param(5)) {
+            Ok(data) => { data }
+            Err(_) => { return self.error(-32602, "invalid data", None); }
+        };
+
+        let contract = match receiver {
+            None => {
+                vm::contract::Contract { code: data, caller: sender, ..Default::default() }
+            }
+            Some(receiver) => {
+                let code = ledger.get_accounts().get_account(&receiver).codehash;
+                vm::contract::Contract { code, address: receiver, caller: sender, input: data, ..Default::default() }
+            }
+        };
+        // 실행 결과는 ledger에 반영되지 않으며 같은 backend를 읽는 별도의 ledger를 사용한다.
+        let state_ledger = Arc::new(Ledger::with_backend(ledger.get_backend()));
+        let estimated = vm::runtime::estimate_gas(
+            state_ledger, &sender, &contract, receiver.is_none(), gas_cap);
+        return match estimated {
+            Ok(gas) => {
+                let res = RpcStringResponse::new(self.0.id, format!("0x{:x}", gas).as_str());
+                serde_json::to_string::<RpcStringResponse>(&res).unwrap()
+            }
+            Err(err) => {
+                let code = match err.reverted { true => { 3 } false => { -32000 } };
+                let data = err.data.map(|data| format!("0x{}", hex::encode(data.as_slice())));
+                self.error(code, err.message.as_str(), data)
+            }
+        };
     }
 }
 
@@ -764,7 +1036,7 @@ impl ProcedureCall for EthGetBlockByHash {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let result = "0x00";
         let res = RpcStringResponse::new(self.0.id, &result);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
@@ -784,7 +1056,7 @@ impl ProcedureCall for EthGetBlockByNumber {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let res = RpcStringResponse::new(self.0.id, &"".to_string());
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
@@ -803,9 +1075,87 @@ impl ProcedureCall for EthGetTransactionByHash {
         return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
     }
 
-    fn receive(&self, ledger: &mut Ledger) -> String {
+    fn receive(&self, ledger: &Ledger) -> String {
         let result = "";
         let res = RpcStringResponse::new(self.0.id, &result);
         return serde_json::to_string::<RpcStringResponse>(&res).unwrap();
     }
-}
\ No newline at end of file
+}
+
+/// 트랜잭션의 실행 결과(receipt)를 반환하는 RPC
+/// 블록 대신 트랜잭션을 확정한 마일스톤의 height와 hash를 blockNumber, blockHash로 사용한다.
+/// receipt가 없다면 null을 반환한다.
+pub struct EthGetTransactionReceipt(RpcStringsRequest);
+
+impl EthGetTransactionReceipt {
+    pub fn new(id: &u64, tx_hash: &H256) -> Self {
+        let params = vec![Value::from(format!("0x{}", hex::encode(tx_hash.as_bytes())))];
+        let request =
+            RpcStringsRequest::new(
+                &id,
+                RPC_VERSION,
+                method_names::ETH_GET_TX_RECEIPT,
+                &params);
+        return EthGetTransactionReceipt { 0: request };
+    }
+}
+
+impl From<RpcStringsRequest> for EthGetTransactionReceipt {
+    fn from(request: RpcStringsRequest) -> Self {
+        EthGetTransactionReceipt { 0: request }
+    }
+}
+
+impl ProcedureCall for EthGetTransactionReceipt {
+    fn call(&self) -> String {
+        return serde_json::to_string::<RpcStringsRequest>(&self.0).unwrap();
+    }
+
+    fn receive(&self, ledger: &Ledger) -> String {
+        let str_hash = self.0.params.get(0).map_or("", |param| param.trim_start_matches("0x"));
+        let receipt = match hex::decode(str_hash) {
+            Ok(hash) if hash.len() == 32 => { ledger.get_receipt(&H256::from_slice(hash.as_slice())) }
+            _ => { None }
+        };
+        let receipt = match receipt {
+            Some(receipt) => { receipt }
+            None => {
+                let res = RpcObjectResponse::new(self.0.id, Value::Null);
+                return serde_json::to_string::<RpcObjectResponse>(&res).unwrap();
+            }
+        };
+        let hex = |value: &[u8]| format!("0x{}", hex::encode(value));
+        let milestone = receipt.milestone.and_then(|height| ledger.get_milestones().get_milestone(height));
+        // 확정되지 않은 트랜잭션은 마일스톤 안의 위치가 없다.
+        let position = ledger.receipt_position(&receipt.tx_hash);
+        let to = ledger.get_transactions().get_transaction(&receipt.tx_hash)
+            .filter(|_| receipt.contract_address.is_none())
+            .map(|tx| hex(tx.recipient.as_bytes()));
+        let logs: Vec<Value> = receipt.logs.iter().enumerate().map(|(idx, log)| {
+            serde_json::json!({
+                "address": hex(log.address.as_bytes()),
+                "topics": log.topics.iter().map(|topic| hex(topic.as_bytes())).collect::<Vec<String>>(),
+                "data": hex(log.data.as_slice()),
+                "logIndex": format!("0x{:x}", idx),
+                "transactionHash": hex(receipt.tx_hash.as_bytes()),
+                "blockNumber": receipt.milestone.map(|height| format!("0x{:x}", height)),
+            })
+        }).collect();
+        let result = serde_json::json!({
+            "transactionHash": hex(receipt.tx_hash.as_bytes()),
+            "transactionIndex": position.map(|(idx, _)| format!("0x{:x}", idx)),
+            "blockHash": milestone.map(|milestone| hex(milestone.hash().as_bytes())),
+            "blockNumber": receipt.milestone.map(|height| format!("0x{:x}", height)),
+            "from": hex(receipt.from.as_bytes()),
+            "to": to,
+            "status": format!("0x{:x}", receipt.status),
+            "gasUsed": format!("0x{:x}", receipt.gas_used),
+            "cumulativeGasUsed": position.map(|(_, gas)| format!("0x{:x}", gas)),
+            "contractAddress": receipt.contract_address.map(|address| hex(address.as_bytes())),
+            "logs": logs,
+            "logsBloom": hex(receipt.logs_bloom.as_bytes()),
+        });
+        let res = RpcObjectResponse::new(self.0.id, result);
+        return serde_json::to_string::<RpcObjectResponse>(&res).unwrap();
+    }
+}
@@ -60,6 +60,27 @@ impl RpcStringsRequest {
     }
 }
 
+impl RpcStringsRequest {
+    /// eth_call, eth_estimateGas의 call object를 [from, to, gas, gasPrice, value, data] 순서의 params로 변환한다.
+    /// 없는 필드는 공백 문자열이 된다.
+    pub fn from_call_object(id: &u64, jsonrpc: &str, method: &str, call: &Value) -> Self {
+        let mut params: Vec<String> = vec![];
+        for field in ["from", "to", "gas", "gasPrice", "value", "data"].iter() {
+            let value = match call.get(*field) {
+                Some(value) => { value.as_str().unwrap_or("").to_string() }
+                None => { "".to_string() }
+            };
+            params.push(value);
+        }
+        return RpcStringsRequest {
+            id: id.clone(),
+            jsonrpc: jsonrpc.to_string(),
+            method: method.to_string(),
+            params
+        };
+    }
+}
+
 /// RPC 요청 메시지이며 params 값의 타입이 Integer인 메시지
 #[derive(Serialize, Deserialize)]
 pub struct RpcIntegersRequest {
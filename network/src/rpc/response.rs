@@ -85,4 +85,50 @@ impl RpcMapResponse {
             result: str_str_result,
         }
     }
-}
\ No newline at end of file
+}
+
+/// result가 JSON object 또는 null인 응답 (receipt 등)
+#[derive(Serialize, Deserialize)]
+pub struct RpcObjectResponse {
+    pub id: u64,
+    pub jsonrpc: String,
+    pub result: serde_json::Value,
+}
+
+impl RpcObjectResponse {
+    pub fn new(id: u64, result: serde_json::Value) -> Self {
+        RpcObjectResponse {
+            id,
+            jsonrpc: RPC_VERSION.to_string(),
+            result,
+        }
+    }
+}
+
+/// 요청을 처리하지 못했을 때 result 대신 전달되는 오류
+/// * `code` - JSON-RPC 오류 코드이며 revert된 경우 3을 갖는다.
+/// * `data` - revert된 경우 컨트랙트가 반환한 값
+#[derive(Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RpcErrorResponse {
+    pub id: u64,
+    pub jsonrpc: String,
+    pub error: RpcError,
+}
+
+impl RpcErrorResponse {
+    pub fn new(id: u64, code: i64, message: &str, data: Option<String>) -> Self {
+        RpcErrorResponse {
+            id,
+            jsonrpc: RPC_VERSION.to_string(),
+            error: RpcError { code, message: message.to_string(), data },
+        }
+    }
+}
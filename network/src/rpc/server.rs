@@ -1,6 +1,7 @@
 use std::net::{SocketAddr, Ipv4Addr, SocketAddrV4};
 use std::str::FromStr;
-use basic_http::server::{HttpServer, Handler};
+use basic_http::server::HttpServer;
+use ledger::handle::LedgerHandle;
 use std::collections::HashMap;
 use crate::rpc::handler::rpc_handler;
 
@@ -9,9 +10,10 @@ pub struct RpcServer {
 }
 
 impl RpcServer {
-    pub fn new(ip: &str, port: u16, ) -> Self {
+    /// 모든 요청은 같은 ledger를 사용하므로 pool의 트랜잭션이 요청 사이에 유지된다.
+    pub fn new(ip: &str, port: u16, ledger: LedgerHandle) -> Self {
         let mut server = HttpServer::new(ip, port, None);
-        server.append_handler("/", rpc_handler);
+        server.append_handler("/", move |request, response| rpc_handler(&ledger, request, response));
         // let ip = Ipv4Addr::from_str(ip).unwrap();
         // let ipv4 = SocketAddrV4::new(ip, port);
         // let socket = SocketAddr::from(ipv4);
@@ -0,0 +1,54 @@
+use common::datadir::DataDir;
+use ethereum_types::Address;
+use ledger::fsck::{self, CheckOptions, Replay};
+use ledger::ledger::Ledger;
+use vm::runtime::TransactionReplay;
+
+const USAGE: &str = "usage: fsck [--quarantine] [--no-replay] <data directory> [authority address (hex)]...\n\
+    exit codes: 0 clean, 1 all problems quarantined, 4 problems remain, 8 operational error";
+
+/// 검사를 시작하지 못한 경우의 종료 코드
+const OPERATIONAL_ERROR: i32 = 8;
+
+fn exit(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(OPERATIONAL_ERROR);
+}
+
+fn main() {
+    let mut quarantine = false;
+    let mut replay = true;
+    let mut positional = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--quarantine" => { quarantine = true; }
+            "--no-replay" => { replay = false; }
+            _ => { positional.push(arg); }
+        }
+    }
+    if positional.is_empty() { exit(USAGE.to_string()); }
+
+    let data_dir = DataDir::new(positional[0].as_str());
+    if !Ledger::database_file(&data_dir).exists() {
+        exit(format!("no database in {}", data_dir.path().display()));
+    }
+    let ledger = match Ledger::open(&data_dir) {
+        Ok(ledger) => { ledger }
+        Err(err) => { exit(format!("cannot open {}: {:?}", data_dir.path().display(), err)) }
+    };
+    let authorities: Vec<Address> = positional[1..].iter().map(|arg| {
+        match hex::decode(arg.trim_start_matches("0x")) {
+            Ok(bytes) if bytes.len() == 20 => { Address::from_slice(bytes.as_slice()) }
+            _ => { exit(format!("invalid address: {}", arg)) }
+        }
+    }).collect();
+
+    let options = CheckOptions { authorities, quarantine };
+    let replayer = TransactionReplay;
+    let report = fsck::check(&ledger, &options, match replay {
+        true => { Some(&replayer as &dyn Replay) }
+        false => { None }
+    });
+    println!("{}", report.to_json());
+    std::process::exit(report.exit_code());
+}
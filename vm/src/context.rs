@@ -1,10 +1,14 @@
 use crate::stack::{Stack, ReturnStack};
 use crate::memory::Memory;
 use crate::contract::Contract;
+use crate::state::StateDb;
+use ethereum_types::Address;
 
 pub struct CallContext {
     pub stack: Stack,
     pub memory: Memory,
     pub rstack: ReturnStack,
-    pub contract: Contract
+    pub contract: Contract,
+    pub destructs: Vec<Address>,    // SELFDESTRUCT로 제거 예약된 컨트랙트 주소
+    pub state: StateDb,
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use ethereum_types::{Address, U256, H256};
 use std::collections::HashMap;
 use crate::opcode::{opcode_to_u8, JUMPDEST};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Contract {
     pub code: Vec<u8>,
     pub codehash: H256,
@@ -0,0 +1,55 @@
+use crate::opcode::{get_opcode_name, PUSH1, PUSH32};
+
+/// 바이트코드를 디스어셈블한 결과의 한 줄
+/// PUSHN 명령어의 경우 뒤따르는 데이터 영역을 함께 갖는다.
+pub struct DisasmLine {
+    pub pc: u64,
+    pub opcode: u8,
+    pub push_data: Vec<u8>,
+}
+
+impl DisasmLine {
+    pub fn name(&self) -> &str {
+        get_opcode_name(&self.opcode)
+    }
+}
+
+impl ToString for DisasmLine {
+    fn to_string(&self) -> String {
+        if self.push_data.is_empty() {
+            return format!("{:05x}: {}", self.pc, self.name());
+        }
+        format!("{:05x}: {} 0x{}", self.pc, self.name(), hex::encode(&self.push_data))
+    }
+}
+
+/// 바이트코드를 명령어 단위로 분리한다.
+/// 반환되는 vector의 index + 1이 coverage 리포트의 line number가 된다.
+pub fn disassemble(code: &Vec<u8>) -> Vec<DisasmLine> {
+    let mut lines = Vec::<DisasmLine>::new();
+    let mut pc = 0usize;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let mut push_data = vec![];
+        let mut size = 1usize;
+        if opcode >= PUSH1 && opcode <= PUSH32 {
+            let n = (opcode - PUSH1 + 1) as usize;
+            let end = std::cmp::min(pc + 1 + n, code.len());
+            push_data = code[pc + 1..end].to_vec();
+            size += n;
+        }
+        lines.push(DisasmLine { pc: pc as u64, opcode, push_data });
+        pc += size;
+    }
+    return lines;
+}
+
+/// 디스어셈블 결과를 사람이 읽을 수 있는 텍스트로 변환한다.
+pub fn to_listing(code: &Vec<u8>) -> String {
+    let mut listing = String::new();
+    for line in disassemble(code).iter() {
+        listing.push_str(line.to_string().as_str());
+        listing.push('\n');
+    }
+    listing
+}
@@ -1,7 +1,9 @@
-#[derive(Eq, PartialEq)]
+#[derive(Eq, PartialEq, Debug)]
 pub enum RunError {
     NoError, InvalidJump, ExecutionReverted,
     InvalidOpCode, StackUnderflow, StackOverflow,
     WriteProtection,
-    ReturnDataOutOfBounds
+    ReturnDataOutOfBounds,
+    InvalidCode, WasmTrap,
+    OutOfGas
 }
\ No newline at end of file
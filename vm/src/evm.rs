@@ -23,6 +23,8 @@ impl VirtualMachine {
 }
 
 impl VirtualMachine {
+    pub fn get_ledger(&self) -> &Ledger { &self.ledger }
+
     // pub fn set_contract(&mut self, _contract: Contract) {
     //     self.contract = _contract;
     // }
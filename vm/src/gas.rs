@@ -0,0 +1,126 @@
+use ethereum_types::{H256, U256};
+use crate::opcode::*;
+use crate::stack::Stack;
+
+/// go-ethereum(params/protocol_params.go, core/vm/gas.go)의 Istanbul 기준 gas 비용
+pub const GasQuickStep: u64 = 2;
+pub const GasFastestStep: u64 = 3;
+pub const GasFastStep: u64 = 5;
+pub const GasMidStep: u64 = 8;
+pub const GasSlowStep: u64 = 10;
+pub const GasExtStep: u64 = 20;
+
+pub const TxGas: u64 = 21000;
+pub const TxGasContractCreation: u64 = 53000;
+pub const TxDataZeroGas: u64 = 4;
+pub const TxDataNonZeroGas: u64 = 16;
+
+pub const MemoryGas: u64 = 3;
+pub const QuadCoeffDiv: u64 = 512;
+pub const CopyGas: u64 = 3;
+pub const Sha3Gas: u64 = 30;
+pub const Sha3WordGas: u64 = 6;
+pub const ExpGas: u64 = 10;
+pub const ExpByteGas: u64 = 50;
+pub const LogGas: u64 = 375;
+pub const LogTopicGas: u64 = 375;
+pub const LogDataGas: u64 = 8;
+pub const SloadGas: u64 = 800;
+pub const SstoreSetGas: u64 = 20000;
+pub const SstoreResetGas: u64 = 5000;
+pub const JumpdestGas: u64 = 1;
+pub const SelfdestructGas: u64 = 5000;
+/// wasm 컨트랙트의 명령어 하나의 비용
+pub const WasmStepGas: u64 = 1;
+
+/// 블록 gas limit이 없으므로 eth_estimateGas 등에서 사용하는 기본 상한
+pub const GasCap: u64 = 30_000_000;
+
+/// 명령어 실행 전 항상 차감되는 고정 비용
+pub fn constant_gas(op: OpCode) -> u64 {
+    match op {
+        STOP | RETURN | REVERT => { 0 }
+        JUMPDEST => { JumpdestGas }
+        ADDRESS | ORIGIN | CALLER | CALLVALUE | CALLDATASIZE | CODESIZE | GASPRICE
+        | COINBASE | TIMESTAMP | NUMBER | DIFFICULTY | GASLIMIT | RETURNDATASIZE
+        | POP | PC | MSIZE | GAS | CHAINID => { GasQuickStep }
+        ADD | SUB | LT | GT | SLT | SGT | EQ | ISZERO | AND | OR | XOR | NOT | BYTE
+        | SHL | SHR | SAR | CALLDATALOAD | MLOAD | MSTORE | MSTORE8
+        | CALLDATACOPY | CODECOPY | RETURNDATACOPY => { GasFastestStep }
+        MUL | DIV | SDIV | MOD | SMOD | SELFBALANCE => { GasFastStep }
+        ADDMOD | MULMOD | JUMP => { GasMidStep }
+        JUMPI => { GasSlowStep }
+        EXP => { ExpGas }
+        BLOCKHASH => { GasExtStep }
+        SHA3 => { Sha3Gas }
+        SLOAD => { SloadGas }
+        SELFDESTRUCT => { SelfdestructGas }
+        LOG0 | LOG1 | LOG2 | LOG3 | LOG4 => { LogGas }
+        // SSTORE는 기존 값에 따라 dynamic gas로만 계산된다.
+        SSTORE => { 0 }
+        _ => {
+            if op >= PUSH1 && op <= SWAP16 { return GasFastestStep; }
+            0
+        }
+    }
+}
+
+/// 메모리를 current bytes에서 new bytes로 늘릴 때 추가되는 비용
+pub fn memory_gas(current: u64, new: u64) -> u64 {
+    if new <= current { return 0; }
+    let cost = |size: u64| -> u64 {
+        let words = (size as u128 + 31) / 32;
+        let fee = words * MemoryGas as u128 + words * words / QuadCoeffDiv as u128;
+        if fee > u64::max_value() as u128 { return u64::max_value(); }
+        fee as u64
+    };
+    cost(new).saturating_sub(cost(current))
+}
+
+fn words(size: &U256) -> u64 {
+    if *size > U256::from(u64::max_value() - 31) { return u64::max_value() / 32 + 1; }
+    (size.as_u64() + 31) / 32
+}
+
+/// stack에 놓인 인자에 따라 달라지는 비용 (메모리 확장 비용과 SSTORE는 제외)
+pub fn dynamic_gas(op: OpCode, stack: &Stack) -> u64 {
+    match op {
+        SHA3 => { words(&stack.back(1).get()).saturating_mul(Sha3WordGas) }
+        CALLDATACOPY | CODECOPY | RETURNDATACOPY => {
+            words(&stack.back(2).get()).saturating_mul(CopyGas)
+        }
+        EXP => {
+            let exponent = stack.back(1).get();
+            let bytes = (exponent.bits() as u64 + 7) / 8;
+            bytes * ExpByteGas
+        }
+        LOG0 | LOG1 | LOG2 | LOG3 | LOG4 => {
+            let size = stack.back(1).get();
+            if size > U256::from(u64::max_value()) { return u64::max_value(); }
+            let topics = (op - LOG0) as u64;
+            (topics * LogTopicGas).saturating_add(size.as_u64().saturating_mul(LogDataGas))
+        }
+        _ => { 0 }
+    }
+}
+
+/// storage 값을 변경하는 비용. 0에서 0이 아닌 값으로 바꿀 때 가장 비싸다.
+pub fn sstore_gas(current: &H256, value: &H256) -> u64 {
+    if current.is_zero() && !value.is_zero() { return SstoreSetGas; }
+    SstoreResetGas
+}
+
+/// 트랜잭션 실행 전 차감되는 기본 비용
+pub fn intrinsic_gas(data: &Vec<u8>, contract_creation: bool) -> u64 {
+    let mut gas = match contract_creation {
+        true => { TxGasContractCreation }
+        false => { TxGas }
+    };
+    for byte in data.iter() {
+        gas += match *byte {
+            0 => { TxDataZeroGas }
+            _ => { TxDataNonZeroGas }
+        };
+    }
+    gas
+}
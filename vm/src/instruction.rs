@@ -8,6 +8,7 @@ use std::cell::{Cell, RefCell};
 use crate::memory::get_data;
 use crate::jumptable::ExecuteFn;
 use crate::evm::VirtualMachine;
+use ledger::log::Log;
 
 /// 27:: 0x01
 pub fn op_add(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
@@ -397,6 +398,31 @@ pub fn op_coinbase(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &In
     (None, None)
 }
 
+/// 515::
+pub fn op_sload(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
+                -> (Option<Vec<u8>>, Option<RunError>) {
+    let address = _call_context.contract.address.clone();
+    let loc = _call_context.stack.pop().unwrap();
+    let key = H256::from_uint(&loc.get());
+    let value = _call_context.state.get_storage(_evm.get_ledger(), &address, &key);
+    _call_context.stack.push(&value.into_uint());
+    (None, None)
+}
+
+/// 523::
+pub fn op_sstore(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
+                 -> (Option<Vec<u8>>, Option<RunError>) {
+    let address = _call_context.contract.address.clone();
+    let loc = _call_context.stack.pop().unwrap();
+    let val = _call_context.stack.pop().unwrap();
+    _call_context.state.set_storage(
+        &address,
+        &H256::from_uint(&loc.get()),
+        &H256::from_uint(&val.get()),
+    );
+    (None, None)
+}
+
 /// 488::
 pub fn op_mload(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
                 -> (Option<Vec<u8>>, Option<RunError>) {
@@ -535,6 +561,18 @@ pub fn op_stop(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interp
     (None, None)
 }
 
+/// 822::
+/// 컨트랙트 제거를 예약한다. 실제 제거는 실행 결과가 반영될 때 storage 변경과 함께 수행된다.
+pub fn op_selfdestruct(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
+                       -> (Option<Vec<u8>>, Option<RunError>) {
+    let _beneficiary = _call_context.stack.pop().unwrap();    // Biiot은 balance를 사용하지 않는다.
+    let address = _call_context.contract.address.clone();
+    if !_call_context.destructs.contains(&address) {
+        _call_context.destructs.push(address);
+    }
+    (None, None)
+}
+
 // fn _make_log(_pc: &mut Cell<u64>, _evm: &VirtualMachine, _interpreter: &Interpreter, _call_context: &mut CallContext)
 //              -> (Option<Vec<u8>>, Option<RunError>) {
 //     _call_context.stack.swap(_size as i16);
@@ -574,6 +612,9 @@ fn make_log(_pc: &mut Cell<u64>, _interpreter: &Interpreter, _call_context: &mut
         min_size.get().as_u64() as i64,
     );
 
+    let address = _call_context.contract.address.clone();
+    _call_context.state.add_log(Log::new(&address, topics, d.clone().unwrap_or_default()));
+
     println!("Event data check");
     for element in d.iter() {
         println!("Element");
@@ -14,12 +14,24 @@ use std::thread::sleep;
 use std::time::Duration;
 use crate::evm::VirtualMachine;
 use std::thread;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use crate::profiler::Profiler;
+use ledger::ledger::Ledger;
+use crate::state::StateDb;
+use crate::gas::{constant_gas, dynamic_gas, memory_gas, sstore_gas};
+use ethereum_types::{BigEndianHash, H256};
 
 pub struct Interpreter {
     pub origin: Address,
     pub return_data: Vec<u8>,
-    pub return_with_err: u8
+    pub return_with_err: u8,
+    pub debug: bool,    // step마다 대기하며 stack과 opcode를 출력한다.
+    pub profiler: Option<Arc<Mutex<Profiler>>>,
+    pub destructs: Vec<Address>,    // 트랜잭션 종료 시 제거될 컨트랙트 주소
+    pub ledger: Option<Arc<Ledger>>,    // storage를 읽어올 ledger. None일 경우 실행마다 새로 연다.
+    pub state: StateDb,                 // 성공적으로 끝난 실행들의 storage 변경과 log
+    pub gas_limit: Option<u64>,         // None일 경우 gas를 제한하지 않고 step 수로만 실행을 제한한다.
+    pub gas_used: u64,                  // 마지막 실행에서 사용한 gas
 }
 
 impl Interpreter {
@@ -27,10 +39,25 @@ impl Interpreter {
         let mut intp = Interpreter{
             origin: _origin,
             return_data: Vec::new(),
-            return_with_err: 0
+            return_with_err: 0,
+            debug: true,
+            profiler: None,
+            destructs: Vec::new(),
+            ledger: None,
+            state: StateDb::new(),
+            gas_limit: None,
+            gas_used: 0,
         };
         return intp;
     }
+
+    /// 실행 결과를 주어진 profiler에 누적한다. profiling 중에는 debug 출력을 하지 않는다.
+    pub fn with_profiler(_origin: Address, profiler: Arc<Mutex<Profiler>>) -> Self {
+        let mut intp = Interpreter::new(_origin);
+        intp.debug = false;
+        intp.profiler = Some(profiler);
+        return intp;
+    }
 }
 
 impl Interpreter {
@@ -41,25 +68,46 @@ impl Interpreter {
         let mut rstack = ReturnStack::new();
         let mut call_context = CallContext{
             stack, memory, rstack,
-            contract: _contract.take()
+            contract: _contract.take(),
+            destructs: Vec::new(),
+            state: self.state.clone(),  // revert 시 버려지도록 사본에서 실행한다.
         };
         let mut pc = Cell::new(0u64);
         call_context.contract.input = _input;
+        let codehash = match &self.profiler {
+            None => { None }
+            Some(profiler) => {
+                Some(profiler.lock().unwrap().begin_execution(&call_context.contract.code))
+            }
+        };
+
+        let ledger = match &self.ledger {
+            None => { Arc::new(Ledger::in_memory()) }
+            Some(ledger) => { ledger.clone() }
+        };
+        let tmp_evm = VirtualMachine::new(ledger);
 
+        self.gas_used = 0;
         let mut steps = 0;
         loop {
-            sleep(Duration::from_millis(100));
+            if self.debug { sleep(Duration::from_millis(100)); }
             steps += 1;
-            if steps % 1000 == 0 { break; }
-            print!("step: {}, ", steps);
-            println!("pc: {}", pc.get());
+            if self.gas_limit.is_none() && steps % 1000 == 0 { break; }
+            if self.debug {
+                print!("step: {}, ", steps);
+                println!("pc: {}", pc.get());
+            }
 
             // Get the operation from the jump-table and validate the stack to ensure
             // there are enough stack items available to perform the operation.
             op = call_context.contract.get_byte(pc.get());
             let str_op = get_opcode_name(&op);
-            println!("find opcode:{:x}({})", op, str_op);
-            let operation = get_operation(op).unwrap();
+            if self.debug { println!("find opcode:{:x}({})", op, str_op); }
+            let op_pc = pc.get();
+            let operation = match get_operation(op) {
+                None => { return (None, Some(RunError::InvalidOpCode)); }
+                Some(operation) => { operation }
+            };
 
             // validate stack (not implemented)
 
@@ -73,14 +121,34 @@ impl Interpreter {
                 memory_size = memorysize_n_overflow.0;
             }
 
+            // consume gas (constant + dynamic + memory expansion)
+            let mut cost = constant_gas(op).saturating_add(dynamic_gas(op, &call_context.stack));
+            cost = cost.saturating_add(memory_gas(call_context.memory.size() as u64, memory_size));
+            if op == crate::opcode::SSTORE {
+                let address = call_context.contract.address.clone();
+                let key = H256::from_uint(&call_context.stack.back(0).get());
+                let value = H256::from_uint(&call_context.stack.back(1).get());
+                let current = call_context.state.get_storage(tmp_evm.get_ledger(), &address, &key);
+                cost = cost.saturating_add(sstore_gas(&current, &value));
+            }
+            self.gas_used = self.gas_used.saturating_add(cost);
+            if self.gas_limit.map_or(false, |limit| self.gas_used > limit) {
+                if self.debug { println!("error:: out of gas"); }
+                return (None, Some(RunError::OutOfGas));
+            }
+
             if memory_size > 0 { call_context.memory.resize(memory_size); }
 
             // execute the operation
             let exec_fn = operation.execute;
-            let ledger = Arc::new(ledger::ledger::Ledger::new());
-            let tmp_evm = VirtualMachine::new(ledger);
+            let step_begin = common::timeutil::measure_begin();
             let (res, err) = exec_fn(&mut pc, &tmp_evm, self, &mut call_context);
-            call_context.stack.print_stack();
+            if codehash.is_some() {
+                let elapsed = common::timeutil::measure_end(&step_begin);
+                self.profiler.as_ref().unwrap().lock().unwrap()
+                    .record_step(codehash.as_ref().unwrap(), op_pc, op, elapsed);
+            }
+            if self.debug { call_context.stack.print_stack(); }
 
             // thread::sleep(Duration::from_millis(50));
 
@@ -92,11 +160,11 @@ impl Interpreter {
             }
 
             if err != None::<RunError> {
-                println!("error:: contract stop");
+                if self.debug { println!("error:: contract stop"); }
                 return (None, err);
             }
             else if operation.reverts {
-                println!("operation:: revert");
+                if self.debug { println!("operation:: revert"); }
                 if res.is_some() {
                     return (Some(res.unwrap()), Some(ExecutionReverted));
                 } else {
@@ -104,15 +172,19 @@ impl Interpreter {
                 }
             }
             else if operation.halts {
-                println!("operation:: halts");
-                if !res.is_none() {
+                if self.debug { println!("operation:: halts"); }
+                if self.debug && !res.is_none() {
                     println!("::return value::");
                     common::printutil::print_u8vec(&res.clone().unwrap());
                 }
+                self.state = std::mem::take(&mut call_context.state);
+                for address in call_context.destructs.drain(..) {
+                    if !self.destructs.contains(&address) { self.destructs.push(address); }
+                }
                 return (res, None);
             }
             else if !operation.jumps {
-                println!("update program counter:: +1");
+                if self.debug { println!("update program counter:: +1"); }
                 pc.get_mut().add_assign(1);
             }
         }
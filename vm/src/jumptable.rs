@@ -1,4 +1,4 @@
-use crate::instruction::{op_add, op_sub, op_mul, op_div, op_mod, op_exp, op_not, op_lt, op_eq, op_iszero, op_and, op_sha3, op_address, op_callvalue, op_calldataload, op_calldatasize, op_codecopy, op_pop, op_coinbase, op_mload, op_mstore, op_jump, op_jumpi, op_jumpdest, op_msize, op_return, op_revert, op_stop, op_push1, make_log0, make_log1, make_log2, make_log3, make_log4, make_push2, make_push3, make_push4, make_push32, make_push31, make_push30, make_push29, make_push28, make_push27, make_push26, make_push25, make_push24, make_push23, make_push22, make_push21, make_push20, make_push19, make_push18, make_push17, make_push16, make_push15, make_push14, make_push13, make_push12, make_push11, make_push10, make_push9, make_push8, make_push7, make_push6, make_push5, make_dup1, make_dup2, make_dup3, make_dup4, make_dup5, make_dup6, make_dup7, make_dup8, make_dup9, make_dup10, make_dup11, make_dup12, make_dup13, make_dup14, make_dup15, make_dup16, make_swap16, make_swap15, make_swap14, make_swap13, make_swap12, make_swap11, make_swap10, make_swap9, make_swap8, make_swap7, make_swap6, make_swap5, make_swap4, make_swap3, make_swap2, make_swap1, op_calldatacopy};
+use crate::instruction::{op_add, op_sub, op_mul, op_div, op_mod, op_exp, op_not, op_lt, op_eq, op_iszero, op_and, op_sha3, op_address, op_callvalue, op_calldataload, op_calldatasize, op_codecopy, op_pop, op_coinbase, op_sload, op_sstore, op_mload, op_mstore, op_jump, op_jumpi, op_jumpdest, op_msize, op_return, op_revert, op_stop, op_selfdestruct, op_push1, make_log0, make_log1, make_log2, make_log3, make_log4, make_push2, make_push3, make_push4, make_push32, make_push31, make_push30, make_push29, make_push28, make_push27, make_push26, make_push25, make_push24, make_push23, make_push22, make_push21, make_push20, make_push19, make_push18, make_push17, make_push16, make_push15, make_push14, make_push13, make_push12, make_push11, make_push10, make_push9, make_push8, make_push7, make_push6, make_push5, make_dup1, make_dup2, make_dup3, make_dup4, make_dup5, make_dup6, make_dup7, make_dup8, make_dup9, make_dup10, make_dup11, make_dup12, make_dup13, make_dup14, make_dup15, make_dup16, make_swap16, make_swap15, make_swap14, make_swap13, make_swap12, make_swap11, make_swap10, make_swap9, make_swap8, make_swap7, make_swap6, make_swap5, make_swap4, make_swap3, make_swap2, make_swap1, op_calldatacopy};
 use crate::err::RunError;
 use std::cell::Cell;
 use crate::interpreter::Interpreter;
@@ -285,6 +285,30 @@ pub fn get_instruction_set() -> HashMap<u8, Operation> {
              reverts: false,
              returns: false,
          }),
+        (crate::opcode::SLOAD, // 0x54
+         Operation {
+             execute: op_sload,
+             memory_size: None,
+             min_stack: crate::stack::min_stack(&1i16, &1i16),
+             max_stack: crate::stack::max_stack(&1i16, &1i16),
+             halts: false,
+             jumps: false,
+             writes: false,
+             reverts: false,
+             returns: false,
+         }),
+        (crate::opcode::SSTORE, // 0x55
+         Operation {
+             execute: op_sstore,
+             memory_size: None,
+             min_stack: crate::stack::min_stack(&2i16, &0i16),
+             max_stack: crate::stack::max_stack(&2i16, &0i16),
+             halts: false,
+             jumps: false,
+             writes: true,
+             reverts: false,
+             returns: false,
+         }),
         (crate::opcode::MLOAD, // 0x51
          Operation {
              execute: op_mload,
@@ -393,6 +417,18 @@ pub fn get_instruction_set() -> HashMap<u8, Operation> {
              reverts: false,
              returns: false,
          }),
+        (crate::opcode::SELFDESTRUCT, // 0xFF
+         Operation {
+             execute: op_selfdestruct,
+             memory_size: None,
+             min_stack: crate::stack::min_stack(&1i16, &0i16),
+             max_stack: crate::stack::max_stack(&1i16, &0i16),
+             halts: true,
+             jumps: false,
+             writes: true,
+             reverts: false,
+             returns: false,
+         }),
         (crate::opcode::LOG0, // 0xA0
          Operation {
              execute: make_log0,
@@ -1230,7 +1266,5 @@ pub fn get_instruction_set() -> HashMap<u8, Operation> {
 pub fn get_operation(_v: u8) -> Option<Operation> {
     let opc = _v as OpCode;
     let instset = get_instruction_set();
-    let a = instset.get(&opc)
-        .expect("invalid opcode");
-    Some(a.clone())
+    instset.get(&opc).cloned()
 }
\ No newline at end of file
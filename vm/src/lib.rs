@@ -1,19 +1,282 @@
 pub mod constants;
 pub mod context;
 pub mod contract;
+pub mod disasm;
 pub mod err;
 pub mod evm;
+pub mod gas;
 pub mod instruction;
 pub mod interpreter;
 pub mod jumptable;
 pub mod memory;
 pub mod opcode;
+pub mod profiler;
+pub mod runtime;
 pub mod stack;
+pub mod state;
+pub mod wasm;
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::sync::{Arc, Mutex};
+    use ethereum_types::Address;
+    use crate::contract::Contract;
+    use crate::interpreter::Interpreter;
+    use crate::profiler::Profiler;
+    use crate::runtime::{call_contract, deploy_contract, estimate_gas, ContractRuntime};
+    use ethereum_types::H256;
+    use ledger::ledger::Ledger;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn profile_contract_executions() {
+        // PUSH1 0x01, PUSH1 0x02, ADD, STOP, PUSH1 0x03 (unreachable)
+        let code = vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00, 0x60, 0x03];
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        for _ in 0..3 {
+            let mut interpreter = Interpreter::with_profiler(Address::zero(), profiler.clone());
+            let contract = RefCell::new(Contract { code: code.clone(), ..Default::default() });
+            interpreter.run_contract(&contract, vec![]);
+        }
+
+        let profiler = profiler.lock().unwrap();
+        let profile = profiler.get_profile(&Profiler::codehash(&code)).unwrap();
+        assert_eq!(profile.executions, 3);
+        assert_eq!(profile.steps, 12);
+        assert_eq!(profile.pc_hits.get(&4), Some(&3));
+        assert_eq!(profile.opcode_counts.get(&crate::opcode::PUSH1), Some(&6));
+        assert_eq!(profile.coverage(), (4, 5));
+        assert!(profiler.to_lcov().contains("DA:5,0\nLH:4\nLF:5\n"));
+        assert_eq!(profiler.to_json()["contracts"][0]["executions"], 3);
+    }
+
+    #[test]
+    fn run_wasm_contract() {
+        // 호출될 때마다 storage 0번 값을 calldata 첫 byte만큼 증가시키고 log를 남기는 컨트랙트
+        let code = wat::parse_str(r#"
+            (module
+                (import "env" "storage_get" (func $get (param i32 i32)))
+                (import "env" "storage_set" (func $set (param i32 i32)))
+                (import "env" "calldata_copy" (func $calldata_copy (param i32 i32 i32)))
+                (import "env" "log" (func $log (param i32 i32 i32 i32)))
+                (import "env" "return_data" (func $return (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "call")
+                    (call $get (i32.const 0) (i32.const 32))
+                    (call $calldata_copy (i32.const 64) (i32.const 0) (i32.const 1))
+                    (i32.store8 (i32.const 63)
+                        (i32.add (i32.load8_u (i32.const 63)) (i32.load8_u (i32.const 64))))
+                    (call $set (i32.const 0) (i32.const 32))
+                    (call $log (i32.const 0) (i32.const 1) (i32.const 32) (i32.const 32))
+                    (call $return (i32.const 32) (i32.const 32))))
+        "#).unwrap();
+        assert_eq!(ContractRuntime::from_code(&code), ContractRuntime::Wasm);
+
+        let ledger = Arc::new(Ledger::in_memory());
+        let address = Address::from_low_u64_be(0x28);
+        let contract = Contract { code: code.clone(), address, ..Default::default() };
+        let deployed = deploy_contract(ledger.clone(), &Address::zero(), contract, None);
+        assert!(deployed.error.is_none());
+        assert_eq!(deployed.output, Some(code.clone()));
+
+        let contract = Contract { code, address, input: vec![0x05], ..Default::default() };
+        let result = call_contract(ledger.clone(), &Address::zero(), contract, None);
+        assert!(result.error.is_none());
+        let expected = H256::from_low_u64_be(5);
+        assert_eq!(result.output, Some(expected.as_bytes().to_vec()));
+        assert_eq!(result.state.get_storage(&ledger, &address, &H256::zero()), expected);
+        assert_eq!(result.state.logs.len(), 1);
+        assert_eq!(result.state.logs[0].data, expected.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn estimate_gas_by_execution() {
+        let ledger = Arc::new(Ledger::in_memory());
+        let address = Address::from_low_u64_be(0x29);
+
+        // PUSH1 0x01, PUSH1 0x00, SSTORE, STOP
+        let code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+        let contract = Contract { code, address, ..Default::default() };
+        let gas = estimate_gas(ledger.clone(), &Address::zero(), &contract, false, 100_000).ok().unwrap();
+        assert_eq!(gas, 21000 + 3 + 3 + 20000);
+        let result = call_contract(ledger.clone(), &Address::zero(), contract.clone(), Some(gas - 21000 - 1));
+        assert!(result.error == Some(crate::err::RunError::OutOfGas));
+        let err = estimate_gas(ledger.clone(), &Address::zero(), &contract, false, 30000).err().unwrap();
+        assert_eq!(err.message, "gas required exceeds allowance (30000)");
+
+        // 항상 Error("nope")으로 revert되는 wasm 컨트랙트
+        let mut reason = vec![0x08, 0xc3, 0x79, 0xa0];
+        reason.extend_from_slice(H256::from_low_u64_be(0x20).as_bytes());
+        reason.extend_from_slice(H256::from_low_u64_be(4).as_bytes());
+        reason.extend_from_slice(b"nope");
+        reason.extend_from_slice(&[0u8; 28]);
+        let escaped: String = reason.iter().map(|b| format!("\\{:02x}", b)).collect();
+        let code = wat::parse_str(format!(r#"
+            (module
+                (import "env" "revert" (func $revert (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{}")
+                (func (export "call") (call $revert (i32.const 0) (i32.const {}))))
+        "#, escaped, reason.len())).unwrap();
+        let contract = Contract { code, address, ..Default::default() };
+        let err = estimate_gas(ledger, &Address::zero(), &contract, false, 100_000).err().unwrap();
+        assert!(err.reverted);
+        assert_eq!(err.message, "execution reverted: nope");
+        assert_eq!(err.data, Some(reason));
+    }
+
+    #[test]
+    fn selfdestruct_removes_account() {
+        use ethereum_types::U256;
+        use ledger::account::AccountNode;
+        use ledger::ledger::CommitError;
+        use ledger::transaction::{RawTransaction, Transaction};
+        use crate::runtime::commit_execution;
+        let mut ledger = Ledger::in_memory();
+        // 실행은 같은 backend를 읽는 ledger로 하고 반영은 ledger에 한다.
+        let view = Arc::new(Ledger::with_backend(ledger.get_backend()));
+        let sk = crypto::key::Sk::random();
+        let (origin, address) = (Address::from(sk.pubkey().address()), Address::from_low_u64_be(0x32));
+        let empty_root = ledger.state_root();
+        // sender와 nonce는 서명된 트랜잭션에서 얻는다.
+        let signed = |ledger: &Ledger, nonce: usize| {
+            let mut raw = RawTransaction {
+                nonce, gas_price: U256::zero(), gas: U256::zero(), recipient: address,
+                value: U256::zero(), data: vec![], v: 0, r: vec![], s: vec![],
+            };
+            raw.sign(&sk, None);
+            let mut tx = Transaction::from_raw_transaction(&raw);
+            tx.set_parents(&ledger.get_dag().tips());
+            tx
+        };
+        // PUSH1 0x01, PUSH1 0x00, SSTORE, STOP
+        let contract = Contract { code: vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00], address, ..Default::default() };
+        let result = call_contract(view.clone(), &origin, contract.clone(), Some(100_000));
+        assert_eq!(commit_execution(&mut ledger, Transaction::default(), &contract, false, &result),
+                   Err(CommitError::InvalidSignature));
+        commit_execution(&mut ledger, signed(&view, 0), &contract, false, &result).unwrap();
+        assert!(!ledger.account_state(&address).is_empty());
+
+        // PUSH1 0x00, SELFDESTRUCT
+        let code = vec![0x60, 0x00, 0xff];
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        ledger.upsert_account(&AccountNode { key, codehash: code.clone(), ..Default::default() }).unwrap();
+        let contract = Contract { code, address, ..Default::default() };
+        let result = call_contract(view.clone(), &origin, contract.clone(), Some(100_000));
+        assert_eq!(result.destructs, vec![address]);
+        assert!(result.state.dirty.is_removed(&address));
+        let root = commit_execution(&mut ledger, signed(&view, 1), &contract, false, &result).unwrap();
+        assert!(ledger.account_state(&address).is_empty());
+        assert!(!ledger.get_accounts().exist(&key));
+        // 제거된 account는 state root에도 남지 않는다. origin의 nonce만 증가한 상태와 같아야 한다.
+        let mut expected = Ledger::in_memory();
+        let expected_view = Arc::new(Ledger::with_backend(expected.get_backend()));
+        let stop = Contract { code: vec![0x00], address, ..Default::default() };
+        for nonce in 0..2 {
+            let result = call_contract(expected_view.clone(), &origin, stop.clone(), Some(100_000));
+            commit_execution(&mut expected, signed(&expected_view, nonce), &stop, false, &result).unwrap();
+        }
+        assert_ne!(root, empty_root);
+        assert_eq!(root, expected.state_root());
+    }
+
+    #[test]
+    fn batch_calls_see_pending_changes() {
+        use ledger::account::AccountNode;
+        use ledger::batch::{self, BatchEntry, BatchTransaction};
+        use crate::runtime::BatchCallExecutor;
+        let mut ledger = Ledger::in_memory();
+        let sk = crypto::key::Sk::new(&[0x44; 32]);
+        let address = Address::from_low_u64_be(0x41);
+        // PUSH1 0x00, SLOAD, PUSH1 0x01, ADD, PUSH1 0x00, SSTORE, STOP
+        let code = vec![0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x00];
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        ledger.upsert_account(&AccountNode { key, codehash: code, ..Default::default() }).unwrap();
+
+        // 두 번째 호출은 첫 번째 호출이 바꾼 값을 읽는다.
+        let call = BatchEntry::Call { recipient: address, data: vec![] };
+        let mut batch_tx = BatchTransaction {
+            chain_id: 1, nonce: 0, atomic: true, entries: vec![call.clone(), call], y_parity: 0, r: vec![], s: vec![]
+        };
+        batch_tx.sign(&sk);
+        let outcome = batch::execute(&ledger, &batch_tx, &BatchCallExecutor).unwrap();
+        assert!(outcome.sub_receipts.iter().all(|receipt| receipt.is_success()));
+        let mut tx = batch_tx.to_transaction();
+        tx.state_hash = outcome.states.hash();
+        ledger.commit_batch(&tx, &outcome).unwrap();
+        assert_eq!(ledger.get_storage_value(&address, &H256::zero()), H256::from_low_u64_be(2));
+    }
+
+    #[test]
+    fn wasm_infinite_loop_runs_out_of_gas() {
+        let ledger = Arc::new(Ledger::in_memory());
+        let code = wat::parse_str(r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "call") (loop (br 0))))
+        "#).unwrap();
+        let contract = Contract { code, address: Address::from_low_u64_be(0x33), ..Default::default() };
+        let result = call_contract(ledger.clone(), &Address::zero(), contract.clone(), Some(100_000));
+        assert!(result.error == Some(crate::err::RunError::OutOfGas));
+        assert!(result.gas_used > 100_000);
+        let err = estimate_gas(ledger, &Address::zero(), &contract, false, 1_000_000).err().unwrap();
+        assert_eq!(err.message, "gas required exceeds allowance (1000000)");
+    }
+
+    #[test]
+    fn replay_committed_transaction() {
+        use ledger::account::AccountNode;
+        use ledger::fsck::{self, CheckOptions, Problem};
+        use ledger::receipt::Receipt;
+        use ledger::transaction::Transaction;
+        use crate::runtime::TransactionReplay;
+        use ethereum_types::U256;
+        use ledger::envelope::{DynamicFeeTransaction, TypedTransaction};
+        use ledger::transaction::RawTransaction;
+        use crate::gas::intrinsic_gas;
+        let mut ledger = Ledger::in_memory();
+        let view = Arc::new(Ledger::with_backend(ledger.get_backend()));
+        let address = Address::from_low_u64_be(0x30);
+        // 512번 반복한 뒤 storage 0번에 1을 저장한다. (1000 step 이상 실행된다)
+        // PUSH2 0x0200, JUMPDEST, PUSH1 0x01, SWAP1, SUB, DUP1, PUSH1 0x03, JUMPI, PUSH1 0x01, PUSH1 0x00, SSTORE, STOP
+        let code = vec![0x61, 0x02, 0x00, 0x5b, 0x60, 0x01, 0x90, 0x03, 0x80, 0x60, 0x03, 0x57,
+                        0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+        let key = H256::from(crypto::hash::keccak256(address.as_bytes()));
+        ledger.upsert_account(&AccountNode { key, codehash: code.clone(), ..Default::default() }).unwrap();
+
+        // fee와 value가 있는 서명된 legacy, typed 트랜잭션
+        let sk = crypto::key::Sk::random();
+        let sender = Address::from(sk.pubkey().address());
+        let mut legacy: TypedTransaction = RawTransaction {
+            nonce: 0, gas_price: U256::from(20), gas: U256::from(100_000), recipient: address,
+            value: U256::from(5), data: vec![1], v: 0, r: vec![], s: vec![],
+        }.into();
+        let mut typed = TypedTransaction::DynamicFee(DynamicFeeTransaction {
+            chain_id: 7, nonce: 1, max_priority_fee_per_gas: U256::from(3), max_fee_per_gas: U256::from(20),
+            gas: U256::from(100_000), recipient: Some(address), value: U256::from(5), data: vec![2], access_list: vec![],
+            y_parity: 0, r: vec![], s: vec![],
+        });
+        for signed in vec![&mut legacy, &mut typed] {
+            signed.sign(&sk, 7);
+            let mut tx = signed.to_transaction();
+            let contract = Contract { code: code.clone(), address, caller: sender, input: tx.data.clone(), ..Default::default() };
+            let result = call_contract(view.clone(), &sender, contract, Some(100_000));
+            assert!(result.error.is_none());
+            tx.set_parents(&ledger.get_dag().tips());
+            tx.state_hash = result.state.dirty.hash();
+            let gas_used = intrinsic_gas(&tx.data, false) + result.gas_used;
+            let receipt = Receipt::new(&tx.hash(), 1, &sender, vec![], gas_used, None, vec![]);
+            ledger.commit(&tx, &result.state.dirty, &receipt).unwrap();
+        }
+
+        let options = CheckOptions { authorities: vec![], quarantine: false };
+        let report = fsck::check(&ledger, &options, Some(&TransactionReplay));
+        assert_eq!(report.replayed, 2);
+        assert!(report.issues.is_empty());
+    }
 }
@@ -1,14 +1,20 @@
 mod constants;
 mod context;
 mod contract;
+mod disasm;
 mod err;
 mod evm;
+mod gas;
 mod instruction;
 mod interpreter;
 mod jumptable;
 mod memory;
 mod opcode;
+mod profiler;
+mod runtime;
 mod stack;
+mod state;
+mod wasm;
 
 use std::cell::{Cell, RefCell};
 use std::fs::File;
@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use ethereum_types::H256;
+use serde_json::{json, Value};
+use crate::disasm::disassemble;
+use crate::opcode::get_opcode_name;
+
+/// 하나의 컨트랙트(codehash)에 대해 누적된 실행 정보
+pub struct ContractProfile {
+    pub code: Vec<u8>,
+    pub executions: u64,
+    pub steps: u64,
+    pub elapsed: Duration,
+    pub pc_hits: BTreeMap<u64, u64>,
+    pub opcode_counts: BTreeMap<u8, u64>,
+    pub opcode_elapsed: BTreeMap<u8, Duration>,
+}
+
+impl ContractProfile {
+    pub fn new(code: &Vec<u8>) -> Self {
+        ContractProfile {
+            code: code.clone(),
+            executions: 0,
+            steps: 0,
+            elapsed: Duration::default(),
+            pc_hits: BTreeMap::new(),
+            opcode_counts: BTreeMap::new(),
+            opcode_elapsed: BTreeMap::new(),
+        }
+    }
+
+    /// 한 번이라도 실행된 명령어의 수와 전체 명령어의 수를 반환한다.
+    pub fn coverage(&self) -> (usize, usize) {
+        let lines = disassemble(&self.code);
+        let hit = lines.iter().filter(|line| self.pc_hits.contains_key(&line.pc)).count();
+        (hit, lines.len())
+    }
+}
+
+/// 여러 번의 컨트랙트 실행에 걸쳐 pc별 실행 횟수, opcode별 실행 횟수 및 시간을 누적하는 profiler
+/// Interpreter에 `Arc<Mutex<Profiler>>`로 연결하여 사용한다.
+pub struct Profiler {
+    contracts: HashMap<H256, ContractProfile>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler { contracts: HashMap::new() }
+    }
+
+    pub fn codehash(code: &Vec<u8>) -> H256 {
+        H256::from(crypto::hash::keccak256(code.as_slice()))
+    }
+
+    pub fn begin_execution(&mut self, code: &Vec<u8>) -> H256 {
+        let codehash = Profiler::codehash(code);
+        let profile = self.contracts.entry(codehash)
+            .or_insert_with(|| ContractProfile::new(code));
+        profile.executions += 1;
+        codehash
+    }
+
+    pub fn record_step(&mut self, codehash: &H256, pc: u64, op: u8, elapsed: Duration) {
+        let profile = match self.contracts.get_mut(codehash) {
+            None => { return }
+            Some(profile) => { profile }
+        };
+        profile.steps += 1;
+        profile.elapsed += elapsed;
+        *profile.pc_hits.entry(pc).or_insert(0) += 1;
+        *profile.opcode_counts.entry(op).or_insert(0) += 1;
+        *profile.opcode_elapsed.entry(op).or_insert(Duration::default()) += elapsed;
+    }
+
+    pub fn get_profile(&self, codehash: &H256) -> Option<&ContractProfile> {
+        self.contracts.get(codehash)
+    }
+
+    pub fn clear(&mut self) {
+        self.contracts.clear();
+    }
+
+    fn sorted_codehashes(&self) -> Vec<H256> {
+        let mut codehashes: Vec<H256> = self.contracts.keys().cloned().collect();
+        codehashes.sort();
+        codehashes
+    }
+
+    /// 누적된 결과를 JSON으로 변환한다.
+    pub fn to_json(&self) -> Value {
+        let mut contracts = vec![];
+        for codehash in self.sorted_codehashes().iter() {
+            let profile = self.contracts.get(codehash).unwrap();
+            let mut opcodes = vec![];
+            for (op, count) in profile.opcode_counts.iter() {
+                let elapsed = profile.opcode_elapsed.get(op).cloned().unwrap_or_default();
+                opcodes.push(json!({
+                    "opcode": format!("0x{:02x}", op),
+                    "name": get_opcode_name(op),
+                    "count": count,
+                    "time_ns": elapsed.as_nanos() as u64,
+                }));
+            }
+            let mut pcs = vec![];
+            for line in disassemble(&profile.code).iter() {
+                pcs.push(json!({
+                    "pc": line.pc,
+                    "instruction": line.to_string(),
+                    "hits": profile.pc_hits.get(&line.pc).cloned().unwrap_or(0),
+                }));
+            }
+            let (hit, found) = profile.coverage();
+            contracts.push(json!({
+                "codehash": format!("0x{}", hex::encode(codehash.as_bytes())),
+                "executions": profile.executions,
+                "steps": profile.steps,
+                "time_ns": profile.elapsed.as_nanos() as u64,
+                "coverage": { "hit": hit, "found": found },
+                "opcodes": opcodes,
+                "pcs": pcs,
+            }));
+        }
+        json!({ "contracts": contracts })
+    }
+
+    /// 누적된 결과를 LCOV 형식의 coverage 리포트로 변환한다.
+    /// 각 컨트랙트는 `<codehash>.asm` 파일로 표현되며, line number는 `disasm::to_listing`의 줄 번호와 같다.
+    pub fn to_lcov(&self) -> String {
+        let mut report = String::new();
+        for codehash in self.sorted_codehashes().iter() {
+            let profile = self.contracts.get(codehash).unwrap();
+            report.push_str("TN:\n");
+            report.push_str(format!("SF:0x{}.asm\n", hex::encode(codehash.as_bytes())).as_str());
+            let lines = disassemble(&profile.code);
+            let mut hit = 0;
+            for (idx, line) in lines.iter().enumerate() {
+                let hits = profile.pc_hits.get(&line.pc).cloned().unwrap_or(0);
+                if hits > 0 { hit += 1; }
+                report.push_str(format!("DA:{},{}\n", idx + 1, hits).as_str());
+            }
+            report.push_str(format!("LH:{}\n", hit).as_str());
+            report.push_str(format!("LF:{}\n", lines.len()).as_str());
+            report.push_str("end_of_record\n");
+        }
+        report
+    }
+
+    /// JSON 결과를 파일로 저장한다.
+    pub fn export_json(&self, path: &str) {
+        let mut file = common::fileutil::new_file(path);
+        let data = serde_json::to_vec_pretty(&self.to_json()).unwrap();
+        common::fileutil::write_file(&mut file, data);
+    }
+
+    /// LCOV 리포트와 각 컨트랙트의 디스어셈블 결과(`<codehash>.asm`)를 dir에 저장한다.
+    pub fn export_lcov(&self, dir: &str) {
+        let mut file = common::fileutil::new_file(format!("{}/coverage.info", dir).as_str());
+        common::fileutil::write_file(&mut file, self.to_lcov().into_bytes());
+        for (codehash, profile) in self.contracts.iter() {
+            let path = format!("{}/0x{}.asm", dir, hex::encode(codehash.as_bytes()));
+            let mut file = common::fileutil::new_file(path.as_str());
+            common::fileutil::write_file(&mut file, crate::disasm::to_listing(&profile.code).into_bytes());
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use ethereum_types::{Address, H256};
+use ledger::batch::{CallExecutor, SubReceipt};
+use ledger::dirty_state::DirtyStates;
+use ledger::fsck::Replay;
+use ledger::ledger::{CommitError, Ledger};
+use ledger::receipt::{Receipt, STATUS_FAILED, STATUS_SUCCESS};
+use ledger::transaction::Transaction;
+use crate::contract::Contract;
+use crate::err::RunError;
+use crate::interpreter::Interpreter;
+use crate::state::StateDb;
+use crate::wasm::WasmRuntime;
+use crate::gas::{intrinsic_gas, GasCap};
+
+/// 배포되는 코드가 이 값으로 시작하면 wasm 런타임으로 실행된다. (wasm binary의 magic number `\0asm`)
+/// EVM 바이트코드는 STOP(0x00)으로 시작할 이유가 없으므로 두 형식이 겹치지 않는다.
+pub const WASM_CODE_PREFIX: [u8; 4] = [0x00, 0x61, 0x73, 0x6D];
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum ContractRuntime {
+    Evm,
+    Wasm,
+}
+
+impl ContractRuntime {
+    pub fn from_code(code: &Vec<u8>) -> Self {
+        if code.starts_with(&WASM_CODE_PREFIX) { return ContractRuntime::Wasm; }
+        ContractRuntime::Evm
+    }
+}
+
+/// 컨트랙트 배포 또는 호출의 결과
+/// * `output` - 배포일 경우 저장될 코드, 호출일 경우 반환값
+/// * `state` - 실행으로 인한 storage 변경과 log (실패한 실행은 비어있다)
+/// * `gas_used` - intrinsic gas를 제외하고 실행에 사용된 gas
+/// * `destructs` - SELFDESTRUCT된 컨트랙트. state.dirty에 account 제거로 함께 기록되어 반영될 때 삭제된다.
+pub struct ExecutionResult {
+    pub output: Option<Vec<u8>>,
+    pub error: Option<RunError>,
+    pub state: StateDb,
+    pub gas_used: u64,
+    pub destructs: Vec<Address>,
+}
+
+/// state는 실행 전의 변경이며 실행 결과의 state는 이 변경을 포함한다.
+fn run(ledger: Arc<Ledger>, origin: &Address, contract: Contract, deploy: bool, gas_limit: Option<u64>,
+       mut state: StateDb) -> ExecutionResult {
+    let mut destructs = vec![];
+    let (output, error, gas_used) = match ContractRuntime::from_code(&contract.code) {
+        ContractRuntime::Wasm => {
+            let mut runtime = WasmRuntime::new(&ledger);
+            runtime.gas_limit = gas_limit;
+            let (output, error) = match deploy {
+                true => { runtime.deploy(&contract, &mut state) }
+                false => { runtime.call(&contract, &mut state) }
+            };
+            (output, error, runtime.gas_used)
+        }
+        ContractRuntime::Evm => {
+            let mut interpreter = Interpreter::new(origin.clone());
+            interpreter.debug = false;
+            interpreter.ledger = Some(ledger.clone());
+            interpreter.gas_limit = gas_limit;
+            interpreter.state = state;
+            let input = contract.input.clone();
+            let (output, error) = interpreter.run_contract(&RefCell::new(contract), input);
+            state = std::mem::take(&mut interpreter.state);
+            if error.is_none() { destructs = std::mem::take(&mut interpreter.destructs); }
+            (output, error, interpreter.gas_used)
+        }
+    };
+    for address in destructs.iter() { state.dirty.remove_account(address); }
+    ExecutionResult { output, error, state, gas_used, destructs }
+}
+
+/// 코드의 prefix로 런타임을 선택하여 컨트랙트를 배포한다.
+/// gas_limit이 None일 경우 gas를 제한하지 않는다.
+pub fn deploy_contract(ledger: Arc<Ledger>, origin: &Address, contract: Contract, gas_limit: Option<u64>)
+                       -> ExecutionResult {
+    run(ledger, origin, contract, true, gas_limit, StateDb::new())
+}
+
+/// 배포된 코드의 prefix로 런타임을 선택하여 컨트랙트를 호출한다.
+/// gas_limit이 None일 경우 gas를 제한하지 않는다.
+pub fn call_contract(ledger: Arc<Ledger>, origin: &Address, contract: Contract, gas_limit: Option<u64>)
+                     -> ExecutionResult {
+    run(ledger, origin, contract, false, gas_limit, StateDb::new())
+}
+
+/// 실행 결과로 트랜잭션과 storage 변경, receipt를 함께 기록하고 새로운 state root를 반환한다.
+/// sender와 nonce는 tx의 서명에서 얻으며 tx의 state_hash는 실행 결과로 채운다. tx는 DAG에 연결할 수 있어야 한다.
+/// SELFDESTRUCT된 컨트랙트도 같은 batch에서 삭제된다. 실패한 실행은 storage를 변경하지 않으며 receipt만 기록된다. receipt의 gas_used는 intrinsic gas를 포함한다.
+/// 실행 성공 여부와 관계없이 sender의 nonce는 증가하며, tx의 nonce가 sender의 다음 nonce가 아니라면 기록되지 않는다.
+pub fn commit_execution(ledger: &mut Ledger, mut tx: Transaction, contract: &Contract, contract_creation: bool,
+                        result: &ExecutionResult) -> Result<H256, CommitError> {
+    let sender = tx.try_get_sender().ok_or(CommitError::InvalidSignature)?;
+    tx.state_hash = result.state.dirty.hash();
+    let data = match contract_creation {
+        true => { &contract.code }
+        false => { &contract.input }
+    };
+    let gas_used = intrinsic_gas(data, contract_creation) + result.gas_used;
+    let (status, contract_address) = match result.error {
+        None if contract_creation => { (STATUS_SUCCESS, Some(contract.address.clone())) }
+        None => { (STATUS_SUCCESS, None) }
+        Some(_) => { (STATUS_FAILED, None) }
+    };
+    let receipt = Receipt::new(&tx.hash(), status, &sender, result.output.clone().unwrap_or_default(),
+                               gas_used, contract_address, result.state.logs.clone());
+    ledger.commit(&tx, &result.state.dirty, &receipt)
+}
+
+/// ledger 검사(fsck)에서 receipt에 기록된 sender로 트랜잭션을 다시 실행한다.
+/// 컨트랙트 코드는 검사하는 ledger에서, storage는 재실행 중인 state에서 읽는다.
+/// gas limit은 receipt의 gas_used에서 intrinsic gas를 뺀 값이므로 원래 실행과 같은 지점까지 실행된다.
+pub struct TransactionReplay;
+
+impl Replay for TransactionReplay {
+    fn replay(&self, ledger: &Ledger, state: &Arc<Ledger>, tx: &Transaction, receipt: &Receipt) -> Option<DirtyStates> {
+        // 실패한 실행은 storage를 변경하지 않는다.
+        if !receipt.is_success() { return Some(DirtyStates::new()); }
+        let intrinsic = intrinsic_gas(&tx.data, receipt.contract_address.is_some());
+        let gas_limit = Some(receipt.gas_used.saturating_sub(intrinsic));
+        let result = match &receipt.contract_address {
+            Some(address) => {
+                let contract = Contract {
+                    code: tx.data.clone(), address: address.clone(), caller: receipt.from.clone(), ..Default::default()
+                };
+                deploy_contract(state.clone(), &receipt.from, contract, gas_limit)
+            }
+            None => {
+                let code = ledger.get_accounts().get_account(&tx.recipient).codehash;
+                if code.is_empty() { return Some(DirtyStates::new()); }
+                let contract = Contract {
+                    code, address: tx.recipient.clone(), caller: receipt.from.clone(), input: tx.data.clone(),
+                    ..Default::default()
+                };
+                call_contract(state.clone(), &receipt.from, contract, gas_limit)
+            }
+        };
+        match result.error {
+            None => { Some(result.state.dirty) }
+            Some(_) => { Some(DirtyStates::new()) }
+        }
+    }
+}
+
+/// batch 트랜잭션의 컨트랙트 호출을 실행한다.
+/// 앞선 entry들의 변경(pending)이 보이도록 실행하며, 호출마다 GasCap까지만 실행한다.
+pub struct BatchCallExecutor;
+
+impl CallExecutor for BatchCallExecutor {
+    fn call(&self, ledger: &Ledger, pending: &DirtyStates, sender: &Address, recipient: &Address,
+            data: &Vec<u8>) -> (SubReceipt, DirtyStates) {
+        let code = match pending.is_removed(recipient) {
+            true => { vec![] }
+            false => { ledger.get_accounts().get_account(recipient).codehash }
+        };
+        // 코드가 없는 account로의 호출은 실행할 것이 없다.
+        if code.is_empty() { return (SubReceipt::new(STATUS_SUCCESS, vec![], vec![]), DirtyStates::new()); }
+        let contract = Contract {
+            code, address: recipient.clone(), caller: sender.clone(), input: data.clone(), ..Default::default()
+        };
+        let state = StateDb { dirty: pending.clone(), logs: vec![] };
+        let ledger = Arc::new(Ledger::with_backend(ledger.get_backend()));
+        let result = run(ledger, sender, contract, false, Some(GasCap), state);
+        let output = result.output.unwrap_or_default();
+        if result.error.is_some() { return (SubReceipt::new(STATUS_FAILED, output, vec![]), DirtyStates::new()); }
+        // 실행 결과의 state는 pending을 포함하므로 이 호출이 바꾼 값만 남긴다.
+        let mut changes = DirtyStates::new();
+        for address in result.state.dirty.addresses().iter() {
+            for (key, value) in result.state.dirty.changes(address).iter() {
+                if pending.get_value(address, key) != Some(value.clone()) { changes.set_value(address, key, value); }
+            }
+        }
+        for address in result.state.dirty.removed().iter() {
+            if !pending.is_removed(address) { changes.remove_account(address); }
+        }
+        (SubReceipt::new(STATUS_SUCCESS, output, result.state.logs), changes)
+    }
+}
+
+/// Error(string)의 function selector
+const REVERT_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// revert 시 반환된 데이터가 `Error(string)`으로 ABI 인코딩되어 있다면 그 문자열을 반환한다.
+pub fn unpack_revert_reason(data: &Vec<u8>) -> Option<String> {
+    if data.len() < 4 + 64 || data[..4] != REVERT_SELECTOR { return None; }
+    let body = &data[4..];
+    let offset = ethereum_types::U256::from_big_endian(&body[..32]);
+    if offset > ethereum_types::U256::from(body.len() - 32) { return None; }
+    let offset = offset.as_usize();
+    let length = ethereum_types::U256::from_big_endian(&body[offset..offset + 32]);
+    if length > ethereum_types::U256::from(body.len() - offset - 32) { return None; }
+    let start = offset + 32;
+    String::from_utf8(body[start..start + length.as_usize()].to_vec()).ok()
+}
+
+/// gas 추정에 실패한 이유
+/// * `reverted` - 상한에서도 실행이 revert된 경우 true이며 `data`는 revert 시 반환된 값이다.
+pub struct EstimateError {
+    pub reverted: bool,
+    pub message: String,
+    pub data: Option<Vec<u8>>,
+}
+
+/// 트랜잭션을 읽기 전용으로 반복 실행하여 실패하지 않는 가장 작은 gas limit을 찾는다.
+/// intrinsic gas와 gas_cap 사이에서 이진 탐색하며, 실행 결과는 ledger에 반영되지 않는다.
+/// * `contract_creation` - true일 경우 contract.code를 배포 코드로, false일 경우 contract.input을 calldata로 사용한다.
+pub fn estimate_gas(ledger: Arc<Ledger>, origin: &Address, contract: &Contract, contract_creation: bool,
+                    gas_cap: u64) -> Result<u64, EstimateError> {
+    let data = match contract_creation {
+        true => { &contract.code }
+        false => { &contract.input }
+    };
+    let intrinsic = intrinsic_gas(data, contract_creation);
+    if gas_cap < intrinsic {
+        return Err(EstimateError {
+            reverted: false,
+            message: format!("intrinsic gas too low: have {}, want {}", gas_cap, intrinsic),
+            data: None,
+        });
+    }
+    // 코드가 없는 account로의 호출은 실행할 것이 없다.
+    if !contract_creation && contract.code.is_empty() { return Ok(intrinsic); }
+
+    let execute = |gas: u64| -> ExecutionResult {
+        run(ledger.clone(), origin, contract.clone(), contract_creation, Some(gas - intrinsic), StateDb::new())
+    };
+
+    let result = execute(gas_cap);
+    if let Some(err) = result.error {
+        if err == RunError::ExecutionReverted {
+            let data = result.output.unwrap_or_default();
+            let message = match unpack_revert_reason(&data) {
+                Some(reason) => { format!("execution reverted: {}", reason) }
+                None => { "execution reverted".to_string() }
+            };
+            return Err(EstimateError { reverted: true, message, data: Some(data) });
+        }
+        let message = match err {
+            RunError::OutOfGas => { format!("gas required exceeds allowance ({})", gas_cap) }
+            _ => { format!("execution failed: {:?}", err) }
+        };
+        return Err(EstimateError { reverted: false, message, data: None });
+    }
+
+    // 실행에 사용된 gas는 항상 성공하는 limit의 하한이므로 탐색 범위를 좁힐 수 있다.
+    let mut lo = intrinsic + result.gas_used - 1;
+    let mut hi = gas_cap;
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        match execute(mid).error {
+            None => { hi = mid; }
+            Some(_) => { lo = mid; }
+        }
+    }
+    Ok(hi)
+}
@@ -0,0 +1,46 @@
+use ethereum_types::{Address, H256};
+use ledger::ledger::Ledger;
+use ledger::dirty_state::DirtyStates;
+use ledger::log::Log;
+
+/// 컨트랙트 실행 중 변경된 storage 값과 발생한 log를 모아두는 상태
+/// EVM과 wasm 런타임이 동일한 StateDb를 통해 ledger storage에 접근한다.
+/// 실행이 성공하면 dirty 값이 트랜잭션의 상태 변화(DirtyStates)가 된다.
+pub struct StateDb {
+    pub dirty: DirtyStates,
+    pub logs: Vec<Log>,
+}
+
+impl Default for StateDb {
+    fn default() -> Self { StateDb::new() }
+}
+
+impl Clone for StateDb {
+    fn clone(&self) -> Self {
+        StateDb { dirty: self.dirty.clone(), logs: self.logs.clone() }
+    }
+}
+
+impl StateDb {
+    pub fn new() -> Self {
+        StateDb { dirty: DirtyStates::new(), logs: vec![] }
+    }
+
+    /// 실행 중 변경된 값이 있다면 그 값을, 없다면 ledger에 저장된 값을 반환한다.
+    /// 제거되는 account의 storage는 비어있다.
+    pub fn get_storage(&self, ledger: &Ledger, address: &Address, key: &H256) -> H256 {
+        if self.dirty.is_removed(address) { return H256::zero(); }
+        match self.dirty.get_value(address, key) {
+            Some(value) => { value }
+            None => { ledger.get_storage_value(address, key) }
+        }
+    }
+
+    pub fn set_storage(&mut self, address: &Address, key: &H256, value: &H256) {
+        self.dirty.set_value(address, key, value);
+    }
+
+    pub fn add_log(&mut self, log: Log) {
+        self.logs.push(log);
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt::{Display, Formatter};
+use ethereum_types::H256;
+use wasmi::{Externals, FuncInstance, FuncRef, HostError, ImportsBuilder, MemoryRef, Module,
+            ModuleImportResolver, ModuleInstance, ModuleRef, RuntimeArgs, RuntimeValue,
+            Signature, Trap, TrapKind, ValueType};
+use parity_wasm::elements::{self, External, FunctionType, ImportCountType, ImportEntry, ImportSection,
+                            Instruction, Internal, Section, Type, TypeSection};
+use ledger::ledger::Ledger;
+use ledger::log::Log;
+use crate::contract::Contract;
+use crate::err::RunError;
+use crate::state::StateDb;
+use crate::gas::{CopyGas, GasCap, LogDataGas, LogGas, LogTopicGas, SloadGas, WasmStepGas, sstore_gas};
+
+/// wasm 컨트랙트가 import하는 host function이 속한 모듈 이름
+pub const HOST_MODULE: &str = "env";
+/// 컨트랙트 배포 시 호출되는 export (없어도 된다)
+pub const DEPLOY_EXPORT: &str = "deploy";
+/// 컨트랙트 호출 시 호출되는 export
+pub const CALL_EXPORT: &str = "call";
+
+const STORAGE_GET: usize = 0;
+const STORAGE_SET: usize = 1;
+const LOG: usize = 2;
+const CALLER: usize = 3;
+const ADDRESS: usize = 4;
+const CALLDATA_SIZE: usize = 5;
+const CALLDATA_COPY: usize = 6;
+const RETURN_DATA: usize = 7;
+const REVERT: usize = 8;
+const GAS: usize = 9;
+
+/// 명령어 비용을 차감하기 위해 실행 전 주입되는 host function
+const GAS_IMPORT: &str = "gas";
+
+/// revert host function 또는 잘못된 메모리 접근으로 실행이 중단될 때 사용하는 trap
+#[derive(Debug)]
+enum HostTrap {
+    Revert,
+    MemoryAccess,
+    OutOfGas,
+}
+
+impl Display for HostTrap {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            HostTrap::Revert => { write!(f, "execution reverted") }
+            HostTrap::MemoryAccess => { write!(f, "invalid memory access") }
+            HostTrap::OutOfGas => { write!(f, "out of gas") }
+        }
+    }
+}
+
+impl HostError for HostTrap {}
+
+/// `env` 모듈의 host function을 wasm 모듈에 연결한다.
+/// 모든 포인터는 컨트랙트가 export한 `memory`의 offset이며 key, value는 32 bytes, 주소는 20 bytes이다.
+/// * storage_get(key_ptr, value_ptr), storage_set(key_ptr, value_ptr)
+/// * log(topics_ptr, topic_count, data_ptr, data_len)
+/// * caller(ptr), address(ptr)
+/// * calldata_size() -> i32, calldata_copy(dest_ptr, offset, len)
+/// * return_data(ptr, len), revert(ptr, len)
+/// * gas(amount) - 실행 전 주입되며 명령어 비용을 차감한다.
+struct HostResolver;
+
+impl ModuleImportResolver for HostResolver {
+    fn resolve_func(&self, field_name: &str, signature: &Signature) -> Result<FuncRef, wasmi::Error> {
+        let (index, params, ret): (usize, &[ValueType], Option<ValueType>) = match field_name {
+            "storage_get" => { (STORAGE_GET, &[ValueType::I32, ValueType::I32], None) }
+            "storage_set" => { (STORAGE_SET, &[ValueType::I32, ValueType::I32], None) }
+            "log" => { (LOG, &[ValueType::I32, ValueType::I32, ValueType::I32, ValueType::I32], None) }
+            "caller" => { (CALLER, &[ValueType::I32], None) }
+            "address" => { (ADDRESS, &[ValueType::I32], None) }
+            "calldata_size" => { (CALLDATA_SIZE, &[], Some(ValueType::I32)) }
+            "calldata_copy" => { (CALLDATA_COPY, &[ValueType::I32, ValueType::I32, ValueType::I32], None) }
+            "return_data" => { (RETURN_DATA, &[ValueType::I32, ValueType::I32], None) }
+            "revert" => { (REVERT, &[ValueType::I32, ValueType::I32], None) }
+            GAS_IMPORT => { (GAS, &[ValueType::I32], None) }
+            _ => {
+                return Err(wasmi::Error::Instantiation(
+                    format!("host function `{}` not found", field_name)));
+            }
+        };
+        let expected = Signature::new(params, ret);
+        if signature != &expected {
+            return Err(wasmi::Error::Instantiation(
+                format!("host function `{}` has wrong signature", field_name)));
+        }
+        Ok(FuncInstance::alloc_host(expected, index))
+    }
+}
+
+/// host function 호출 시 접근 가능한 실행 환경
+/// 명령어마다 WasmStepGas를 매기며 storage, log 등 host function 호출에는 EVM과 같은 비용을 매긴다.
+struct WasmHost<'a> {
+    ledger: &'a Ledger,
+    contract: &'a Contract,
+    memory: MemoryRef,
+    state: &'a mut StateDb,
+    output: Vec<u8>,
+    gas_limit: Option<u64>,
+    gas_used: u64,
+}
+
+impl<'a> WasmHost<'a> {
+    fn use_gas(&mut self, gas: u64) -> Result<(), Trap> {
+        self.gas_used = self.gas_used.saturating_add(gas);
+        if self.gas_limit.map_or(false, |limit| self.gas_used > limit) {
+            return Err(Trap::new(TrapKind::Host(Box::new(HostTrap::OutOfGas))));
+        }
+        Ok(())
+    }
+
+    fn read(&self, ptr: u32, size: usize) -> Result<Vec<u8>, Trap> {
+        self.memory.get(ptr, size)
+            .map_err(|_| Trap::new(TrapKind::Host(Box::new(HostTrap::MemoryAccess))))
+    }
+
+    fn write(&self, ptr: u32, value: &[u8]) -> Result<(), Trap> {
+        self.memory.set(ptr, value)
+            .map_err(|_| Trap::new(TrapKind::Host(Box::new(HostTrap::MemoryAccess))))
+    }
+}
+
+impl<'a> Externals for WasmHost<'a> {
+    fn invoke_index(&mut self, index: usize, args: RuntimeArgs) -> Result<Option<RuntimeValue>, Trap> {
+        let address = self.contract.address.clone();
+        match index {
+            STORAGE_GET => {
+                self.use_gas(SloadGas)?;
+                let key = H256::from_slice(&self.read(args.nth_checked(0)?, 32)?);
+                let value = self.state.get_storage(self.ledger, &address, &key);
+                self.write(args.nth_checked(1)?, value.as_bytes())?;
+                Ok(None)
+            }
+            STORAGE_SET => {
+                let key = H256::from_slice(&self.read(args.nth_checked(0)?, 32)?);
+                let value = H256::from_slice(&self.read(args.nth_checked(1)?, 32)?);
+                let current = self.state.get_storage(self.ledger, &address, &key);
+                self.use_gas(sstore_gas(&current, &value))?;
+                self.state.set_storage(&address, &key, &value);
+                Ok(None)
+            }
+            LOG => {
+                let topics_ptr: u32 = args.nth_checked(0)?;
+                let topic_count: u32 = args.nth_checked(1)?;
+                let mut topics = vec![];
+                for idx in 0..std::cmp::min(topic_count, 4) {
+                    let ptr = idx.checked_mul(32).and_then(|offset| topics_ptr.checked_add(offset))
+                    .ok_or_else(|| Trap::new(TrapKind::Host(Box::new(HostTrap::MemoryAccess))))?;
+                topics.push(H256::from_slice(&self.read(ptr, 32)?));
+                }
+                let data_len: u32 = args.nth_checked(3)?;
+                self.use_gas(LogGas + topics.len() as u64 * LogTopicGas + data_len as u64 * LogDataGas)?;
+                let data = self.read(args.nth_checked(2)?, data_len as usize)?;
+                self.state.add_log(Log::new(&address, topics, data));
+                Ok(None)
+            }
+            CALLER => {
+                self.write(args.nth_checked(0)?, self.contract.caller.as_bytes())?;
+                Ok(None)
+            }
+            ADDRESS => {
+                self.write(args.nth_checked(0)?, address.as_bytes())?;
+                Ok(None)
+            }
+            CALLDATA_SIZE => {
+                Ok(Some(RuntimeValue::I32(self.contract.input.len() as i32)))
+            }
+            CALLDATA_COPY => {
+                let offset: u32 = args.nth_checked(1)?;
+                let size: u32 = args.nth_checked(2)?;
+                self.use_gas((size as u64 + 31) / 32 * CopyGas)?;
+                let data = crate::memory::get_data(&self.contract.input, offset as u64, size as u64);
+                self.write(args.nth_checked(0)?, data.as_slice())?;
+                Ok(None)
+            }
+            RETURN_DATA | REVERT => {
+                let size: u32 = args.nth_checked(1)?;
+                self.output = self.read(args.nth_checked(0)?, size as usize)?;
+                if index == REVERT {
+                    return Err(Trap::new(TrapKind::Host(Box::new(HostTrap::Revert))));
+                }
+                Ok(None)
+            }
+            GAS => {
+                let gas: u32 = args.nth_checked(0)?;
+                self.use_gas(gas as u64)?;
+                Ok(None)
+            }
+            _ => { panic!("unknown host function index {}", index) }
+        }
+    }
+}
+
+/// 분기 없이 실행되는 명령어 구간마다 그 구간의 비용을 차감하는 `gas` 호출을 앞에 주입한다.
+/// 주입된 import가 함수 index 공간에 추가되므로 기존 함수를 가리키는 index는 하나씩 밀린다.
+fn inject_gas(mut module: elements::Module) -> Result<elements::Module, elements::Error> {
+    let signature = Type::Function(FunctionType::new(vec![elements::ValueType::I32], vec![]));
+    let type_index = match module.type_section_mut() {
+        Some(section) => {
+            section.types_mut().push(signature);
+            section.types().len() as u32 - 1
+        }
+        None => {
+            module.insert_section(Section::Type(TypeSection::with_types(vec![signature])))?;
+            0
+        }
+    };
+    let gas_func = module.import_count(ImportCountType::Function) as u32;
+    let entry = ImportEntry::new(HOST_MODULE.to_string(), GAS_IMPORT.to_string(), External::Function(type_index));
+    match module.import_section_mut() {
+        Some(section) => { section.entries_mut().push(entry); }
+        None => { module.insert_section(Section::Import(ImportSection::with_entries(vec![entry])))?; }
+    }
+
+    let shift = |index: &mut u32| { if *index >= gas_func { *index += 1; } };
+    for section in module.sections_mut().iter_mut() {
+        match section {
+            Section::Export(section) => {
+                for entry in section.entries_mut().iter_mut() {
+                    if let Internal::Function(index) = entry.internal_mut() { shift(index); }
+                }
+            }
+            Section::Element(section) => {
+                for segment in section.entries_mut().iter_mut() {
+                    for index in segment.members_mut().iter_mut() { shift(index); }
+                }
+            }
+            Section::Start(index) => { shift(index); }
+            Section::Code(section) => {
+                for body in section.bodies_mut().iter_mut() {
+                    let code = std::mem::take(body.code_mut().elements_mut());
+                    let mut metered = vec![];
+                    let mut segment = vec![];
+                    for mut instruction in code.into_iter() {
+                        if let Instruction::Call(index) = &mut instruction { shift(index); }
+                        let boundary = match instruction {
+                            Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) | Instruction::Else |
+                            Instruction::End | Instruction::Br(_) | Instruction::BrIf(_) | Instruction::BrTable(_) |
+                            Instruction::Return | Instruction::Unreachable | Instruction::Call(_) |
+                            Instruction::CallIndirect(_, _) => { true }
+                            _ => { false }
+                        };
+                        segment.push(instruction);
+                        if boundary { charge(&mut metered, &mut segment, gas_func); }
+                    }
+                    charge(&mut metered, &mut segment, gas_func);
+                    *body.code_mut().elements_mut() = metered;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(module)
+}
+
+/// 구간의 비용을 차감하는 호출과 구간의 명령어를 code에 옮긴다.
+fn charge(code: &mut Vec<Instruction>, segment: &mut Vec<Instruction>, gas_func: u32) {
+    if segment.is_empty() { return; }
+    let cost = std::cmp::min(segment.len() as u64 * WasmStepGas, i32::MAX as u64);
+    code.push(Instruction::I32Const(cost as i32));
+    code.push(Instruction::Call(gas_func));
+    code.append(segment);
+}
+
+/// 순수 Rust로 작성된 wasmi 인터프리터를 사용하여 wasm 컨트랙트를 실행하는 런타임
+/// storage와 log는 EVM과 동일하게 StateDb를 거쳐 ledger에 반영된다.
+/// gas_limit이 None이라면 GasCap까지만 실행한다.
+pub struct WasmRuntime<'a> {
+    ledger: &'a Ledger,
+    pub gas_limit: Option<u64>,
+    pub gas_used: u64,      // 마지막 실행에서 사용한 gas
+}
+
+impl<'a> WasmRuntime<'a> {
+    pub fn new(ledger: &'a Ledger) -> Self {
+        WasmRuntime { ledger, gas_limit: None, gas_used: 0 }
+    }
+
+    fn instantiate(&self, code: &Vec<u8>) -> Result<(ModuleRef, MemoryRef), RunError> {
+        let module = elements::deserialize_buffer::<elements::Module>(code).ok()
+            .and_then(|module| inject_gas(module).ok())
+            .and_then(|module| Module::from_parity_wasm_module(module).ok());
+        let module = match module {
+            Some(module) => { module }
+            None => { return Err(RunError::InvalidCode); }
+        };
+        let imports = ImportsBuilder::new().with_resolver(HOST_MODULE, &HostResolver);
+        let instance = match ModuleInstance::new(&module, &imports) {
+            Ok(instance) => { instance }
+            Err(_) => { return Err(RunError::InvalidCode); }
+        };
+        if instance.has_start() { return Err(RunError::InvalidCode); }
+        let instance = instance.assert_no_start();
+        let memory = instance.export_by_name("memory")
+            .and_then(|export| export.as_memory().cloned());
+        return match memory {
+            None => { Err(RunError::InvalidCode) }
+            Some(memory) => { Ok((instance, memory)) }
+        };
+    }
+
+    /// export된 함수를 실행한다. 실패한 실행의 storage 변경과 log는 state에 반영되지 않는다.
+    fn invoke(&mut self, contract: &Contract, export: &str, state: &mut StateDb, optional: bool)
+              -> (Option<Vec<u8>>, Option<RunError>) {
+        let (instance, memory) = match self.instantiate(&contract.code) {
+            Ok(result) => { result }
+            Err(err) => { return (None, Some(err)); }
+        };
+        self.gas_used = 0;
+        if optional && instance.export_by_name(export).is_none() { return (None, None); }
+
+        let mut scratch = state.clone();
+        let mut host = WasmHost {
+            ledger: self.ledger,
+            contract,
+            memory,
+            state: &mut scratch,
+            output: vec![],
+            gas_limit: Some(self.gas_limit.unwrap_or(GasCap)),
+            gas_used: 0,
+        };
+        let result = instance.invoke_export(export, &[], &mut host);
+        let output = std::mem::take(&mut host.output);
+        self.gas_used = host.gas_used;
+        return match result {
+            Ok(_) => {
+                *state = scratch;
+                (Some(output), None)
+            }
+            Err(wasmi::Error::Trap(trap)) => {
+                let host_trap = match trap.kind() {
+                    TrapKind::Host(err) => { err.downcast_ref::<HostTrap>() }
+                    _ => { None }
+                };
+                match host_trap {
+                    Some(HostTrap::Revert) => { (Some(output), Some(RunError::ExecutionReverted)) }
+                    Some(HostTrap::OutOfGas) => { (None, Some(RunError::OutOfGas)) }
+                    _ => { (None, Some(RunError::WasmTrap)) }
+                }
+            }
+            Err(_) => { (None, Some(RunError::InvalidCode)) }
+        };
+    }
+
+    /// 컨트랙트를 배포한다. `deploy` export가 있다면 실행하며, 저장될 코드는 모듈 자체이다.
+    pub fn deploy(&mut self, contract: &Contract, state: &mut StateDb) -> (Option<Vec<u8>>, Option<RunError>) {
+        let (_, err) = self.invoke(contract, DEPLOY_EXPORT, state, true);
+        if err.is_some() { return (None, err); }
+        (Some(contract.code.clone()), None)
+    }
+
+    /// 배포된 컨트랙트의 `call` export를 실행하고 `return_data`로 전달된 값을 반환한다.
+    pub fn call(&mut self, contract: &Contract, state: &mut StateDb) -> (Option<Vec<u8>>, Option<RunError>) {
+        self.invoke(contract, CALL_EXPORT, state, false)
+    }
+}